@@ -0,0 +1,53 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::model::channel::Message;
+use serenity::model::id::{MessageId, UserId};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedMessage {
+    pub author_id: UserId,
+    pub author_name: String,
+    pub timestamp: DateTime<Utc>,
+    pub content: String,
+}
+
+/// The text of a channel's story posts, kept so it can be exported. Only the in-character part
+/// of each message is kept, and messages with none are left out
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Archive {
+    messages: BTreeMap<MessageId, ArchivedMessage>,
+}
+
+impl Archive {
+    pub fn add(&mut self, message: &Message, content: &str) {
+        if content.trim().is_empty() {
+            return;
+        }
+        let archived_message = ArchivedMessage {
+            author_id: message.author.id,
+            author_name: message.author.name.clone(),
+            timestamp: message.timestamp,
+            content: String::from(content.trim()),
+        };
+        self.insert(message.id, archived_message);
+    }
+
+    pub fn insert(&mut self, message_id: MessageId, archived_message: ArchivedMessage) {
+        self.messages.insert(message_id, archived_message);
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Oldest first
+    pub fn messages(&self) -> impl Iterator<Item = (&MessageId, &ArchivedMessage)> {
+        self.messages.iter()
+    }
+}
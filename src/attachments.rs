@@ -0,0 +1,120 @@
+use crate::config::{AttachmentConfig, GeneralAppConfigData};
+use log::{error, info};
+use serenity::model::channel::{Attachment, Message};
+use serenity::prelude::Context;
+use std::borrow::Cow;
+use std::path::Path;
+
+const TEXT_EXTENSIONS: [&str; 2] = [".txt", ".md"];
+
+fn is_text_attachment(attachment: &Attachment, config: &AttachmentConfig) -> bool {
+    let filename = attachment.filename.to_lowercase();
+    attachment.size <= config.max_size_bytes
+        && TEXT_EXTENSIONS
+            .iter()
+            .any(|extension| filename.ends_with(extension))
+}
+
+// The message's own text followed by each attachment's, as if it had all been posted together
+fn append_texts(content: &str, texts: &[String]) -> String {
+    let mut parts: Vec<&str> = vec![content];
+    parts.extend(texts.iter().map(|text| text.trim()));
+    parts
+        .into_iter()
+        .filter(|part| !part.trim().is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n\n")
+}
+
+// Downloaded text is kept on disk by attachment id, so seeing the message again (replaying what
+// was missed, chapters, backfills) doesn't download it again
+async fn attachment_text(attachment: &Attachment, cache_path: &Path) -> Option<String> {
+    let cached_file = cache_path.join(format!("{}.txt", attachment.id));
+    if let Ok(text) = std::fs::read_to_string(&cached_file) {
+        return Some(text);
+    }
+    let bytes = match attachment.download().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed downloading attachment {}: {}", attachment.id, e);
+            return None;
+        }
+    };
+    let text = String::from_utf8_lossy(&bytes).into_owned();
+    info!(
+        "Downloaded {} ({} bytes)",
+        attachment.filename,
+        bytes.len()
+    );
+    let cached = std::fs::create_dir_all(cache_path)
+        .and_then(|_| std::fs::write(&cached_file, &text));
+    if let Err(e) = cached {
+        error!("Failed caching attachment {}: {}", attachment.id, e);
+    }
+    Some(text)
+}
+
+/// [message] with the text of any .txt or .md attachments (up to the configured size) added to
+/// its content, so they count like any other story text. Borrowed as is when there are none
+pub async fn with_attachment_text<'a>(ctx: &Context, message: &'a Message) -> Cow<'a, Message> {
+    let config = {
+        let config_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<GeneralAppConfigData>()
+                .expect("Expected GeneralAppConfigData in TypeMap.")
+                .clone()
+        };
+        let config = config_lock.read().unwrap();
+        config.attachment_config.clone()
+    };
+    let text_attachments: Vec<&Attachment> = message
+        .attachments
+        .iter()
+        .filter(|attachment| is_text_attachment(attachment, &config))
+        .collect();
+    if text_attachments.is_empty() {
+        return Cow::Borrowed(message);
+    }
+    let mut texts = vec![];
+    for attachment in text_attachments {
+        if let Some(text) = attachment_text(attachment, &config.cache_path).await {
+            texts.push(text);
+        }
+    }
+    let mut message = message.clone();
+    message.content = append_texts(&message.content, &texts);
+    Cow::Owned(message)
+}
+
+/// [with_attachment_text] for a batch of fetched messages
+pub async fn with_attachment_texts(ctx: &Context, messages: Vec<Message>) -> Vec<Message> {
+    let mut with_texts = Vec::with_capacity(messages.len());
+    for message in messages {
+        let with_text = match with_attachment_text(ctx, &message).await {
+            Cow::Owned(with_text) => with_text,
+            Cow::Borrowed(_) => message,
+        };
+        with_texts.push(with_text);
+    }
+    with_texts
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::attachments::append_texts;
+
+    #[test]
+    fn attachment_texts_follow_the_message() {
+        let texts = vec![
+            String::from("Chapter one.\n"),
+            String::from("  "),
+            String::from("Chapter two."),
+        ];
+        assert_eq!(
+            append_texts("Here's the next bit", &texts),
+            "Here's the next bit\n\nChapter one.\n\nChapter two."
+        );
+        assert_eq!(append_texts("", &texts[..1]), "Chapter one.");
+    }
+}
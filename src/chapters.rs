@@ -0,0 +1,217 @@
+use crate::stats::WordStats;
+use serde::{Deserialize, Serialize};
+use serenity::model::channel::Message;
+use serenity::model::id::MessageId;
+use serenity::model::user::User;
+use std::collections::HashMap;
+
+/// Part of a channel's story, from its first message up to (but not including) the next
+/// chapter's first message. Message ids only go up over time, so they double as positions
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Chapter {
+    pub title: String,
+    pub start: MessageId,
+    #[serde(default)]
+    pub general_stats: WordStats,
+    #[serde(default)]
+    pub author_stats: HashMap<User, WordStats>,
+}
+
+impl Chapter {
+    fn new(title: &str, start: MessageId) -> Self {
+        Self {
+            title: String::from(title),
+            start,
+            general_stats: WordStats::default(),
+            author_stats: HashMap::new(),
+        }
+    }
+
+    pub fn is_called(&self, title: &str) -> bool {
+        self.title.to_lowercase() == title.to_lowercase()
+    }
+
+    fn clear_stats(&mut self) {
+        self.general_stats = WordStats::default();
+        self.author_stats.clear();
+    }
+
+    fn update(&mut self, message: &Message, author: Option<&User>, content: &str) {
+        self.general_stats.update(message, content);
+        let author = match author {
+            Some(author) => author,
+            None => return,
+        };
+        match self.author_stats.get_mut(author) {
+            Some(word_stats) => word_stats.update(message, content),
+            None if !content.trim().is_empty() => {
+                let word_stats = WordStats::new_from_message(message, content);
+                self.author_stats.insert(author.clone(), word_stats);
+            }
+            None => {}
+        }
+    }
+}
+
+/// Messages from [from] onwards, and before [until] if there is one, that need to be seen again
+/// to fill in chapter stats
+pub type ReplaySpan = (MessageId, Option<MessageId>);
+
+/// A channel's chapters, kept in order of where they start. Anything before the first chapter
+/// isn't in any of them
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Chapters {
+    chapters: Vec<Chapter>,
+}
+
+impl Chapters {
+    pub fn iter(&self) -> impl Iterator<Item = &Chapter> {
+        self.chapters.iter()
+    }
+
+    pub fn get(&self, title: &str) -> Option<&Chapter> {
+        self.chapters
+            .iter()
+            .find(|chapter| chapter.is_called(title))
+    }
+
+    // Where the chapter after the one at [index] starts, [None] if it's the last one
+    fn next_start(&self, index: usize) -> Option<MessageId> {
+        self.chapters.get(index + 1).map(|chapter| chapter.start)
+    }
+
+    // The index of the chapter [message_id] is in
+    fn index_for(&self, message_id: MessageId) -> Option<usize> {
+        self.chapters
+            .iter()
+            .rposition(|chapter| chapter.start <= message_id)
+    }
+
+    /// Adds [message] to the stats of the chapter it's in, if any. [author] is who it counts
+    /// for, see [crate::proxies::CharacterOwners::credited_author]
+    pub fn update(&mut self, message: &Message, author: Option<&User>, content: &str) {
+        if let Some(index) = self.index_for(message.id) {
+            self.chapters[index].update(message, author, content);
+        }
+    }
+
+    /// Starts a new chapter at [start]. [seen_up_to] is the latest message already processed,
+    /// if that's in the new chapter then some of it has been counted in the wrong chapter (or
+    /// none at all), and the span that needs to be seen again is returned with its stats cleared
+    pub fn insert(
+        &mut self,
+        title: &str,
+        start: MessageId,
+        seen_up_to: Option<MessageId>,
+    ) -> Result<Option<ReplaySpan>, String> {
+        if self.get(title).is_some() {
+            return Err(format!("There's already a chapter called {}", title));
+        }
+        if let Some(chapter) = self.chapters.iter().find(|chapter| chapter.start == start) {
+            return Err(format!("{} already starts at that message", chapter.title));
+        }
+        let index = self
+            .chapters
+            .iter()
+            .position(|chapter| chapter.start > start)
+            .unwrap_or(self.chapters.len());
+        self.chapters.insert(index, Chapter::new(title, start));
+        let needs_replay = match seen_up_to {
+            Some(seen_up_to) => seen_up_to >= start,
+            None => false,
+        };
+        if !needs_replay {
+            return Ok(None);
+        }
+        // The chapter before used to run on into this one
+        let from = match index {
+            0 => start,
+            _ => {
+                self.chapters[index - 1].clear_stats();
+                self.chapters[index - 1].start
+            }
+        };
+        Ok(Some((from, self.next_start(index))))
+    }
+
+    /// Removes the chapter called [title], its messages become part of the chapter before. If
+    /// there is one, returns the span that needs to be seen again with its stats cleared
+    pub fn remove(&mut self, title: &str) -> Result<Option<ReplaySpan>, String> {
+        let index = self
+            .chapters
+            .iter()
+            .position(|chapter| chapter.is_called(title))
+            .ok_or_else(|| format!("There's no chapter called {}", title))?;
+        self.chapters.remove(index);
+        if index == 0 {
+            return Ok(None);
+        }
+        let previous = &mut self.chapters[index - 1];
+        previous.clear_stats();
+        let from = previous.start;
+        Ok(Some((from, self.next_start(index - 1))))
+    }
+
+    pub fn rename(&mut self, title: &str, new_title: &str) -> Result<(), String> {
+        if title.to_lowercase() != new_title.to_lowercase() && self.get(new_title).is_some() {
+            return Err(format!("There's already a chapter called {}", new_title));
+        }
+        match self
+            .chapters
+            .iter_mut()
+            .find(|chapter| chapter.is_called(title))
+        {
+            Some(chapter) => {
+                chapter.title = String::from(new_title);
+                Ok(())
+            }
+            None => Err(format!("There's no chapter called {}", title)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::chapters::Chapters;
+    use serenity::model::id::MessageId;
+
+    #[test]
+    fn inserting_chapters_keeps_them_in_order() {
+        let mut chapters = Chapters::default();
+        assert_eq!(chapters.insert("Two", MessageId(20), None), Ok(None));
+        assert_eq!(chapters.insert("One", MessageId(10), None), Ok(None));
+        assert_eq!(chapters.insert("Three", MessageId(30), None), Ok(None));
+        let titles: Vec<&str> = chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["One", "Two", "Three"]);
+        assert_eq!(chapters.index_for(MessageId(5)), None);
+        assert_eq!(chapters.index_for(MessageId(25)), Some(1));
+        assert_eq!(chapters.next_start(1), Some(MessageId(30)));
+        assert_eq!(chapters.next_start(2), None);
+        assert!(chapters.insert("one", MessageId(40), None).is_err());
+        assert!(chapters.insert("Four", MessageId(30), None).is_err());
+    }
+
+    #[test]
+    fn chapters_in_seen_history_need_replaying() {
+        let mut chapters = Chapters::default();
+        chapters.insert("One", MessageId(10), None).unwrap();
+        chapters.insert("Three", MessageId(30), None).unwrap();
+        // Splits One, so everything from its start to Three needs seeing again
+        assert_eq!(
+            chapters.insert("Two", MessageId(20), Some(MessageId(35))),
+            Ok(Some((MessageId(10), Some(MessageId(30)))))
+        );
+        // Nothing has been seen past where it starts
+        assert_eq!(
+            chapters.insert("Four", MessageId(50), Some(MessageId(35))),
+            Ok(None)
+        );
+        assert_eq!(
+            chapters.remove("three"),
+            Ok(Some((MessageId(20), Some(MessageId(50)))))
+        );
+        assert_eq!(chapters.remove("One"), Ok(None));
+        assert!(chapters.remove("One").is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use chrono::{Duration, NaiveDate};
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageEncoder};
+use plotters::prelude::*;
+use plotters::style::register_font;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 700;
+const FONT: &str = "sans-serif";
+
+static FONT_REGISTERED: AtomicBool = AtomicBool::new(false);
+
+/// Charts can't draw any text until a font has been loaded, this only does it the first time
+pub fn register_chart_font(font_path: &Path) -> Result<(), String> {
+    if FONT_REGISTERED.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let bytes = std::fs::read(font_path).map_err(|e| {
+        format!(
+            "Couldn't read the chart font at {}: {}",
+            font_path.display(),
+            e
+        )
+    })?;
+    // Fonts have to live forever once registered, and this only happens once
+    register_font(FONT, FontStyle::Normal, Box::leak(bytes.into_boxed_slice())).map_err(|_| {
+        format!(
+            "The chart font at {} isn't a font that can be used",
+            font_path.display()
+        )
+    })?;
+    FONT_REGISTERED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+// Draws into an RGB buffer with [draw], then turns that into a PNG
+fn render<F>(draw: F) -> Result<Vec<u8>, String>
+where
+    F: FnOnce(DrawingArea<BitMapBackend, plotters::coord::Shift>) -> Result<(), Box<dyn Error>>,
+{
+    let mut buffer = vec![0; (WIDTH * HEIGHT * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE).map_err(|e| e.to_string())?;
+        draw(root).map_err(|e| format!("Failed drawing chart: {}", e))?;
+    }
+    let mut png = vec![];
+    PngEncoder::new(&mut png)
+        .write_image(&buffer, WIDTH, HEIGHT, ColorType::Rgb8)
+        .map_err(|e| format!("Failed encoding chart: {}", e))?;
+    Ok(png)
+}
+
+fn format_day(first_day: NaiveDate, day: i32) -> String {
+    (first_day + Duration::days(day as i64))
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// A running total of [daily_words], starting from [starting_total] for anything older
+pub fn cumulative_words(
+    caption: &str,
+    starting_total: usize,
+    daily_words: &BTreeMap<NaiveDate, usize>,
+) -> Result<Vec<u8>, String> {
+    let first_day = match daily_words.keys().next() {
+        Some(first_day) => *first_day,
+        None => return Err(String::from("No words with dates have been recorded yet")),
+    };
+    let mut total = starting_total;
+    let points: Vec<(i32, usize)> = daily_words
+        .iter()
+        .map(|(day, words)| {
+            total += words;
+            ((*day - first_day).num_days() as i32, total)
+        })
+        .collect();
+    let last_day = points.last().map(|(day, _)| *day).unwrap_or(0).max(1);
+    render(|root| {
+        let mut chart = ChartBuilder::on(&root)
+            .caption(caption, (FONT, 28))
+            .margin(20)
+            .margin_right(60)
+            .x_label_area_size(40)
+            .y_label_area_size(90)
+            .build_cartesian_2d(0..last_day, 0..total.max(1))?;
+        chart
+            .configure_mesh()
+            .x_labels(8)
+            .x_label_formatter(&|day| format_day(first_day, *day))
+            .y_desc("Words")
+            .label_style((FONT, 16))
+            .draw()?;
+        chart.draw_series(LineSeries::new(points, BLUE.stroke_width(3)))?;
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// One line per author, [series] holds their words for each week in [week_starts]
+pub fn weekly_words(
+    caption: &str,
+    week_starts: &[NaiveDate],
+    series: &[(String, Vec<usize>)],
+) -> Result<Vec<u8>, String> {
+    if series.is_empty() || week_starts.is_empty() {
+        return Err(String::from("No words written in that time"));
+    }
+    let max_words = series
+        .iter()
+        .flat_map(|(_, counts)| counts.iter())
+        .max()
+        .cloned()
+        .unwrap_or(0);
+    let first_week = week_starts[0];
+    let last_week = (week_starts.len() as i32 - 1).max(1);
+    render(|root| {
+        let mut chart = ChartBuilder::on(&root)
+            .caption(caption, (FONT, 28))
+            .margin(20)
+            .margin_right(60)
+            .x_label_area_size(40)
+            .y_label_area_size(90)
+            .build_cartesian_2d(0..last_week, 0..max_words.max(1))?;
+        chart
+            .configure_mesh()
+            .x_labels(week_starts.len())
+            .x_label_formatter(&|week| format_day(first_week, week * 7))
+            .x_desc("Week starting")
+            .y_desc("Words")
+            .label_style((FONT, 16))
+            .draw()?;
+        for (i, (name, counts)) in series.iter().enumerate() {
+            let color = Palette99::pick(i).to_rgba();
+            chart
+                .draw_series(LineSeries::new(
+                    counts
+                        .iter()
+                        .enumerate()
+                        .map(|(week, words)| (week as i32, *words)),
+                    color.stroke_width(3),
+                ))?
+                .label(name.as_str())
+                .legend(move |(x, y)| {
+                    PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(3))
+                });
+        }
+        chart
+            .configure_series_labels()
+            .label_font((FONT, 16))
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// Moves each count by [offset_hours], wrapping around the week, e.g. to go from UTC to a
+/// server's time zone
+pub fn shift_hours(grid: &[[usize; 24]; 7], offset_hours: i64) -> [[usize; 24]; 7] {
+    let mut shifted = [[0; 24]; 7];
+    for (day, hours) in grid.iter().enumerate() {
+        for (hour, words) in hours.iter().enumerate() {
+            let shifted_hour = (day as i64 * 24 + hour as i64 + offset_hours).rem_euclid(7 * 24);
+            shifted[(shifted_hour / 24) as usize][(shifted_hour % 24) as usize] += words;
+        }
+    }
+    shifted
+}
+
+// White for nothing up to a deep orange for the busiest hour
+fn heat_color(intensity: f64) -> RGBColor {
+    RGBColor(
+        255,
+        (255.0 * (1.0 - 0.75 * intensity)) as u8,
+        (255.0 * (1.0 - intensity)) as u8,
+    )
+}
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Words written in each hour of each weekday, [grid] is Monday first
+pub fn weekday_hour_heatmap(caption: &str, grid: &[[usize; 24]; 7]) -> Result<Vec<u8>, String> {
+    let max_words = grid.iter().flat_map(|hours| hours.iter()).max().cloned();
+    let max_words = match max_words {
+        Some(max_words) if max_words > 0 => max_words,
+        _ => return Err(String::from("No words with times have been recorded yet")),
+    };
+    render(|root| {
+        let mut chart = ChartBuilder::on(&root)
+            .caption(caption, (FONT, 28))
+            .margin(20)
+            .margin_right(60)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            // Segmented ranges include their end, so these are 24 hours and 7 days
+            .build_cartesian_2d((0..23).into_segmented(), (0..6).into_segmented())?;
+        let label = |value: &SegmentValue<i32>| match value {
+            SegmentValue::CenterOf(value) | SegmentValue::Exact(value) => *value,
+            SegmentValue::Last => 0,
+        };
+        chart
+            .configure_mesh()
+            .disable_mesh()
+            .x_labels(24)
+            .y_labels(7)
+            .x_label_formatter(&|hour| format!("{:02}", label(hour)))
+            // Monday at the top
+            .y_label_formatter(&|row| String::from(WEEKDAYS[(6 - label(row)).clamp(0, 6) as usize]))
+            .x_desc("Hour")
+            .label_style((FONT, 16))
+            .draw()?;
+        chart.draw_series(grid.iter().enumerate().flat_map(|(day, hours)| {
+            let row = 6 - day as i32;
+            hours.iter().enumerate().map(move |(hour, words)| {
+                let hour = hour as i32;
+                Rectangle::new(
+                    [
+                        (SegmentValue::Exact(hour), SegmentValue::Exact(row)),
+                        (SegmentValue::Exact(hour + 1), SegmentValue::Exact(row + 1)),
+                    ],
+                    heat_color(*words as f64 / max_words as f64).filled(),
+                )
+            })
+        }))?;
+        root.present()?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::charts::shift_hours;
+
+    #[test]
+    fn shifting_hours_wraps_around_the_week() {
+        let mut grid = [[0; 24]; 7];
+        grid[0][1] = 5; // Monday 01:00
+        grid[6][23] = 3; // Sunday 23:00
+        let shifted = shift_hours(&grid, -2);
+        assert_eq!(shifted[6][23], 5);
+        assert_eq!(shifted[6][21], 3);
+        let shifted = shift_hours(&grid, 2);
+        assert_eq!(shifted[0][3], 5);
+        assert_eq!(shifted[0][1], 3);
+    }
+}
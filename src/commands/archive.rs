@@ -0,0 +1,393 @@
+use crate::attachments::with_attachment_texts;
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::config::GeneralAppConfigData;
+use crate::manuscript::{AuthorStyle, ExportFormat, Manuscript};
+use crate::state::{ChannelData, StoreData, StoryKey};
+use chrono::Duration;
+use log::info;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::http::AttachmentType;
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::borrow::Cow;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+// Discord won't take attachments bigger than this without a boosted server
+const MAX_ATTACHMENT_BYTES: usize = 8 * 1024 * 1024;
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum ArchiveAction {
+    Show,
+    On,
+    Off,
+}
+impl ArchiveAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::On => "on",
+            Self::Off => "off",
+        }
+    }
+}
+impl FromStr for ArchiveAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid archive action {}", s)),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|format| format.to_str() == s) {
+            Some(format) => Ok(format),
+            None => Err(format!("Invalid export format {}", s)),
+        }
+    }
+}
+
+impl FromStr for AuthorStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|style| style.to_str() == s) {
+            Some(style) => Ok(style),
+            None => Err(format!("Invalid author style {}", s)),
+        }
+    }
+}
+
+/// Goes through the channel's history newest first, passing each story post to [add] along with
+/// the channel's data. Used to fill in things that weren't kept from the start, returns how many
+/// messages were looked at
+pub async fn backfill_story_posts(
+    ctx: &Context,
+    story_key: &StoryKey,
+    add: fn(&mut ChannelData, &Message),
+) -> std::result::Result<usize, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let prefix = {
+        let config_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<GeneralAppConfigData>()
+                .expect("Expected GeneralAppConfigData in TypeMap.")
+                .clone()
+        };
+        let config = config_lock.read().unwrap();
+        config.prefix.clone()
+    };
+    let channel_id = story_key.1;
+    let mut before: Option<MessageId> = None;
+    let mut fetched_messages = 0;
+    loop {
+        let messages = channel_id
+            .messages(&ctx.http, |get_messages_builder| match before {
+                Some(before) => get_messages_builder.before(before).limit(100),
+                None => get_messages_builder.limit(100),
+            })
+            .await
+            .map_err(|e| format!("Failed fetching messages: {}", e))?;
+        if messages.is_empty() {
+            break;
+        }
+        fetched_messages += messages.len();
+        before = messages.iter().map(|message| message.id).min();
+        let messages = with_attachment_texts(ctx, messages).await;
+        {
+            let mut store = store_lock.write().unwrap();
+            match store.get_channel_data_mut(story_key) {
+                Some(channel_data) => {
+                    // Only story posts (including proxied ones), like the ones seen as they come in
+                    let story_posts = messages.iter().filter(|message| {
+                        let from_bot = message.author.bot && message.webhook_id.is_none();
+                        !from_bot && !message.content.starts_with(&prefix)
+                    });
+                    for message in story_posts {
+                        add(channel_data, message);
+                    }
+                }
+                None => {
+                    return Err(String::from(
+                        "The channel was removed while going through its history",
+                    ))
+                }
+            }
+        }
+        info!(
+            "Went through {} messages so far in {}...",
+            fetched_messages, channel_id
+        );
+    }
+    Ok(fetched_messages)
+}
+
+async fn update_archive(
+    ctx: &Context,
+    msg: &Message,
+    story_key: &StoryKey,
+    action: &ArchiveAction,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let already_archiving = {
+        let mut store = store_lock.write().unwrap();
+        let channel_data = match store.get_channel_data_mut(story_key) {
+            Some(channel_data) => channel_data,
+            None => {
+                return Err(String::from(
+                    "Channel not initialised, use [init-channel] to add it",
+                ))
+            }
+        };
+        match action {
+            ArchiveAction::Show => {
+                let mut builder = MessageBuilder::new();
+                builder.push("Archiving in ").channel(story_key.1);
+                return Ok(match channel_data.archive() {
+                    Some(archive) => builder
+                        .push(format!(" is on, with {} posts kept", archive.len()))
+                        .build(),
+                    None => builder.push(" is off").build(),
+                });
+            }
+            ArchiveAction::Off => {
+                return Ok(match channel_data.stop_archiving() {
+                    Some(removed) => format!(
+                        "Stopped archiving and deleted the {} posts that were kept, they can't be searched any more",
+                        removed
+                    ),
+                    None => String::from("Archiving was already off"),
+                });
+            }
+            ArchiveAction::On => channel_data.start_archiving(),
+        }
+    };
+    if already_archiving {
+        return Ok(String::from("Archiving was already on"));
+    }
+    // Fetching history can take a while, so show it's being worked on
+    let _ = msg
+        .react(ctx, ReactionType::Unicode(String::from("⌚")))
+        .await;
+    let fetched_messages =
+        backfill_story_posts(ctx, story_key, ChannelData::archive_message).await?;
+    Ok(format!(
+        "Started archiving, and looked through {} earlier messages for story posts",
+        fetched_messages
+    ))
+}
+
+#[command("archive")]
+#[usage("<#channel name> <action>")]
+#[description("Keep the text of a channel's story posts, so it can be compiled into a manuscript with [export]. Only in-character text is kept. Archived channels can be searched with [search]. Turning it on also archives the channel's history, turning it off deletes everything kept, the search index included. Changes need the MasterScrivener role. Actions: show|on|off")]
+#[example("#the-fall-of-rome on")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn archive(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        let maybe_channel_id = args.single::<ChannelId>();
+        let maybe_action = args.single::<ArchiveAction>();
+        match (maybe_channel_id, maybe_action) {
+            (Ok(channel_id), Ok(action)) => {
+                let story_key: StoryKey = (server_id, channel_id);
+                let allowed = action == ArchiveAction::Show
+                    || author_is_in_allowed_roles(ctx, &server_id, &msg.author).await;
+                if allowed {
+                    match update_archive(ctx, msg, &story_key, &action).await {
+                        Ok(reply) => reply,
+                        Err(e) => format!(
+                            "Error with command arguments, try [help archive]\nError:{}",
+                            e
+                        ),
+                    }
+                } else {
+                    format!(
+                        "Changing archiving is only available to those with the role {}",
+                        ALLOWED_ROLES[0]
+                    )
+                }
+            }
+            (Err(e), _) => format!(
+                "Error with command arguments, try [help archive]\nError:{}",
+                e
+            ),
+            (_, Err(e)) => format!(
+                "Error with command arguments, try [help archive]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+async fn make_export(
+    ctx: &Context,
+    story_key: &StoryKey,
+    format: ExportFormat,
+    author_style: AuthorStyle,
+) -> std::result::Result<(String, Vec<u8>), String> {
+    let channel_name = story_key
+        .1
+        .name(&ctx.cache)
+        .await
+        .unwrap_or_else(|| String::from("story"));
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let channel_data = match store.get_channel_data(story_key) {
+        Some(channel_data) => channel_data,
+        None => {
+            return Err(String::from(
+                "Channel not initialised, use [init-channel] to add it",
+            ))
+        }
+    };
+    let archive = match channel_data.archive() {
+        Some(archive) if !archive.is_empty() => archive,
+        Some(_) => return Err(String::from("Nothing has been archived there yet")),
+        None => {
+            return Err(String::from(
+                "The channel isn't archived, use [archive on] to start",
+            ))
+        }
+    };
+    let chapter_starts: Vec<(MessageId, String)> = channel_data
+        .chapters
+        .iter()
+        .map(|chapter| (chapter.start, chapter.title.clone()))
+        .collect();
+    let post_window = match store.get_server_data(&story_key.0) {
+        Some(server_data) => server_data.settings.post_window(),
+        None => Duration::zero(),
+    };
+    let manuscript = Manuscript::new(
+        &channel_name,
+        archive,
+        &chapter_starts,
+        author_style,
+        post_window,
+    );
+    let bytes = match format {
+        ExportFormat::Markdown => manuscript.to_markdown().into_bytes(),
+        ExportFormat::Html => manuscript.to_html().into_bytes(),
+        ExportFormat::Epub => manuscript.to_epub()?,
+    };
+    if bytes.len() > MAX_ATTACHMENT_BYTES {
+        return Err(String::from(
+            "The manuscript is too big to attach, try epub which is compressed",
+        ));
+    }
+    Ok((format!("{}.{}", channel_name, format.extension()), bytes))
+}
+
+#[command("export")]
+#[usage("<#channel name> [<format>] [<author style>]")]
+#[description("Compile an archived channel into a manuscript, with a section for each chapter, and attach it. [attribution] puts the author's name in front of each post, [headings] adds one whenever a different author takes over, and [anonymous] leaves names out. Defaults to markdown with attribution. Formats: markdown|html|epub. Author styles: attribution|headings|anonymous")]
+#[example("#the-fall-of-rome")]
+#[example("#the-fall-of-rome epub headings")]
+#[only_in("guilds")] // Reminder: guild = server
+#[bucket("global-wordcloud-bucket")]
+async fn export(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        let maybe_channel_id = args.single::<ChannelId>();
+        let mut format = ExportFormat::Markdown;
+        let mut author_style = AuthorStyle::Attribution;
+        let mut parse_error = None;
+        // Format and style can come in either order
+        while !args.is_empty() && parse_error.is_none() {
+            if let Ok(parsed) = args.single::<ExportFormat>() {
+                format = parsed;
+            } else if let Ok(parsed) = args.single::<AuthorStyle>() {
+                author_style = parsed;
+            } else {
+                parse_error = Some(format!("Didn't understand {}", args.rest()));
+            }
+        }
+        match (maybe_channel_id, parse_error) {
+            (Ok(channel_id), None) => {
+                let story_key: StoryKey = (server_id, channel_id);
+                match make_export(ctx, &story_key, format, author_style).await {
+                    Ok((filename, bytes)) => {
+                        let files = vec![AttachmentType::Bytes {
+                            data: Cow::from(bytes),
+                            filename,
+                        }];
+                        msg.channel_id
+                            .send_files(&ctx.http, files, |create_message| {
+                                create_message.reference_message(msg)
+                            })
+                            .await?;
+                        None
+                    }
+                    Err(e) => Some(e),
+                }
+            }
+            (Err(e), _) => Some(format!(
+                "Error with command arguments, try [help export]\nError:{}",
+                e
+            )),
+            (_, Some(e)) => Some(format!(
+                "Error with command arguments, try [help export]\nError:{}",
+                e
+            )),
+        }
+    } else {
+        Some(String::from(
+            "BUG: message had no server id, bot only supports server text channels",
+        ))
+    };
+    if let Some(reply) = reply {
+        msg.reply(ctx, reply).await?;
+    }
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = ARCHIVE_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = ArchiveAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
+
+#[test]
+fn usage_matches_all_formats_and_styles() {
+    let desc: &str = EXPORT_COMMAND_OPTIONS.desc.unwrap();
+    let formats: Vec<&str> = ExportFormat::iter().map(|format| format.to_str()).collect();
+    let styles: Vec<&str> = AuthorStyle::iter().map(|style| style.to_str()).collect();
+    assert!(desc.contains(&format!("Formats: {}.", formats.join("|"))));
+    assert!(desc.ends_with(&format!("Author styles: {}", styles.join("|"))));
+}
@@ -0,0 +1,362 @@
+use crate::attachments::with_attachment_texts;
+use crate::chapters::ReplaySpan;
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::commands::show_stats::{get_stats, get_truncate_limit};
+use crate::commands::word_cloud::{
+    react_or_reply, request_and_fetch_wordcloud, wordcloud_is_enabled,
+};
+use crate::proxies::CharacterOwners;
+use crate::state::{StoreData, StoryKey};
+use crate::stories::StatsTarget;
+use crate::utils::helpers::message_link;
+use log::info;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum ChapterAction {
+    List,
+    Start,
+    Remove,
+    Rename,
+    Stats,
+    Wordcloud,
+}
+impl ChapterAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::List => "list",
+            Self::Start => "start",
+            Self::Remove => "remove",
+            Self::Rename => "rename",
+            Self::Stats => "stats",
+            Self::Wordcloud => "wordcloud",
+        }
+    }
+
+    fn changes_chapters(&self) -> bool {
+        matches!(self, Self::Start | Self::Remove | Self::Rename)
+    }
+}
+impl FromStr for ChapterAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid chapter action {}", s)),
+        }
+    }
+}
+
+fn chapter_title(args: &mut Args) -> std::result::Result<String, String> {
+    args.single_quoted::<String>()
+        .map_err(|_| String::from("Expected a chapter title"))
+}
+
+// A message link like the ones from [Copy Message Link], or just the id
+fn parse_message_id(s: &str) -> Option<MessageId> {
+    s.rsplit('/').next()?.parse::<u64>().ok().map(MessageId)
+}
+
+async fn list_chapters(ctx: &Context, story_key: &StoryKey) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let channel_data = match store.get_channel_data(story_key) {
+        Some(channel_data) => channel_data,
+        None => return String::from("Channel not initialised, use [init-channel] to add it"),
+    };
+    let mut builder = MessageBuilder::new();
+    builder.push("Chapters in ").channel(story_key.1).push("\n");
+    let mut any_chapters = false;
+    for (i, chapter) in channel_data.chapters.iter().enumerate() {
+        any_chapters = true;
+        builder
+            .push(format!("{}. ", i + 1))
+            .push_bold_safe(&chapter.title)
+            .push(format!(
+                " -> {} words by {} author(s), starting at <{}>\n",
+                chapter.general_stats.word_count,
+                chapter.author_stats.len(),
+                message_link(story_key.0, story_key.1, chapter.start)
+            ));
+    }
+    if !any_chapters {
+        builder.push("None yet, use [chapter start] to begin one");
+    }
+    builder.build()
+}
+
+// Sees every message in [span] again to fill in the chapter stats that were cleared for it
+async fn replay_chapters(
+    ctx: &Context,
+    story_key: &StoryKey,
+    (from, until): ReplaySpan,
+) -> std::result::Result<usize, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let channel_id = story_key.1;
+    // [after] skips the message itself, so it's fetched on its own first. It might have been
+    // deleted since, which is fine
+    let start: Vec<Message> = channel_id
+        .message(&ctx.http, from)
+        .await
+        .into_iter()
+        .collect();
+    let mut messages = with_attachment_texts(ctx, start).await;
+    let mut after = from;
+    let mut replayed = 0;
+    loop {
+        let batch = channel_id
+            .messages(&ctx.http, |get_messages_builder| {
+                get_messages_builder.after(after).limit(100)
+            })
+            .await
+            .map_err(|e| format!("Failed fetching messages: {}", e))?;
+        let reached_end = batch.is_empty();
+        if let Some(latest) = batch.iter().map(|message| message.id).max() {
+            after = latest;
+        }
+        messages.extend(with_attachment_texts(ctx, batch).await);
+        let reached_until = match until {
+            Some(until) => after >= until,
+            None => false,
+        };
+        {
+            let mut store = store_lock.write().unwrap();
+            let owners = match store.get_server_data(&story_key.0) {
+                Some(server_data) => server_data.character_owners.clone(),
+                None => CharacterOwners::default(),
+            };
+            let channel_data = match store.get_channel_data_mut(story_key) {
+                Some(channel_data) => channel_data,
+                None => return Err(String::from("The channel was removed while replaying")),
+            };
+            for message in messages.drain(..) {
+                let before_until = match until {
+                    Some(until) => message.id < until,
+                    None => true,
+                };
+                if before_until {
+                    channel_data.update_chapters(&message, &owners);
+                    replayed += 1;
+                }
+            }
+        }
+        if reached_end || reached_until {
+            break;
+        }
+        info!(
+            "Replayed {} messages into chapters so far in {}...",
+            replayed, channel_id
+        );
+    }
+    Ok(replayed)
+}
+
+async fn change_chapters(
+    ctx: &Context,
+    msg: &Message,
+    story_key: &StoryKey,
+    action: &ChapterAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let title = chapter_title(args)?;
+    let (reply, replay_span) = {
+        let mut store = store_lock.write().unwrap();
+        let channel_data = match store.get_channel_data_mut(story_key) {
+            Some(channel_data) => channel_data,
+            None => {
+                return Err(String::from(
+                    "Channel not initialised, use [init-channel] to add it",
+                ))
+            }
+        };
+        match action {
+            ChapterAction::Start => {
+                // Replying to a message starts the chapter there, as does giving a link to one,
+                // otherwise it starts from here on
+                let start = match (&msg.referenced_message, args.single::<String>()) {
+                    (_, Ok(arg)) => parse_message_id(&arg)
+                        .ok_or_else(|| format!("Expected a message link or id, got {}", arg))?,
+                    (Some(referenced_message), _) => referenced_message.id,
+                    (None, _) => msg.id,
+                };
+                let replay_span = channel_data.start_chapter(&title, start)?;
+                (format!("Started the chapter {}", title), replay_span)
+            }
+            ChapterAction::Remove => {
+                let replay_span = channel_data.chapters.remove(&title)?;
+                (format!("Removed the chapter {}", title), replay_span)
+            }
+            ChapterAction::Rename => {
+                let new_title = chapter_title(args)?;
+                channel_data.chapters.rename(&title, &new_title)?;
+                (format!("Renamed {} to {}", title, new_title), None)
+            }
+            _ => return Err(format!("{} doesn't change chapters", action.to_str())),
+        }
+    };
+    match replay_span {
+        Some(replay_span) => {
+            // Fetching history can take a while, so show it's being worked on
+            let _ = msg
+                .react(ctx, ReactionType::Unicode(String::from("⌚")))
+                .await;
+            let replayed = replay_chapters(ctx, story_key, replay_span).await?;
+            Ok(format!(
+                "{}, and sorted {} earlier messages into chapters",
+                reply, replayed
+            ))
+        }
+        None => Ok(reply),
+    }
+}
+
+async fn chapter_wordcloud(
+    ctx: &Context,
+    msg: &Message,
+    story_key: &StoryKey,
+    args: &mut Args,
+) -> Option<String> {
+    if !wordcloud_is_enabled(ctx).await {
+        return Some(String::from(
+            "Wordclouds are not enabled, sorry - Speak to your bot admin",
+        ));
+    }
+    let title = match chapter_title(args) {
+        Ok(title) => title,
+        Err(e) => return Some(e),
+    };
+    let user_id = match args.is_empty() {
+        true => None,
+        false => match args.single::<UserId>() {
+            Ok(user_id) => Some(user_id),
+            Err(e) => return Some(e.to_string()),
+        },
+    };
+    react_or_reply(msg, ctx).await;
+    request_and_fetch_wordcloud(
+        &story_key.0,
+        &StatsTarget::Chapter(story_key.1, title),
+        ctx,
+        &msg.channel_id,
+        &user_id,
+        &None,
+        false,
+    )
+    .await
+}
+
+#[command("chapter")]
+#[usage("<action> [<#channel name>] [<chapter title>] [<new title> | <message link> | -full | <@user mention>]")]
+#[description("Mark where chapters start in a story channel, this one if none is given, and see stats or a wordcloud for each. [start] begins a chapter from here, from the message replied to, or from a message link, and older messages are sorted into it. A chapter runs until the next one starts. Put titles with spaces in quotes. Changes need the MasterScrivener role. Actions: list|start|remove|rename|stats|wordcloud")]
+#[example("start \"Chapter 3\"")]
+#[example("start #the-fall-of-rome \"Chapter 1\" https://discord.com/channels/1/2/3")]
+#[example("stats \"Chapter 3\" -full")]
+#[example("wordcloud \"Chapter 3\" @Caligula")]
+#[example("rename \"Chapter 3\" \"The Ides of March\"")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn chapter(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<ChapterAction>() {
+            Ok(action) => {
+                let channel_id = args.single::<ChannelId>().unwrap_or(msg.channel_id);
+                let story_key: StoryKey = (server_id, channel_id);
+                let allowed = !action.changes_chapters()
+                    || author_is_in_allowed_roles(ctx, &server_id, &msg.author).await;
+                if !allowed {
+                    Some(format!(
+                        "Changing chapters is only available to those with the role {}",
+                        ALLOWED_ROLES[0]
+                    ))
+                } else {
+                    match action {
+                        ChapterAction::List => Some(list_chapters(ctx, &story_key).await),
+                        ChapterAction::Stats => match chapter_title(&mut args) {
+                            Ok(title) => {
+                                let truncate_limit = get_truncate_limit(&mut args);
+                                let target = StatsTarget::Chapter(channel_id, title);
+                                let response =
+                                    get_stats(server_id, &target, ctx, truncate_limit).await;
+                                msg.channel_id.say(&ctx.http, &response).await?;
+                                None
+                            }
+                            Err(e) => Some(e),
+                        },
+                        ChapterAction::Wordcloud => {
+                            chapter_wordcloud(ctx, msg, &story_key, &mut args).await
+                        }
+                        _ => {
+                            match change_chapters(ctx, msg, &story_key, &action, &mut args).await {
+                                Ok(reply) => Some(reply),
+                                Err(e) => Some(format!(
+                                    "Error with command arguments, try [help chapter]\nError:{}",
+                                    e
+                                )),
+                            }
+                        }
+                    }
+                }
+            }
+            Err(e) => Some(format!(
+                "Error with command arguments, try [help chapter]\nError:{}",
+                e
+            )),
+        }
+    } else {
+        Some(String::from(
+            "BUG: message had no server id, bot only supports server text channels",
+        ))
+    };
+    if let Some(reply) = reply {
+        msg.reply(ctx, reply).await?;
+    }
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = CHAPTER_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = ChapterAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
+
+#[test]
+fn message_ids_come_from_links_or_ids() {
+    assert_eq!(
+        parse_message_id("https://discord.com/channels/1/2/345"),
+        Some(MessageId(345))
+    );
+    assert_eq!(parse_message_id("345"), Some(MessageId(345)));
+    assert_eq!(parse_message_id("chapter"), None);
+}
@@ -0,0 +1,370 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::config::GeneralAppConfigData;
+use crate::proxies::{look_up_owners, roll_up, PluralKit};
+use crate::state::{ChannelData, ServerData, StoreData};
+use crate::stories::StatsTarget;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use log::error;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum CharacterAction {
+    List,
+    Claim,
+    Unclaim,
+    Lookup,
+    Stats,
+}
+impl CharacterAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::List => "list",
+            Self::Claim => "claim",
+            Self::Unclaim => "unclaim",
+            Self::Lookup => "lookup",
+            Self::Stats => "stats",
+        }
+    }
+}
+impl FromStr for CharacterAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid character action {}", s)),
+        }
+    }
+}
+
+fn character_name(args: &mut Args) -> std::result::Result<String, String> {
+    args.single_quoted::<String>()
+        .map_err(|_| String::from("Expected a character name"))
+}
+
+fn describe_owners(server_data: &ServerData) -> String {
+    if server_data.character_owners.is_empty() {
+        return String::from("Nobody has claimed any characters yet, use [character claim]");
+    }
+    let mut owners: Vec<(&String, &UserId)> = server_data.character_owners.iter().collect();
+    owners.sort_by_key(|(character, _)| character.to_lowercase());
+    let mut builder = MessageBuilder::new();
+    for (character, owner) in owners {
+        builder
+            .push_bold_safe(character)
+            .push(" is played by ")
+            .mention(owner)
+            .newline();
+    }
+    builder.build()
+}
+
+fn describe_characters(title: &str, server_data: &ServerData, data: &ChannelData) -> String {
+    if data.character_stats.is_empty() {
+        return format!("No characters have spoken in {} yet", title);
+    }
+    let (players, unclaimed) = roll_up(&data.character_stats, &server_data.character_owners);
+    let mut builder = MessageBuilder::new();
+    builder.push("Characters in ").push(title).newline();
+    for player in players {
+        let characters = player
+            .characters
+            .iter()
+            .map(|(character, words)| format!("{} {}", character, words))
+            .collect::<Vec<String>>()
+            .join(", ");
+        builder
+            .newline()
+            .mention(&player.owner)
+            .push_line(format!(": {} words", player.word_count))
+            .push_line_safe(characters);
+    }
+    if !unclaimed.is_empty() {
+        let characters = unclaimed
+            .iter()
+            .map(|(character, words)| format!("{} {}", character, words))
+            .collect::<Vec<String>>()
+            .join(", ");
+        builder
+            .newline()
+            .push_bold_line("Unclaimed, see [character claim]")
+            .push_line_safe(characters);
+    }
+    builder.build()
+}
+
+fn character_stats(server_data: &ServerData, target: &StatsTarget) -> String {
+    match target {
+        StatsTarget::Channel(channel_id) => match server_data.get_channel_data(channel_id) {
+            Some(channel_data) => describe_characters(
+                &MessageBuilder::new().channel(channel_id).build(),
+                server_data,
+                channel_data,
+            ),
+            None => String::from("Channel not initialised, use [init-channel] to add it"),
+        },
+        StatsTarget::Story(name) => match server_data.story_data(name) {
+            Some(story_data) => describe_characters(
+                &MessageBuilder::new()
+                    .push("the story ")
+                    .push_bold_safe(name)
+                    .build(),
+                server_data,
+                &story_data,
+            ),
+            None => format!("There's no story called {}, see [story list]", name),
+        },
+        StatsTarget::Chapter(..) => {
+            String::from("Characters are only counted for channels and stories")
+        }
+    }
+}
+
+// Makes [owner] the player of [character]. [can_manage] is whether the author can change claims
+// that aren't their own
+fn claim(
+    server_data: &mut ServerData,
+    character: &str,
+    owner: &User,
+    author: UserId,
+    can_manage: bool,
+) -> std::result::Result<String, String> {
+    let previous = server_data.character_owners.owner(character);
+    let taking_over = matches!(previous, Some(previous) if previous != owner.id);
+    if !can_manage && (owner.id != author || taking_over) {
+        return Err(format!(
+            "Claiming characters for someone else, or that someone else has claimed, is only available to those with the role {}",
+            ALLOWED_ROLES[0]
+        ));
+    }
+    server_data.claim_character(character, owner);
+    Ok(MessageBuilder::new()
+        .push_bold_safe(character)
+        .push(" is now played by ")
+        .mention(owner)
+        .build())
+}
+
+// The character and who's claiming them, the author unless someone else is named
+async fn claim_args(
+    ctx: &Context,
+    msg: &Message,
+    args: &mut Args,
+) -> std::result::Result<(String, User), String> {
+    let character = character_name(args)?;
+    let owner = match args.is_empty() {
+        true => msg.author.clone(),
+        false => args
+            .single::<UserId>()
+            .map_err(|e| e.to_string())?
+            .to_user(ctx)
+            .await
+            .map_err(|e| e.to_string())?,
+    };
+    Ok((character, owner))
+}
+
+// Applies an action that only needs the store, returning the reply on success. [can_manage]
+// is whether the author can change claims that aren't their own
+fn apply_action(
+    server_data: &mut ServerData,
+    action: &CharacterAction,
+    author: UserId,
+    can_manage: bool,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    match action {
+        CharacterAction::List => Ok(describe_owners(server_data)),
+        CharacterAction::Unclaim => {
+            let character = character_name(args)?;
+            match server_data.character_owners.owner(&character) {
+                Some(owner) if owner == author || can_manage => {
+                    server_data.character_owners.unclaim(&character);
+                    Ok(format!("Nobody plays {} any more", character))
+                }
+                Some(_) => Err(format!(
+                    "Only whoever plays {} or those with the role {} can unclaim them",
+                    character, ALLOWED_ROLES[0]
+                )),
+                None => Err(format!("Nobody has claimed {}", character)),
+            }
+        }
+        CharacterAction::Stats => {
+            let target = StatsTarget::single(args)?;
+            Ok(character_stats(server_data, &target))
+        }
+        CharacterAction::Claim => Err(String::from("BUG: claims need the user fetched first")),
+        CharacterAction::Lookup => Err(String::from("BUG: lookups need the proxy bot")),
+    }
+}
+
+async fn update_characters(
+    ctx: &Context,
+    msg: &Message,
+    server_id: &GuildId,
+    action: &CharacterAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let can_manage = author_is_in_allowed_roles(ctx, server_id, &msg.author).await;
+    // Fetched before the store is locked, since it can't be held across an await
+    let claiming = match action {
+        CharacterAction::Claim => Some(claim_args(ctx, msg, args).await?),
+        _ => None,
+    };
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    match claiming {
+        Some((character, owner)) => {
+            claim(server_data, &character, &owner, msg.author.id, can_manage)
+        }
+        None => apply_action(server_data, action, msg.author.id, can_manage, args),
+    }
+}
+
+/// Asks PluralKit who plays each of [characters] (name, a message they sent), claiming them for
+/// whoever it says sent it unless someone has claimed them since. Returns the ones it knew
+pub async fn claim_from_pluralkit(
+    ctx: &Context,
+    server_id: &GuildId,
+    characters: Vec<(String, MessageId)>,
+) -> std::result::Result<Vec<(String, UserId)>, String> {
+    let url = {
+        let config_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<GeneralAppConfigData>()
+                .expect("Expected GeneralAppConfigData in TypeMap.")
+                .clone()
+        };
+        let config = config_lock.read().unwrap();
+        config.proxy_config.pluralkit_url.clone()
+    };
+    let url = url.ok_or_else(|| {
+        String::from(
+            "PluralKit lookups aren't set up, characters have to be claimed with [character claim]",
+        )
+    })?;
+    let mut found = vec![];
+    for (character, owner) in look_up_owners(&PluralKit { url }, characters).await {
+        match owner.to_user(ctx).await {
+            Ok(owner) => found.push((character, owner)),
+            Err(e) => error!("Failed fetching {}, who plays {}: {}", owner, character, e),
+        }
+    }
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    let mut claimed = vec![];
+    for (character, owner) in found {
+        if server_data.character_owners.owner(&character).is_none() {
+            server_data.claim_character(&character, &owner);
+            claimed.push((character, owner.id));
+        }
+    }
+    Ok(claimed)
+}
+
+async fn look_up_characters(
+    ctx: &Context,
+    server_id: &GuildId,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let unclaimed = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoreData in TypeMap.")
+                .clone()
+        };
+        let store = store_lock.read().unwrap();
+        let server_data = store
+            .get_server_data(server_id)
+            .ok_or("No channels have been initialised on this server")?;
+        let channel_ids = match args.is_empty() {
+            true => server_data.get_all_channel_ids(),
+            false => vec![args.single::<ChannelId>().map_err(|e| e.to_string())?],
+        };
+        server_data.unclaimed_characters(&channel_ids)
+    };
+    if unclaimed.is_empty() {
+        return Ok(String::from("Every character has been claimed already"));
+    }
+    let asked = unclaimed.len();
+    let claimed = claim_from_pluralkit(ctx, server_id, unclaimed).await?;
+    let mut builder = MessageBuilder::new();
+    builder.push_line(format!(
+        "PluralKit knew {} of the {} unclaimed characters",
+        claimed.len(),
+        asked
+    ));
+    for (character, owner) in claimed {
+        builder
+            .push_bold_safe(&character)
+            .push(" is played by ")
+            .mention(&owner)
+            .newline();
+    }
+    Ok(builder.build())
+}
+
+#[command("character")]
+#[usage("<action> [<character name> [<@user>] | <#channel name | story name>]")]
+#[description("Link characters posted through proxy bots (Tupperbox, PluralKit...) to whoever plays them. Posts from proxy bots are counted per character, [stats] shows them rolled up under their players. They also count for the player everywhere else (goals, leaderboards, show-stats...), including posts from before the character was claimed. Claim characters for yourself, claiming for others or taking over someone else's needs the MasterScrivener role. [lookup] asks PluralKit about unclaimed characters, in one channel or all of them. Put names with spaces in quotes. Actions: list|claim|unclaim|lookup|stats")]
+#[example("claim \"Aria Blackwood\"")]
+#[example("claim Bex @user")]
+#[example("stats #the-fall-of-rome")]
+#[example("lookup")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn character(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        let result = match args.single::<CharacterAction>() {
+            Ok(CharacterAction::Lookup) => look_up_characters(ctx, &server_id, &mut args).await,
+            Ok(action) => update_characters(ctx, msg, &server_id, &action, &mut args).await,
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(reply) => reply,
+            Err(e) => format!(
+                "Error with command arguments, try [help character]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = CHARACTER_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = CharacterAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
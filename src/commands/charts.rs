@@ -0,0 +1,290 @@
+use crate::charts;
+use crate::config::GeneralAppConfigData;
+use crate::state::StoreData;
+use chrono::{NaiveDate, Offset, TimeZone, Utc};
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::http::AttachmentType;
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+const WEEKS_IN_WEEKLY_CHART: usize = 12;
+const AUTHORS_IN_WEEKLY_CHART: usize = 8;
+
+#[derive(Debug, Clone, Copy, EnumIter)]
+enum ChartKind {
+    WordsOverTime,
+    Weekly,
+    Heatmap,
+}
+impl ChartKind {
+    fn to_str(self) -> &'static str {
+        match self {
+            Self::WordsOverTime => "words-over-time",
+            Self::Weekly => "weekly",
+            Self::Heatmap => "heatmap",
+        }
+    }
+}
+impl FromStr for ChartKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|kind| kind.to_str() == s) {
+            Some(kind) => Ok(kind),
+            None => Err(format!("Invalid chart {}", s)),
+        }
+    }
+}
+
+// Everything needed to draw a chart, taken out of the store so it isn't locked while drawing
+enum ChartData {
+    Cumulative(usize, BTreeMap<NaiveDate, usize>),
+    Weekly(Vec<NaiveDate>, Vec<(String, Vec<usize>)>),
+    Heatmap(Box<[[usize; 24]; 7]>, String),
+}
+
+async fn get_chart_data(
+    ctx: &Context,
+    server_id: &GuildId,
+    kind: ChartKind,
+    channel_id: Option<ChannelId>,
+    user_id: Option<UserId>,
+) -> std::result::Result<ChartData, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let server_data = match store.get_server_data(server_id) {
+        Some(server_data) => server_data,
+        None => {
+            return Err(String::from(
+                "There are no initialised channels on this server",
+            ))
+        }
+    };
+    if let Some(channel_id) = channel_id {
+        if server_data.get_channel_data(&channel_id).is_none() {
+            return Err(String::from(
+                "Channel not initialised, use [init-channel] to add it",
+            ));
+        }
+    }
+    let matching_stats = server_data.matching_stats(channel_id, user_id);
+    if matching_stats.is_empty() {
+        return Err(String::from("User has no recorded activity there"));
+    }
+    let chart_data = match kind {
+        ChartKind::WordsOverTime => {
+            let mut daily_words = BTreeMap::new();
+            for stats in matching_stats.iter() {
+                for (day, words) in stats.daily_word_counts() {
+                    *daily_words.entry(*day).or_default() += words;
+                }
+            }
+            let untracked_words = matching_stats
+                .iter()
+                .map(|stats| stats.untracked_word_count())
+                .sum();
+            ChartData::Cumulative(untracked_words, daily_words)
+        }
+        ChartKind::Weekly => {
+            let today = Utc::now().naive_utc().date();
+            let max_authors = match user_id {
+                Some(_) => usize::MAX,
+                None => AUTHORS_IN_WEEKLY_CHART,
+            };
+            let (week_starts, series) = server_data.weekly_words_by_author(
+                channel_id,
+                WEEKS_IN_WEEKLY_CHART,
+                max_authors,
+                today,
+            );
+            let series = series
+                .into_iter()
+                .filter(|(author, _)| match user_id {
+                    Some(user_id) => author.id == user_id,
+                    None => true,
+                })
+                .map(|(author, counts)| (author.name, counts))
+                .collect();
+            ChartData::Weekly(week_starts, series)
+        }
+        ChartKind::Heatmap => {
+            let mut grid = [[0; 24]; 7];
+            for stats in matching_stats.iter() {
+                for (day, hours) in stats.weekday_hour_words().iter().enumerate() {
+                    for (hour, words) in hours.iter().enumerate() {
+                        grid[day][hour] += words;
+                    }
+                }
+            }
+            // Uses the offset as it is now, which is an hour out for anything on the other side
+            // of a daylight saving change
+            let tz = server_data.settings.digest.tz();
+            let offset_seconds = tz
+                .offset_from_utc_datetime(&Utc::now().naive_utc())
+                .fix()
+                .local_minus_utc();
+            let grid = charts::shift_hours(&grid, (offset_seconds / 3600) as i64);
+            ChartData::Heatmap(Box::new(grid), tz.name().to_string())
+        }
+    };
+    Ok(chart_data)
+}
+
+async fn make_caption(
+    ctx: &Context,
+    kind: ChartKind,
+    channel_id: Option<ChannelId>,
+    user_id: Option<UserId>,
+) -> String {
+    let mut caption = String::from(match kind {
+        ChartKind::WordsOverTime => "Words over time",
+        ChartKind::Weekly => "Words per week",
+        ChartKind::Heatmap => "Words by day and hour",
+    });
+    if let Some(user_id) = user_id {
+        if let Ok(user) = user_id.to_user(ctx).await {
+            caption.push_str(&format!(" by {}", user.name));
+        }
+    }
+    match channel_id {
+        Some(channel_id) => {
+            if let Some(channel_name) = channel_id.name(&ctx.cache).await {
+                caption.push_str(&format!(" in #{}", channel_name));
+            }
+        }
+        None => caption.push_str(" across the server"),
+    }
+    caption
+}
+
+async fn make_chart(
+    ctx: &Context,
+    server_id: &GuildId,
+    kind: ChartKind,
+    channel_id: Option<ChannelId>,
+    user_id: Option<UserId>,
+) -> std::result::Result<Vec<u8>, String> {
+    let font_path = {
+        let config_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<GeneralAppConfigData>()
+                .expect("Expected GeneralAppConfigData in TypeMap.")
+                .clone()
+        };
+        let config = config_lock.read().unwrap();
+        config.chart_config.font_path.clone()
+    };
+    charts::register_chart_font(&font_path)?;
+    let chart_data = get_chart_data(ctx, server_id, kind, channel_id, user_id).await?;
+    let caption = make_caption(ctx, kind, channel_id, user_id).await;
+    match chart_data {
+        ChartData::Cumulative(untracked_words, daily_words) => {
+            charts::cumulative_words(&caption, untracked_words, &daily_words)
+        }
+        ChartData::Weekly(week_starts, series) => {
+            charts::weekly_words(&caption, &week_starts, &series)
+        }
+        ChartData::Heatmap(grid, time_zone) => {
+            charts::weekday_hour_heatmap(&format!("{} ({})", caption, time_zone), &grid)
+        }
+    }
+}
+
+#[command("chart")]
+#[usage("<chart> [<#channel name>] [<@user mention>]")]
+#[description("Draw a chart of words written over time, on the whole server or in one channel and/or by one user. [words-over-time] is a running total, [weekly] is words per week per author for the last 12 weeks, and [heatmap] is words by weekday and hour in the time zone set with [digest timezone]. Charts only cover messages since they were added, apart from the running total which starts from the older words. Charts: words-over-time|weekly|heatmap")]
+#[example("words-over-time #the-fall-of-rome")]
+#[example("weekly")]
+#[example("heatmap @Caligula")]
+#[only_in("guilds")] // Reminder: guild = server
+#[bucket("global-wordcloud-bucket")]
+async fn chart(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match parse_args(&mut args) {
+            Ok((kind, channel_id, user_id)) => {
+                match make_chart(ctx, &server_id, kind, channel_id, user_id).await {
+                    Ok(png) => {
+                        let files = vec![AttachmentType::Bytes {
+                            data: Cow::from(png),
+                            filename: format!("{}.png", kind.to_str()),
+                        }];
+                        msg.channel_id
+                            .send_files(&ctx.http, files, |create_message| {
+                                create_message.reference_message(msg)
+                            })
+                            .await?;
+                        None
+                    }
+                    Err(e) => Some(e),
+                }
+            }
+            Err(e) => Some(e),
+        }
+    } else {
+        Some(String::from(
+            "BUG: message had no server id, bot only supports server text channels",
+        ))
+    };
+    if let Some(reply) = reply {
+        msg.reply(ctx, reply).await?;
+    }
+    Ok(())
+}
+
+fn parse_args(
+    args: &mut Args,
+) -> std::result::Result<(ChartKind, Option<ChannelId>, Option<UserId>), String> {
+    let error_help_text = |e: &dyn std::fmt::Display| {
+        format!(
+            "Error with command arguments, try [help chart]\nError:{}",
+            e
+        )
+    };
+    if args.is_empty() || args.len() > 3 {
+        return Err(String::from("Invalid number of args, try [help chart]"));
+    }
+    let kind = args
+        .single::<ChartKind>()
+        .map_err(|e| error_help_text(&e))?;
+    let mut channel_id = None;
+    let mut user_id = None;
+    // Channel and user can come in either order
+    while !args.is_empty() {
+        if let Ok(id) = args.single::<ChannelId>() {
+            channel_id = Some(id);
+        } else if let Ok(id) = args.single::<UserId>() {
+            user_id = Some(id);
+        } else {
+            return Err(error_help_text(&format!(
+                "expected a channel or user, got {}",
+                args.rest()
+            )));
+        }
+    }
+    Ok((kind, channel_id, user_id))
+}
+
+#[test]
+fn usage_matches_all_charts() {
+    let desc: &str = CHART_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Charts: ";
+    let charts_index = desc.rfind(match_).unwrap();
+    let (_, charts_from_desc) = desc.split_at(charts_index + match_.len());
+    let all_charts_from_enum_iter: String = ChartKind::iter()
+        .map(|kind| String::from(kind.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_charts_from_enum_iter, charts_from_desc);
+}
@@ -0,0 +1,215 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::digest::DigestSettings;
+use crate::state::StoreData;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use chrono::{Duration, Utc, Weekday};
+use chrono_tz::Tz;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum DigestAction {
+    Show,
+    PostIn,
+    Off,
+    Schedule,
+    Timezone,
+    Preview,
+}
+impl DigestAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::PostIn => "post-in",
+            Self::Off => "off",
+            Self::Schedule => "schedule",
+            Self::Timezone => "timezone",
+            Self::Preview => "preview",
+        }
+    }
+}
+impl FromStr for DigestAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid digest action {}", s)),
+        }
+    }
+}
+
+fn describe_settings(settings: &DigestSettings) -> String {
+    let mut builder = MessageBuilder::new();
+    match settings.channel {
+        Some(channel_id) => builder
+            .push("Posting the weekly digest in ")
+            .channel(channel_id),
+        None => builder.push("The weekly digest isn't posted, use [post-in] to pick a channel"),
+    };
+    builder
+        .newline()
+        .push(format!(
+            "Every {:?} at {:02}:00 {}",
+            settings.weekday, settings.hour, settings.time_zone
+        ))
+        .build()
+}
+
+// Applies [action] to the settings in place, returning the reply on success
+fn apply_action(
+    settings: &mut DigestSettings,
+    action: &DigestAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    match action {
+        DigestAction::Show | DigestAction::Preview => Ok(String::new()),
+        DigestAction::PostIn => {
+            settings.channel = Some(args.single::<ChannelId>().map_err(|e| e.to_string())?);
+            Ok(String::from("Updated the digest channel"))
+        }
+        DigestAction::Off => {
+            settings.channel = None;
+            Ok(String::from("Stopped posting the digest"))
+        }
+        DigestAction::Schedule => {
+            let weekday = args
+                .single::<String>()
+                .map_err(|e| e.to_string())?
+                .parse::<Weekday>()
+                .map_err(|_| String::from("Expected a day of the week like monday"))?;
+            let hour = args.single::<u32>().map_err(|e| e.to_string())?;
+            if hour > 23 {
+                return Err(String::from("The hour needs to be between 0 and 23"));
+            }
+            settings.weekday = weekday;
+            settings.hour = hour;
+            Ok(String::from("Updated the digest schedule"))
+        }
+        DigestAction::Timezone => {
+            let time_zone = args.single::<String>().map_err(|e| e.to_string())?;
+            let tz = time_zone.parse::<Tz>().map_err(|_| {
+                format!(
+                    "Unknown time zone {}, try one like Europe/London",
+                    time_zone
+                )
+            })?;
+            settings.time_zone = tz.name().to_string();
+            Ok(String::from("Updated the digest time zone"))
+        }
+    }
+}
+
+// The digest as it would be posted today, over however many messages that takes
+async fn preview_digest(ctx: &Context, server_id: &GuildId) -> Vec<String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    // Covers the same days as a digest posted today would
+    let today = Utc::now()
+        .with_timezone(&server_data.settings.digest.tz())
+        .date()
+        .naive_local();
+    let digest = server_data.make_digest(today - Duration::days(7), today - Duration::days(1));
+    digest.to_messages()
+}
+
+async fn update_digest_settings(
+    ctx: &Context,
+    server_id: &GuildId,
+    action: &DigestAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    let settings = &mut server_data.settings.digest;
+    let mut new_settings = settings.clone();
+    let reply = apply_action(&mut new_settings, action, args)?;
+    *settings = new_settings;
+    let description = describe_settings(settings);
+    match reply.is_empty() {
+        true => Ok(description),
+        false => Ok(format!("{}\n{}", reply, description)),
+    }
+}
+
+#[command("digest")]
+#[usage("<action> [<#channel name> | <weekday> <hour> | <time zone>]")]
+#[description("Post a weekly digest of the last 7 days to a channel: words written per channel, the most active authors, new authors and milestones. It's posted once a week on the scheduled day and hour in the server's time zone (an IANA name like Europe/London). Use [preview] to see what it would say today. Changes need the MasterScrivener role. Actions: show|post-in|off|schedule|timezone|preview")]
+#[example("post-in #announcements")]
+#[example("schedule monday 9")]
+#[example("timezone Europe/London")]
+#[example("preview")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn digest(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<DigestAction>() {
+            Ok(DigestAction::Preview) => {
+                let mut messages = preview_digest(ctx, &server_id).await.into_iter();
+                let first = messages.next().unwrap_or_default();
+                msg.reply(ctx, first).await?;
+                for message in messages {
+                    msg.channel_id.say(ctx, message).await?;
+                }
+                return Ok(());
+            }
+            Ok(action) => {
+                let allowed = action == DigestAction::Show
+                    || author_is_in_allowed_roles(ctx, &server_id, &msg.author).await;
+                if allowed {
+                    match update_digest_settings(ctx, &server_id, &action, &mut args).await {
+                        Ok(reply) => reply,
+                        Err(e) => format!(
+                            "Error with command arguments, try [help digest]\nError:{}",
+                            e
+                        ),
+                    }
+                } else {
+                    format!(
+                        "Changing the digest is only available to those with the role {}",
+                        ALLOWED_ROLES[0]
+                    )
+                }
+            }
+            Err(e) => format!(
+                "Error with command arguments, try [help digest]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = DIGEST_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = DigestAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
@@ -0,0 +1,367 @@
+use crate::commands::archive::backfill_story_posts;
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::glossary::{GlossaryCuration, GlossaryEntry};
+use crate::state::{ChannelData, StoreData, StoryKey};
+use crate::stats::WordFilter;
+use crate::stories::StatsTarget;
+use crate::utils::helpers::message_link;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+const MAX_ENTRIES: usize = 20;
+
+fn push_entry(builder: &mut MessageBuilder, server_id: GuildId, entry: &GlossaryEntry) {
+    let (channel_id, message_id, timestamp) = entry.first;
+    builder.newline().push_bold_safe(&entry.name);
+    if !entry.aliases.is_empty() {
+        builder.push_safe(format!(" (also {})", entry.aliases.join(", ")));
+    }
+    builder.push_line(format!(
+        ": {} uses, first on {} <{}>",
+        entry.count,
+        timestamp.format("%Y-%m-%d"),
+        message_link(server_id, channel_id, message_id)
+    ));
+}
+
+fn make_glossary(
+    title: &str,
+    server_id: GuildId,
+    data: &ChannelData,
+    word_filter: &WordFilter,
+    curation: &GlossaryCuration,
+) -> String {
+    let entries = data.glossary.entries(word_filter, curation);
+    if entries.is_empty() {
+        return format!("No names have come up in {} yet", title);
+    }
+    let mut builder = MessageBuilder::new();
+    builder.push("Glossary for ").push(title);
+    if entries.len() > MAX_ENTRIES {
+        builder.push(format!(", the top {} of {}", MAX_ENTRIES, entries.len()));
+    }
+    builder.newline();
+    for entry in entries.iter().take(MAX_ENTRIES) {
+        push_entry(&mut builder, server_id, entry);
+    }
+    builder.build()
+}
+
+async fn get_glossary(ctx: &Context, server_id: GuildId, target: &StatsTarget) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let server_data = match store.get_server_data(&server_id) {
+        Some(server_data) => server_data,
+        None => return String::from("No channels have been initialised on this server"),
+    };
+    let curation = &server_data.settings.glossary;
+    match target {
+        StatsTarget::Channel(channel_id) => match server_data.get_channel_data(channel_id) {
+            Some(channel_data) => make_glossary(
+                &MessageBuilder::new().channel(channel_id).build(),
+                server_id,
+                channel_data,
+                &store.word_filter(&(server_id, *channel_id)),
+                curation,
+            ),
+            None => String::from("Channel not initialised, use [init-channel] to add it"),
+        },
+        StatsTarget::Story(name) => {
+            match (server_data.get_story(name), server_data.story_data(name)) {
+                (Some(story), Some(story_data)) => make_glossary(
+                    &MessageBuilder::new()
+                        .push("the story ")
+                        .push_bold_safe(&story.name)
+                        .build(),
+                    server_id,
+                    &story_data,
+                    &store.word_filter_for_channels(&server_id, &story.channels),
+                    curation,
+                ),
+                _ => format!("There's no story called {}, see [story list]", name),
+            }
+        }
+        StatsTarget::Chapter(..) => {
+            String::from("The glossary is only kept for channels and stories")
+        }
+    }
+}
+
+#[command("glossary")]
+#[usage("<#channel name | story name>")]
+#[description("List the names (characters, places, things...) that come up in a channel or story, with how often they're used and where each was first. Names are words that are capitalised other than at the start of a sentence, see [glossary-edit] to merge aliases or hide words that aren't names. Only posts from when the glossary was added are included, use [glossary-backfill] for earlier ones")]
+#[example("#the-fall-of-rome")]
+#[example("\"Fall of Rome\"")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn glossary(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match StatsTarget::single(&mut args) {
+            Ok(target) => get_glossary(ctx, server_id, &target).await,
+            Err(e) => format!(
+                "Error with command arguments, try [help glossary]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[command("glossary-backfill")]
+#[usage("<#channel name>")]
+#[description("Add a channel's earlier posts to the glossary, for channels initialised before [glossary] existed. Posts already in it aren't counted again. Needs the MasterScrivener role")]
+#[example("#the-fall-of-rome")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn glossary_backfill(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        if !author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+            format!(
+                "Backfilling the glossary is only available to those with the role {}",
+                ALLOWED_ROLES[0]
+            )
+        } else {
+            match args.single::<ChannelId>() {
+                Ok(channel_id) => {
+                    let story_key: StoryKey = (server_id, channel_id);
+                    let initialised = {
+                        let store_lock = {
+                            let data_read = ctx.data.read().await;
+                            data_read
+                                .get::<StoreData>()
+                                .expect("Expected StoreData in TypeMap.")
+                                .clone()
+                        };
+                        let store = store_lock.read().unwrap();
+                        store.channel_data_exists(&story_key)
+                    };
+                    if initialised {
+                        // Fetching history can take a while, so show it's being worked on
+                        let _ = msg
+                            .react(ctx, ReactionType::Unicode(String::from("⌚")))
+                            .await;
+                        match backfill_story_posts(ctx, &story_key, ChannelData::add_to_glossary)
+                            .await
+                        {
+                            Ok(fetched_messages) => format!(
+                                "Looked through {} messages for story posts to add to the glossary",
+                                fetched_messages
+                            ),
+                            Err(e) => e,
+                        }
+                    } else {
+                        String::from("Channel not initialised, use [init-channel] to add it")
+                    }
+                }
+                Err(e) => format!(
+                    "Error with command arguments, try [help glossary-backfill]\nError:{}",
+                    e
+                ),
+            }
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum GlossaryAction {
+    Show,
+    Merge,
+    Unmerge,
+    Hide,
+    Unhide,
+}
+impl GlossaryAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::Merge => "merge",
+            Self::Unmerge => "unmerge",
+            Self::Hide => "hide",
+            Self::Unhide => "unhide",
+        }
+    }
+}
+impl FromStr for GlossaryAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid glossary action {}", s)),
+        }
+    }
+}
+
+fn describe_curation(curation: &GlossaryCuration) -> String {
+    if curation.aliases.is_empty() && curation.hidden.is_empty() {
+        return String::from("The glossary is as found, nothing has been merged or hidden");
+    }
+    let mut builder = MessageBuilder::new();
+    let mut aliases: Vec<(&String, &String)> = curation.aliases.iter().collect();
+    aliases.sort_by_key(|(alias, name)| (name.to_lowercase(), *alias));
+    for (alias, name) in aliases {
+        builder.push_safe(format!("{} is listed under ", alias));
+        builder.push_bold_line_safe(name);
+    }
+    if !curation.hidden.is_empty() {
+        let mut hidden: Vec<&String> = curation.hidden.iter().collect();
+        hidden.sort();
+        builder.push_line_safe(format!(
+            "Hidden: {}",
+            hidden
+                .iter()
+                .map(|word| word.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ));
+    }
+    builder.build()
+}
+
+fn word(args: &mut Args) -> std::result::Result<String, String> {
+    args.single_quoted::<String>()
+        .map_err(|_| String::from("Expected a word"))
+}
+
+// Applies [action] to the curation in place, returning the reply on success
+fn apply_action(
+    curation: &mut GlossaryCuration,
+    action: &GlossaryAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    match action {
+        GlossaryAction::Show => Ok(String::new()),
+        GlossaryAction::Merge => {
+            let name = word(args)?;
+            let aliases: Vec<String> = args
+                .iter::<String>()
+                .quoted()
+                .filter_map(|alias| alias.ok())
+                .collect();
+            if aliases.is_empty() {
+                return Err(String::from("Expected at least one alias"));
+            }
+            for alias in aliases.iter() {
+                curation.merge(&name, alias)?;
+            }
+            Ok(format!("Merged {} into {}", aliases.join(", "), name))
+        }
+        GlossaryAction::Unmerge => {
+            let alias = word(args)?;
+            match curation.unmerge(&alias) {
+                Some(name) => Ok(format!("{} is no longer listed under {}", alias, name)),
+                None => Err(format!("{} isn't an alias", alias)),
+            }
+        }
+        GlossaryAction::Hide => {
+            let word = word(args)?;
+            curation.hidden.insert(word.to_lowercase());
+            Ok(format!("Hid {}", word))
+        }
+        GlossaryAction::Unhide => {
+            let word = word(args)?;
+            match curation.hidden.remove(&word.to_lowercase()) {
+                true => Ok(format!("{} is back in the glossary", word)),
+                false => Err(format!("{} isn't hidden", word)),
+            }
+        }
+    }
+}
+
+async fn update_curation(
+    ctx: &Context,
+    server_id: &GuildId,
+    action: &GlossaryAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let curation = &mut store
+        .get_server_data_mut_maybe_create(server_id)
+        .settings
+        .glossary;
+    let mut new_curation = curation.clone();
+    let reply = apply_action(&mut new_curation, action, args)?;
+    *curation = new_curation;
+    let description = describe_curation(curation);
+    match reply.is_empty() {
+        true => Ok(description),
+        false => Ok(format!("{}\n{}", reply, description)),
+    }
+}
+
+#[command("glossary-edit")]
+#[usage("<action> [<name> [<aliases>...] | <word>]")]
+#[description("Fix up what [glossary] finds on this server. [merge] lists aliases under one name (their uses are added together), [hide] drops words that aren't names. Put words with spaces in quotes. Changes need the MasterScrivener role. Actions: show|merge|unmerge|hide|unhide")]
+#[example("merge Anastasia Ana Stasia")]
+#[example("unmerge Stasia")]
+#[example("hide Okay")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn glossary_edit(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<GlossaryAction>() {
+            Ok(action) => {
+                let allowed = action == GlossaryAction::Show
+                    || author_is_in_allowed_roles(ctx, &server_id, &msg.author).await;
+                if allowed {
+                    match update_curation(ctx, &server_id, &action, &mut args).await {
+                        Ok(reply) => reply,
+                        Err(e) => format!(
+                            "Error with command arguments, try [help glossary-edit]\nError:{}",
+                            e
+                        ),
+                    }
+                } else {
+                    format!(
+                        "Changing the glossary is only available to those with the role {}",
+                        ALLOWED_ROLES[0]
+                    )
+                }
+            }
+            Err(e) => format!(
+                "Error with command arguments, try [help glossary-edit]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = GLOSSARY_EDIT_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = GlossaryAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
@@ -0,0 +1,216 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::goals::{Goal, GoalOwner};
+use crate::state::StoreData;
+use chrono::{NaiveDate, Utc};
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[derive(Debug, EnumIter)]
+enum GoalAction {
+    Set,
+    Status,
+    Clear,
+}
+impl GoalAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::Set => "set",
+            Self::Status => "status",
+            Self::Clear => "clear",
+        }
+    }
+}
+impl FromStr for GoalAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid goal action {}", s)),
+        }
+    }
+}
+
+// A channel or user mention if there is one, otherwise it's the author's own goal
+fn parse_owner(args: &mut Args, author: &User) -> GoalOwner {
+    if let Ok(channel_id) = args.single::<ChannelId>() {
+        GoalOwner::Channel(channel_id)
+    } else if let Ok(user_id) = args.single::<UserId>() {
+        GoalOwner::User(user_id)
+    } else {
+        GoalOwner::User(author.id)
+    }
+}
+
+fn parse_target_and_deadline(args: &mut Args) -> std::result::Result<(usize, NaiveDate), String> {
+    let target = args
+        .single::<usize>()
+        .map_err(|e| format!("Expected a word target: {}", e))?;
+    if target == 0 {
+        return Err(String::from("The word target needs to be more than 0"));
+    }
+    // "by" reads nicely but is optional
+    if args.current() == Some("by") {
+        args.advance();
+    }
+    let deadline = args
+        .single::<NaiveDate>()
+        .map_err(|e| format!("Expected a deadline like 2026-11-30: {}", e))?;
+    Ok((target, deadline))
+}
+
+fn owner_description(owner: &GoalOwner) -> String {
+    match owner {
+        GoalOwner::User(user_id) => MessageBuilder::new().mention(user_id).build(),
+        GoalOwner::Channel(channel_id) => MessageBuilder::new().channel(channel_id).build(),
+    }
+}
+
+async fn change_goal(
+    ctx: &Context,
+    server_id: &GuildId,
+    action: GoalAction,
+    owner: GoalOwner,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let today = Utc::now().naive_utc().date();
+    let new_goal = match action {
+        GoalAction::Set => {
+            let (target, deadline) = parse_target_and_deadline(args)?;
+            if deadline < today {
+                return Err(String::from("The deadline has already passed"));
+            }
+            Some(Goal {
+                owner,
+                target,
+                start: today,
+                deadline,
+            })
+        }
+        _ => None,
+    };
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    if let GoalOwner::Channel(channel_id) = owner {
+        if !store.channel_data_exists(&(*server_id, channel_id)) {
+            return Err(String::from(
+                "Channel not initialised, use [init-channel] to add it",
+            ));
+        }
+    }
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    match new_goal {
+        Some(goal) => {
+            let reply = format!(
+                "Goal set for {}: {} words by {}",
+                owner_description(&owner),
+                goal.target,
+                goal.deadline
+            );
+            server_data.set_goal(goal);
+            Ok(reply)
+        }
+        None => match server_data.remove_goal(&owner) {
+            true => Ok(format!("Goal cleared for {}", owner_description(&owner))),
+            false => Err(format!("No goal set for {}", owner_description(&owner))),
+        },
+    }
+}
+
+async fn goal_status(ctx: &Context, server_id: &GuildId, owner: GoalOwner) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let goal_and_progress = store.get_server_data(server_id).and_then(|server_data| {
+        server_data
+            .get_goal(&owner)
+            .map(|goal| (goal.clone(), server_data.goal_progress(goal)))
+    });
+    match goal_and_progress {
+        Some((goal, progress)) => format!(
+            "Goal for {}:\n{}",
+            owner_description(&owner),
+            goal.status_string(progress, Utc::now().naive_utc().date())
+        ),
+        None => format!(
+            "No goal set for {}, see [help goal] to set one",
+            owner_description(&owner)
+        ),
+    }
+}
+
+#[command("goal")]
+#[usage("<action> [<#channel name> | <@user mention>] [<target> by <deadline>]")]
+#[description("Set a word count goal to hit by a deadline (YYYY-MM-DD), for yourself or for everyone in a channel, and see how it's going. Words written from the day it's set until the deadline count towards it, across all initialised channels for personal goals. Channel goals need the MasterScrivener role. Actions: set|status|clear")]
+#[example("set 50000 by 2026-11-30")]
+#[example("set #the-fall-of-rome 100000 by 2026-12-31")]
+#[example("status")]
+#[example("status @Caligula")]
+#[example("clear #the-fall-of-rome")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn goal(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<GoalAction>() {
+            Ok(GoalAction::Status) => {
+                let owner = parse_owner(&mut args, &msg.author);
+                goal_status(ctx, &server_id, owner).await
+            }
+            Ok(action) => {
+                let owner = parse_owner(&mut args, &msg.author);
+                let allowed = match owner {
+                    GoalOwner::User(user_id) => user_id == msg.author.id,
+                    GoalOwner::Channel(_) => {
+                        author_is_in_allowed_roles(ctx, &server_id, &msg.author).await
+                    }
+                };
+                if allowed {
+                    match change_goal(ctx, &server_id, action, owner, &mut args).await {
+                        Ok(reply) => reply,
+                        Err(e) => {
+                            format!("Error with command arguments, try [help goal]\nError:{}", e)
+                        }
+                    }
+                } else {
+                    format!(
+                        "You can only change your own goals, channel goals need the role {}",
+                        ALLOWED_ROLES[0]
+                    )
+                }
+            }
+            Err(e) => format!("Error with command arguments, try [help goal]\nError:{}", e),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = GOAL_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = GoalAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
@@ -0,0 +1,235 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::markov::MIN_STATES;
+use crate::state::StoreData;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+// A short paragraph
+const MIN_WORDS: usize = 40;
+const MAX_WORDS: usize = 120;
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum ImitationAction {
+    Show,
+    On,
+    Off,
+    OptOut,
+    OptIn,
+}
+impl ImitationAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::On => "on",
+            Self::Off => "off",
+            Self::OptOut => "opt-out",
+            Self::OptIn => "opt-in",
+        }
+    }
+}
+impl FromStr for ImitationAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid imitation action {}", s)),
+        }
+    }
+}
+
+async fn update_imitation(
+    ctx: &Context,
+    msg: &Message,
+    server_id: GuildId,
+    channel_id: ChannelId,
+    action: &ImitationAction,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    match action {
+        ImitationAction::OptOut => {
+            let server_data = store.get_server_data_mut_maybe_create(&server_id);
+            return Ok(match server_data.opt_out_of_imitation(msg.author.id) {
+                true => String::from(
+                    "You've opted out, I've forgotten your style and won't learn it again",
+                ),
+                false => String::from("You'd already opted out"),
+            });
+        }
+        ImitationAction::OptIn => {
+            let server_data = store.get_server_data_mut_maybe_create(&server_id);
+            return Ok(match server_data.opt_in_to_imitation(msg.author.id) {
+                true => String::from("You've opted back in, I'll learn your style from new posts"),
+                false => String::from("You hadn't opted out"),
+            });
+        }
+        _ => {}
+    }
+    let channel_data = match store.get_channel_data_mut(&(server_id, channel_id)) {
+        Some(channel_data) => channel_data,
+        None => {
+            return Err(String::from(
+                "Channel not initialised, use [init-channel] to add it",
+            ))
+        }
+    };
+    let mut builder = MessageBuilder::new();
+    builder.push("Imitation in ").channel(channel_id);
+    match action {
+        ImitationAction::On => {
+            channel_data.set_imitation(true);
+            builder.push(" is on, styles are learned from new posts");
+        }
+        ImitationAction::Off => {
+            channel_data.set_imitation(false);
+            builder.push(" is off, and everything learned there is forgotten");
+        }
+        _ => {
+            builder.push(match channel_data.settings.imitation {
+                true => " is on",
+                false => " is off",
+            });
+        }
+    }
+    Ok(builder.build())
+}
+
+#[command("imitation")]
+#[usage("<action> [<#channel name>]")]
+#[description("Choose the channels [imitate] learns writing styles from, this one if none is given. Nothing is learned until it's turned on, and turning it off forgets everything learned there. Anyone can opt out of being imitated, which also forgets their style. Turning it on or off needs the MasterScrivener role. Actions: show|on|off|opt-out|opt-in")]
+#[example("on #the-fall-of-rome")]
+#[example("opt-out")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn imitation(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<ImitationAction>() {
+            Ok(action) => {
+                let channel_id = args.single::<ChannelId>().unwrap_or(msg.channel_id);
+                let allowed = !matches!(action, ImitationAction::On | ImitationAction::Off)
+                    || author_is_in_allowed_roles(ctx, &server_id, &msg.author).await;
+                if allowed {
+                    match update_imitation(ctx, msg, server_id, channel_id, &action).await {
+                        Ok(reply) => reply,
+                        Err(e) => format!(
+                            "Error with command arguments, try [help imitation]\nError:{}",
+                            e
+                        ),
+                    }
+                } else {
+                    format!(
+                        "Turning imitation on or off is only available to those with the role {}",
+                        ALLOWED_ROLES[0]
+                    )
+                }
+            }
+            Err(e) => format!(
+                "Error with command arguments, try [help imitation]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+async fn imitate_user(
+    ctx: &Context,
+    server_id: GuildId,
+    user_id: UserId,
+    channel_id: Option<ChannelId>,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let text = {
+        let store = store_lock.read().unwrap();
+        let server_data = match store.get_server_data(&server_id) {
+            Some(server_data) => server_data,
+            None => {
+                return Err(String::from(
+                    "No channels have been initialised on this server",
+                ))
+            }
+        };
+        let model = server_data.imitation_model(user_id, channel_id)?;
+        if model.states() < MIN_STATES {
+            return Err(String::from(
+                "I haven't seen enough of their writing yet, only posts in channels with [imitation on] count",
+            ));
+        }
+        model.generate(&mut rand::thread_rng(), MIN_WORDS, MAX_WORDS)
+    };
+    let name = user_id
+        .to_user(&ctx.http)
+        .await
+        .map(|user| user.name)
+        .unwrap_or_else(|_| String::from("someone"));
+    Ok(MessageBuilder::new()
+        .push("In the style of ")
+        .push_bold_safe(name)
+        .push("\n")
+        .push_quote_safe(text)
+        .build())
+}
+
+#[command("imitate")]
+#[usage("<@user mention> [<#channel name>]")]
+#[description("Write a short paragraph in someone's style, made up from the posts they've written in channels with [imitation] on. Just for fun, it won't make much sense")]
+#[example("@Caligula")]
+#[example("@Caligula #the-fall-of-rome")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn imitate(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<UserId>() {
+            Ok(user_id) => {
+                let channel_id = args.single::<ChannelId>().ok();
+                match imitate_user(ctx, server_id, user_id, channel_id).await {
+                    Ok(reply) => reply,
+                    Err(e) => format!(
+                        "Error with command arguments, try [help imitate]\nError:{}",
+                        e
+                    ),
+                }
+            }
+            Err(e) => format!(
+                "Error with command arguments, try [help imitate]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = IMITATION_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = ImitationAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
@@ -1,3 +1,5 @@
+use crate::attachments::{with_attachment_text, with_attachment_texts};
+use crate::proxies::CharacterOwners;
 use crate::state::{ChannelData, StoreData, StoryKey};
 use log::info;
 use serenity::framework::standard::{macros::command, Args, CommandResult};
@@ -39,7 +41,7 @@ async fn actually_init_channel(
         ));
     }
     //Set channel as being initialised
-    {
+    let owners = {
         let store_lock = {
             let data_read = ctx.data.read().await;
             data_read
@@ -49,6 +51,11 @@ async fn actually_init_channel(
         };
         let mut store = store_lock.write().unwrap();
         store.initialising_channels.insert(story_key.clone());
+        // Who plays each character, so proxied posts count for them
+        match store.get_server_data(&story_key.0) {
+            Some(server_data) => server_data.character_owners.clone(),
+            None => CharacterOwners::default(),
+        }
     };
     let mut channel_data = ChannelData::default();
     info!(
@@ -62,7 +69,8 @@ async fn actually_init_channel(
         {
             //Fetch the last_msg_id itself, or we miss it by just jumping in with [before(id)]
             let last_msg = text_channel.message(&ctx.http, last_msg_id).await.unwrap();
-            channel_data.update(&last_msg);
+            let last_msg = with_attachment_text(ctx, &last_msg).await;
+            channel_data.update(&last_msg, &owners);
         }
         loop {
             let messages: Vec<Message> = text_channel
@@ -75,12 +83,13 @@ async fn actually_init_channel(
                 break;
             } else {
                 fetched_messages += messages.len();
+                let messages = with_attachment_texts(ctx, messages).await;
                 // I don't know if the older message will be first or last.
                 for message in messages {
                     if message.timestamp < oldest_message {
                         last_msg_id = message.id
                     }
-                    channel_data.update(&message);
+                    channel_data.update(&message, &owners);
                 }
                 info!(
                     "Processed {} messages so far in {}...",
@@ -125,7 +134,7 @@ async fn react_or_reply(msg: &Message, ctx: &Context) {
 }
 
 // TODO: Load these from config
-const ALLOWED_ROLES: [&str; 3] = ["MasterScrivener", "ScrivMaster", "ScrivAdmin"];
+pub const ALLOWED_ROLES: [&str; 3] = ["MasterScrivener", "ScrivMaster", "ScrivAdmin"];
 
 #[command("init-channel")]
 #[usage("<#channel name>")]
@@ -176,7 +185,7 @@ async fn init_channel(ctx: &Context, msg: &Message, mut args: Args) -> CommandRe
 
 const BOSS: u64 = 190534649548767243;
 
-async fn author_is_in_allowed_roles(ctx: &Context, server_id: &GuildId, user: &User) -> bool {
+pub async fn author_is_in_allowed_roles(ctx: &Context, server_id: &GuildId, user: &User) -> bool {
     if user.id.0 == BOSS {
         return true;
     }
@@ -0,0 +1,339 @@
+use crate::state::{LeaderboardMetric, StoreData, StoryKey};
+use crate::utils::trait_extensions::MessageBuilderExt;
+use chrono::{Duration, NaiveDate, Utc};
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+const PAGE_SIZE: usize = 10;
+
+#[derive(Debug, Clone, Copy, EnumIter)]
+enum Window {
+    All,
+    Today,
+    Week,
+    Month,
+    Year,
+}
+impl Window {
+    fn to_str(self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Today => "today",
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Year => "year",
+        }
+    }
+
+    // Rolling windows that end (and include) [today], in UTC like the daily counts
+    fn dates(self, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        let days = match self {
+            Self::All => return None,
+            Self::Today => 1,
+            Self::Week => 7,
+            Self::Month => 30,
+            Self::Year => 365,
+        };
+        Some((today - Duration::days(days - 1), today))
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::All => "all time",
+            Self::Today => "today",
+            Self::Week => "the last 7 days",
+            Self::Month => "the last 30 days",
+            Self::Year => "the last 365 days",
+        }
+    }
+}
+impl FromStr for Window {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|window| window.to_str() == s) {
+            Some(window) => Ok(window),
+            None => Err(format!("Invalid time window {}", s)),
+        }
+    }
+}
+
+struct LeaderboardArgs {
+    metric: LeaderboardMetric,
+    window: Window,
+    channel_ids: Vec<ChannelId>,
+    category: Option<String>,
+    stories: Vec<String>,
+    page: usize,
+}
+
+fn parse_args(args: &mut Args) -> std::result::Result<LeaderboardArgs, String> {
+    let mut parsed = LeaderboardArgs {
+        metric: LeaderboardMetric::Words,
+        window: Window::All,
+        channel_ids: vec![],
+        category: None,
+        stories: vec![],
+        page: 1,
+    };
+    // Everything is optional and can come in any order
+    while !args.is_empty() {
+        if let Ok(metric) = args.single::<LeaderboardMetric>() {
+            parsed.metric = metric;
+        } else if let Ok(window) = args.single::<Window>() {
+            parsed.window = window;
+        } else if let Ok(channel_id) = args.single::<ChannelId>() {
+            parsed.channel_ids.push(channel_id);
+        } else {
+            match args.single::<String>().unwrap().as_str() {
+                "page" => {
+                    parsed.page = args
+                        .single::<usize>()
+                        .map_err(|e| format!("Expected a page number: {}", e))?
+                        .max(1)
+                }
+                "category" => {
+                    parsed.category = Some(
+                        args.single_quoted::<String>()
+                            .map_err(|e| format!("Expected a category name: {}", e))?,
+                    )
+                }
+                "story" => parsed.stories.push(
+                    args.single_quoted::<String>()
+                        .map_err(|e| format!("Expected a story name: {}", e))?,
+                ),
+                other => return Err(format!("Didn't understand {}", other)),
+            }
+        }
+    }
+    Ok(parsed)
+}
+
+// The initialised channels in the category called [category_name], ignoring case
+async fn channels_in_category(
+    ctx: &Context,
+    channel_ids: Vec<ChannelId>,
+    category_name: &str,
+) -> HashSet<ChannelId> {
+    let mut category_names: HashMap<ChannelId, String> = HashMap::new();
+    let mut in_category = HashSet::new();
+    for channel_id in channel_ids {
+        let category_id = match channel_id.to_channel(ctx).await {
+            Ok(Channel::Guild(channel)) => channel.category_id,
+            _ => None,
+        };
+        if let Some(category_id) = category_id {
+            // Cached since lots of channels share a category, and each lookup can hit the API
+            let name = match category_names.get(&category_id) {
+                Some(name) => name.clone(),
+                None => {
+                    let name = match category_id.to_channel(ctx).await {
+                        Ok(Channel::Category(category)) => category.name,
+                        _ => String::new(),
+                    };
+                    category_names.insert(category_id, name.clone());
+                    name
+                }
+            };
+            if name.eq_ignore_ascii_case(category_name) {
+                in_category.insert(channel_id);
+            }
+        }
+    }
+    in_category
+}
+
+async fn make_leaderboard(
+    ctx: &Context,
+    server_id: &GuildId,
+    args: LeaderboardArgs,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let server_channel_ids = store_lock
+        .read()
+        .unwrap()
+        .get_all_channels_in_server(server_id);
+    if server_channel_ids.is_empty() {
+        return Err(String::from(
+            "There are no initialised channels on this server",
+        ));
+    }
+    let mut category_filter: Option<HashSet<ChannelId>> = None;
+    if let Some(category_name) = &args.category {
+        let in_category = channels_in_category(ctx, server_channel_ids, category_name).await;
+        if in_category.is_empty() {
+            return Err(format!(
+                "There are no initialised channels in the category {}",
+                category_name
+            ));
+        }
+        category_filter = Some(in_category);
+    }
+    // Stories and channels add to each other, a category narrows them down to those in it
+    let mut channel_filter: Option<HashSet<ChannelId>> = None;
+    let mut story_names = vec![];
+    if !args.stories.is_empty() {
+        let store = store_lock.read().unwrap();
+        let server_data = store.get_server_data(server_id).unwrap();
+        for name in args.stories.iter() {
+            match server_data.get_story(name) {
+                Some(story) => {
+                    story_names.push(story.name.clone());
+                    channel_filter
+                        .get_or_insert_with(HashSet::new)
+                        .extend(story.channels.iter().cloned());
+                }
+                None => return Err(format!("There's no story called {}", name)),
+            }
+        }
+    }
+    if !args.channel_ids.is_empty() {
+        channel_filter
+            .get_or_insert_with(HashSet::new)
+            .extend(args.channel_ids.iter().cloned());
+    }
+    let channel_filter = match (channel_filter, category_filter) {
+        (Some(picked), Some(in_category)) => {
+            let in_both: HashSet<ChannelId> = picked.intersection(&in_category).cloned().collect();
+            if in_both.is_empty() {
+                return Err(format!(
+                    "None of those stories or channels are in the category {}",
+                    args.category.unwrap_or_default()
+                ));
+            }
+            Some(in_both)
+        }
+        (picked, in_category) => picked.or(in_category),
+    };
+    let ranked = {
+        let store = store_lock.read().unwrap();
+        for channel_id in args.channel_ids.iter() {
+            let story_key: StoryKey = (*server_id, *channel_id);
+            if !store.channel_data_exists(&story_key) {
+                return Err(String::from(
+                    "Channel not initialised, use [init-channel] to add it",
+                ));
+            }
+        }
+        let window = args.window.dates(Utc::now().naive_utc().date());
+        store.get_server_data(server_id).unwrap().leaderboard(
+            args.metric,
+            window,
+            channel_filter.as_ref(),
+        )
+    };
+
+    let mut builder = MessageBuilder::new();
+    builder.push_bold(format!(
+        "Leaderboard by {} for {}",
+        args.metric.to_str(),
+        args.window.describe()
+    ));
+    for (i, name) in story_names.iter().enumerate() {
+        builder
+            .push(if i == 0 {
+                " in the story "
+            } else {
+                ", the story "
+            })
+            .push_safe(name);
+    }
+    for (i, channel_id) in args.channel_ids.iter().enumerate() {
+        builder
+            .push(if i == 0 && story_names.is_empty() {
+                " in "
+            } else {
+                ", "
+            })
+            .channel(channel_id);
+    }
+    if let Some(category_name) = &args.category {
+        let picked_some = !story_names.is_empty() || !args.channel_ids.is_empty();
+        builder.push_safe(format!(
+            "{} the category {}",
+            if picked_some {
+                ", only those in"
+            } else {
+                " in"
+            },
+            category_name
+        ));
+    }
+    builder.newline();
+    if ranked.is_empty() {
+        return Ok(builder.push("No recorded activity").build());
+    }
+    let pages = ranked.len().div_ceil(PAGE_SIZE);
+    let page = args.page.min(pages);
+    for (i, (author, total)) in ranked
+        .iter()
+        .enumerate()
+        .skip((page - 1) * PAGE_SIZE)
+        .take(PAGE_SIZE)
+    {
+        builder
+            .push(format!("{}. ", i + 1))
+            .user(author)
+            .push_line(format!(" -> {}", total));
+    }
+    builder.apply_if(pages > 1, |b| {
+        b.push(format!("Page {} of {}", page, pages))
+            .apply_if(page < pages, |b| {
+                b.push(format!(", add [page {}] to see more", page + 1))
+            })
+    });
+    Ok(builder.build())
+}
+
+#[command("leaderboard")]
+#[usage("[<metric>] [<time window>] [<#channel name>...] [category <name>] [story <name>...] [page <number>]")]
+#[description("Rank everyone across the server's initialised channels by words, messages or days active (a post split over several messages counts once, see [post-window]), over a rolling time window. Can be narrowed to some channels and stories made with [story create] (put names with spaces in quotes), and to a category, which only keeps the channels and stories in it. Time windows other than all only cover messages since this command was added. Metrics: words|messages|days. Time windows: all|today|week|month|year")]
+#[example("")]
+#[example("messages week")]
+#[example("days month #the-fall-of-rome #the-rise-of-rome")]
+#[example("words year category \"Ancient History\" page 2")]
+#[example("messages story \"Fall of Rome\"")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn leaderboard(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match parse_args(&mut args) {
+            Ok(leaderboard_args) => {
+                match make_leaderboard(ctx, &server_id, leaderboard_args).await {
+                    Ok(reply) => reply,
+                    Err(e) => e,
+                }
+            }
+            Err(e) => format!(
+                "Error with command arguments, try [help leaderboard]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_metrics_and_windows() {
+    let desc: &str = LEADERBOARD_COMMAND_OPTIONS.desc.unwrap();
+    let metrics: Vec<&str> = LeaderboardMetric::iter()
+        .map(|metric| metric.to_str())
+        .collect();
+    let windows: Vec<&str> = Window::iter().map(|window| window.to_str()).collect();
+    assert!(desc.contains(&format!("Metrics: {}.", metrics.join("|"))));
+    assert!(desc.ends_with(&format!("Time windows: {}", windows.join("|"))));
+}
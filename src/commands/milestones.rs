@@ -0,0 +1,208 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::milestones::{with_thousands_separators, MilestoneSettings};
+use crate::state::StoreData;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum MilestonesAction {
+    Show,
+    AnnounceIn,
+    Off,
+    Channel,
+    Author,
+    Server,
+}
+impl MilestonesAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::AnnounceIn => "announce-in",
+            Self::Off => "off",
+            Self::Channel => "channel",
+            Self::Author => "author",
+            Self::Server => "server",
+        }
+    }
+}
+impl FromStr for MilestonesAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid milestones action {}", s)),
+        }
+    }
+}
+
+fn describe_thresholds(thresholds: &[usize]) -> String {
+    match thresholds.is_empty() {
+        true => String::from("none"),
+        false => thresholds
+            .iter()
+            .map(|threshold| with_thousands_separators(*threshold))
+            .collect::<Vec<String>>()
+            .join(", "),
+    }
+}
+
+fn describe_settings(settings: &MilestoneSettings) -> String {
+    let mut builder = MessageBuilder::new();
+    match settings.announcement_channel {
+        Some(channel_id) => builder
+            .push("Announcing milestones in ")
+            .channel(channel_id),
+        None => builder.push("Milestones aren't announced, use [announce-in] to pick a channel"),
+    };
+    builder
+        .newline()
+        .push_line(format!(
+            "Channel totals: {}",
+            describe_thresholds(&settings.channel_thresholds)
+        ))
+        .push_line(format!(
+            "Author totals in a channel: {}",
+            describe_thresholds(&settings.author_channel_thresholds)
+        ))
+        .push(format!(
+            "Author totals across the server: {}",
+            describe_thresholds(&settings.author_server_thresholds)
+        ))
+        .build()
+}
+
+fn parse_thresholds(args: &mut Args) -> std::result::Result<Vec<usize>, String> {
+    let mut thresholds = vec![];
+    for threshold in args.iter::<usize>() {
+        match threshold {
+            Ok(threshold) if threshold > 0 => thresholds.push(threshold),
+            Ok(_) => return Err(String::from("Thresholds need to be more than 0")),
+            Err(e) => return Err(format!("Expected word counts: {}", e)),
+        }
+    }
+    thresholds.sort_unstable();
+    thresholds.dedup();
+    Ok(thresholds)
+}
+
+// Applies [action] to the settings in place, returning the reply on success
+fn apply_action(
+    settings: &mut MilestoneSettings,
+    action: &MilestonesAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    match action {
+        MilestonesAction::Show => Ok(String::new()),
+        MilestonesAction::AnnounceIn => {
+            settings.announcement_channel =
+                Some(args.single::<ChannelId>().map_err(|e| e.to_string())?);
+            Ok(String::from("Updated the announcement channel"))
+        }
+        MilestonesAction::Off => {
+            settings.announcement_channel = None;
+            Ok(String::from("Stopped announcing milestones"))
+        }
+        MilestonesAction::Channel => {
+            settings.channel_thresholds = parse_thresholds(args)?;
+            Ok(String::from("Updated channel milestones"))
+        }
+        MilestonesAction::Author => {
+            settings.author_channel_thresholds = parse_thresholds(args)?;
+            Ok(String::from("Updated author milestones"))
+        }
+        MilestonesAction::Server => {
+            settings.author_server_thresholds = parse_thresholds(args)?;
+            Ok(String::from("Updated server milestones"))
+        }
+    }
+}
+
+async fn update_milestone_settings(
+    ctx: &Context,
+    server_id: &GuildId,
+    action: &MilestonesAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let settings = &mut store
+        .get_server_data_mut_maybe_create(server_id)
+        .settings
+        .milestones;
+    let mut new_settings = settings.clone();
+    let reply = apply_action(&mut new_settings, action, args)?;
+    *settings = new_settings;
+    let description = describe_settings(settings);
+    match reply.is_empty() {
+        true => Ok(description),
+        false => Ok(format!("{}\n{}", reply, description)),
+    }
+}
+
+#[command("milestones")]
+#[usage("<action> [<#channel name> | <word counts>]")]
+#[description("Celebrate word count milestones with a post in an announcement channel. There are separate thresholds for a channel's total, an author's total in a channel, and an author's total across the server, each only announced once. Giving no word counts turns that kind off. Changes need the MasterScrivener role. Actions: show|announce-in|off|channel|author|server")]
+#[example("announce-in #announcements")]
+#[example("channel 50000 100000 250000")]
+#[example("author 10000 50000")]
+#[example("server")]
+#[example("show")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn milestones(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<MilestonesAction>() {
+            Ok(action) => {
+                let allowed = action == MilestonesAction::Show
+                    || author_is_in_allowed_roles(ctx, &server_id, &msg.author).await;
+                if allowed {
+                    match update_milestone_settings(ctx, &server_id, &action, &mut args).await {
+                        Ok(reply) => reply,
+                        Err(e) => format!(
+                            "Error with command arguments, try [help milestones]\nError:{}",
+                            e
+                        ),
+                    }
+                } else {
+                    format!(
+                        "Changing milestones is only available to those with the role {}",
+                        ALLOWED_ROLES[0]
+                    )
+                }
+            }
+            Err(e) => format!(
+                "Error with command arguments, try [help milestones]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = MILESTONES_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = MilestonesAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
@@ -1,7 +1,30 @@
+pub mod archive;
+pub mod chapters;
+pub mod characters;
+pub mod charts;
+pub mod digest;
 pub mod dump_messages;
+pub mod glossary;
+pub mod goals;
+pub mod imitate;
 pub mod init_channel;
+pub mod leaderboard;
+pub mod milestones;
+pub mod mood;
+pub mod ooc;
+pub mod posts;
+pub mod reactions;
+pub mod reminders;
+pub mod search;
 pub mod server_summary;
 pub mod show_channels;
 pub mod show_stats;
+pub mod signature_words;
+pub mod speakers;
+pub mod stemming;
+pub mod stop_words;
+pub mod stories;
+pub mod turns;
 pub mod word_cloud;
+pub mod word_lookup;
 pub mod feedback;
\ No newline at end of file
@@ -0,0 +1,207 @@
+use crate::sentiment::{describe_tone, SentimentTotals};
+use crate::state::{ChannelData, StoreData};
+use crate::stories::StatsTarget;
+use chrono::{Datelike, Duration, NaiveDate};
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::collections::BTreeMap;
+
+const MAX_PERIODS: usize = 12;
+// Stories running longer than this are shown by month rather than by week
+const MAX_WEEKLY_SPAN_DAYS: i64 = 16 * 7;
+// Authors need this many posts with some sentiment before they're ranked
+const MIN_AUTHOR_MESSAGES: usize = 5;
+const MAX_AUTHORS: usize = 3;
+// Characters for each side of the chart, a score of +-0.5 or more fills one
+const BAR_WIDTH: usize = 10;
+
+// Daily totals added up by week (from Monday) or by month, keyed by the first day of each
+fn periods(
+    daily: &BTreeMap<NaiveDate, SentimentTotals>,
+    monthly: bool,
+) -> BTreeMap<NaiveDate, SentimentTotals> {
+    let mut periods: BTreeMap<NaiveDate, SentimentTotals> = BTreeMap::new();
+    for (day, totals) in daily.iter() {
+        let start = match monthly {
+            true => day.with_day(1).unwrap(),
+            false => *day - Duration::days(day.weekday().num_days_from_monday() as i64),
+        };
+        periods.entry(start).or_default().merge(totals);
+    }
+    periods
+}
+
+fn bar_line(label: &str, average: f64) -> String {
+    let length = ((average.abs() * 2.0 * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+    let bar = "█".repeat(length);
+    let (negative, positive) = match average < 0.0 {
+        true => (bar, String::new()),
+        false => (String::new(), bar),
+    };
+    format!(
+        "{} {:+.2} {:>width$}|{}\n",
+        label,
+        average,
+        negative,
+        positive,
+        width = BAR_WIDTH
+    )
+}
+
+fn make_mood_string(title: &str, channel_data: &ChannelData) -> String {
+    let daily = channel_data.general_stats.daily_sentiment();
+    let overall = match channel_data.general_stats.sentiment().average() {
+        Some(overall) => overall,
+        None => return format!("There's nothing to judge the mood of {} by yet", title),
+    };
+    let mut builder = MessageBuilder::new();
+    builder.push("The mood of ").push(title).push(format!(
+        " is {} overall ({:+.2}, from -1 to 1)\n",
+        describe_tone(overall),
+        overall
+    ));
+    let monthly = match (daily.keys().next(), daily.keys().last()) {
+        (Some(first), Some(last)) => (*last - *first).num_days() > MAX_WEEKLY_SPAN_DAYS,
+        _ => false,
+    };
+    let periods = periods(daily, monthly);
+    let mut chart = String::new();
+    for (start, totals) in periods
+        .iter()
+        .skip(periods.len().saturating_sub(MAX_PERIODS))
+    {
+        if let Some(average) = totals.average() {
+            let label = match monthly {
+                true => start.format("%b %Y").to_string(),
+                false => start.format("%Y-%m-%d").to_string(),
+            };
+            chart.push_str(&bar_line(&label, average));
+        }
+    }
+    builder
+        .push_bold_line(match monthly {
+            true => "By month",
+            false => "By week",
+        })
+        .push_codeblock(chart, None);
+
+    let mut authors: Vec<(&User, f64)> = channel_data
+        .author_stats
+        .iter()
+        .filter_map(|(author, stats)| {
+            let totals = stats.sentiment();
+            match totals.messages >= MIN_AUTHOR_MESSAGES {
+                true => Some((author, totals.average()?)),
+                false => None,
+            }
+        })
+        .collect();
+    if authors.len() > 1 {
+        authors.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        let shown = MAX_AUTHORS.min(authors.len() / 2);
+        builder.push_bold_line("Darkest prose");
+        for (author, average) in authors.iter().take(shown) {
+            builder
+                .push_safe(&author.name)
+                .push(format!(" {:+.2}\n", average));
+        }
+        builder.push_bold_line("Lightest prose");
+        for (author, average) in authors.iter().rev().take(shown) {
+            builder
+                .push_safe(&author.name)
+                .push(format!(" {:+.2}\n", average));
+        }
+    }
+    builder.build()
+}
+
+async fn get_mood(ctx: &Context, server_id: GuildId, target: &StatsTarget) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    match target {
+        StatsTarget::Channel(channel_id) => match store.get_channel_data(&(server_id, *channel_id))
+        {
+            Some(channel_data) => make_mood_string(
+                &MessageBuilder::new().channel(channel_id).build(),
+                channel_data,
+            ),
+            None => String::from("Channel not initialised, use [init-channel] to add it"),
+        },
+        StatsTarget::Story(name) => {
+            let server_data = store.get_server_data(&server_id);
+            let story_data = server_data.and_then(|server_data| {
+                Some((server_data.get_story(name)?, server_data.story_data(name)?))
+            });
+            match story_data {
+                Some((story, story_data)) => make_mood_string(
+                    &MessageBuilder::new()
+                        .push("the story ")
+                        .push_bold_safe(&story.name)
+                        .build(),
+                    &story_data,
+                ),
+                None => format!("There's no story called {}, see [story list]", name),
+            }
+        }
+        StatsTarget::Chapter(channel_id, title) => {
+            let chapter_data = store
+                .get_channel_data(&(server_id, *channel_id))
+                .and_then(|channel_data| channel_data.chapter_data(title));
+            match chapter_data {
+                Some(chapter_data) => make_mood_string(
+                    &MessageBuilder::new()
+                        .push_bold_safe(title)
+                        .push(" in ")
+                        .channel(channel_id)
+                        .build(),
+                    &chapter_data,
+                ),
+                None => format!("There's no chapter called {}, see [chapter list]", title),
+            }
+        }
+    }
+}
+
+#[command("mood")]
+#[usage("<#channel name | story name>")]
+#[description("See how the tone of a channel or story has shifted over time, and whose prose is darkest or lightest. Each post is scored by the words in it, from -1 (bleakest) to 1 (brightest), and only posts since this was added count")]
+#[example("#the-fall-of-rome")]
+#[example("\"Fall of Rome\"")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn mood(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match StatsTarget::single(&mut args) {
+            Ok(target) => get_mood(ctx, server_id, &target).await,
+            Err(e) => format!("Error with command arguments, try [help mood]\nError:{}", e),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn days_are_grouped_into_weeks_or_months() {
+    let mut daily = BTreeMap::new();
+    for (day, score) in [(5, -0.5), (6, 0.1), (14, 0.2)].iter() {
+        let mut totals = SentimentTotals::default();
+        totals.add(*score);
+        daily.insert(NaiveDate::from_ymd(2026, 10, *day), totals);
+    }
+    let weekly = periods(&daily, false);
+    let week_starts: Vec<u32> = weekly.keys().map(|start| start.day()).collect();
+    assert_eq!(week_starts, vec![5, 12]);
+    assert_eq!(weekly[&NaiveDate::from_ymd(2026, 10, 5)].messages, 2);
+    let monthly = periods(&daily, true);
+    assert_eq!(monthly.len(), 1);
+    assert_eq!(bar_line("Oct", -0.25), "Oct -0.25      █████|\n");
+}
@@ -0,0 +1,225 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::ooc::OocRules;
+use crate::state::{StoreData, StoryKey};
+use crate::utils::helpers::parse_on_off;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum OocAction {
+    Show,
+    Common,
+    Clear,
+    AddBracket,
+    RemoveBracket,
+    AddPrefix,
+    RemovePrefix,
+    MinWords,
+    Ignore,
+    Unignore,
+    IgnoreBots,
+}
+impl OocAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::Common => "common",
+            Self::Clear => "clear",
+            Self::AddBracket => "add-bracket",
+            Self::RemoveBracket => "remove-bracket",
+            Self::AddPrefix => "add-prefix",
+            Self::RemovePrefix => "remove-prefix",
+            Self::MinWords => "min-words",
+            Self::Ignore => "ignore",
+            Self::Unignore => "unignore",
+            Self::IgnoreBots => "ignore-bots",
+        }
+    }
+}
+impl FromStr for OocAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid ooc action {}", s)),
+        }
+    }
+}
+
+fn describe_rules(channel_id: ChannelId, rules: &OocRules) -> String {
+    let brackets: Vec<String> = rules
+        .brackets
+        .iter()
+        .map(|(open, close)| format!("{} {}", open, close))
+        .collect();
+    let ignored_users = rules
+        .ignored_users
+        .iter()
+        .fold(MessageBuilder::new(), |mut builder, user_id| {
+            builder.mention(user_id).push(" ");
+            builder
+        })
+        .build();
+    MessageBuilder::new()
+        .push("Out of character rules for ")
+        .channel(channel_id)
+        .newline()
+        .push_line_safe(format!("Brackets: {}", brackets.join(", ")))
+        .push_line_safe(format!("Line prefixes: {}", rules.line_prefixes.join(", ")))
+        .push_line(format!("Minimum words: {}", rules.min_words))
+        .push_line(format!("Ignore bots: {}", rules.ignore_bots))
+        .push("Ignored users: ")
+        .push(ignored_users)
+        .build()
+}
+
+// Applies [action] to the rules in place, returning the reply on success
+fn apply_action(
+    rules: &mut OocRules,
+    action: &OocAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    match action {
+        OocAction::Show => Ok(String::new()),
+        OocAction::Common => {
+            *rules = OocRules::common();
+            Ok(String::from("Using the common out of character rules"))
+        }
+        OocAction::Clear => {
+            *rules = OocRules::default();
+            Ok(String::from("Cleared out of character rules"))
+        }
+        OocAction::AddBracket | OocAction::RemoveBracket => {
+            let open = args.single::<String>().map_err(|e| e.to_string())?;
+            let close = args.single::<String>().map_err(|e| e.to_string())?;
+            let bracket = (open, close);
+            rules.brackets.retain(|b| b != &bracket);
+            if action == &OocAction::AddBracket {
+                rules.brackets.push(bracket);
+            }
+            Ok(String::from("Updated brackets"))
+        }
+        OocAction::AddPrefix | OocAction::RemovePrefix => {
+            let prefix = args.single::<String>().map_err(|e| e.to_string())?;
+            let prefix = prefix.to_lowercase();
+            rules.line_prefixes.retain(|p| p != &prefix);
+            if action == &OocAction::AddPrefix {
+                rules.line_prefixes.push(prefix);
+            }
+            Ok(String::from("Updated line prefixes"))
+        }
+        OocAction::MinWords => {
+            rules.min_words = args.single::<usize>().map_err(|e| e.to_string())?;
+            Ok(format!("Minimum words set to {}", rules.min_words))
+        }
+        OocAction::Ignore | OocAction::Unignore => {
+            let user_id = args.single::<UserId>().map_err(|e| e.to_string())?;
+            if action == &OocAction::Ignore {
+                rules.ignored_users.insert(user_id);
+            } else {
+                rules.ignored_users.remove(&user_id);
+            }
+            Ok(String::from("Updated ignored users"))
+        }
+        OocAction::IgnoreBots => {
+            let on_off = args.single::<String>().map_err(|e| e.to_string())?;
+            rules.ignore_bots = parse_on_off(&on_off)?;
+            Ok(format!("Ignore bots set to {}", rules.ignore_bots))
+        }
+    }
+}
+
+async fn update_ooc_rules(
+    ctx: &Context,
+    story_key: &StoryKey,
+    action: &OocAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    match store.get_channel_data_mut(story_key) {
+        Some(channel_data) => {
+            let rules = &mut channel_data.settings.ooc_rules;
+            let mut new_rules = rules.clone();
+            let reply = apply_action(&mut new_rules, action, args)?;
+            *rules = new_rules;
+            let description = describe_rules(story_key.1, rules);
+            match reply.is_empty() {
+                true => Ok(description),
+                false => Ok(format!("{}\n{}", reply, description)),
+            }
+        }
+        None => Err(String::from(
+            "Channel not initialised, use [init-channel] to add it",
+        )),
+    }
+}
+
+#[command("ooc")]
+#[usage("<#channel name> <action> [values]")]
+#[description("Configure how out of character (OOC) chatter is picked out of a channel's story posts. OOC words are counted separately and left out of everything else. Rules only apply to messages from when they are set. Brackets take an opening and closing pair, line prefixes are matched ignoring case, messages with fewer than min-words words are all OOC, and ignored users and bots are always OOC. Use [common] for the usual (( )), [[ ]], // and OOC: rules. Changes need the MasterScrivener role. Actions: show|common|clear|add-bracket|remove-bracket|add-prefix|remove-prefix|min-words|ignore|unignore|ignore-bots")]
+#[example("#the-fall-of-rome common")]
+#[example("#the-fall-of-rome add-bracket { }")]
+#[example("#the-fall-of-rome add-prefix ooc")]
+#[example("#the-fall-of-rome min-words 5")]
+#[example("#the-fall-of-rome ignore @Caligula")]
+#[example("#the-fall-of-rome ignore-bots off")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn ooc(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        let maybe_channel_id = args.single::<ChannelId>();
+        let maybe_action = args.single::<OocAction>();
+        match (maybe_channel_id, maybe_action) {
+            (Ok(channel_id), Ok(action)) => {
+                let story_key: StoryKey = (server_id, channel_id);
+                let allowed = action == OocAction::Show
+                    || author_is_in_allowed_roles(ctx, &server_id, &msg.author).await;
+                if allowed {
+                    match update_ooc_rules(ctx, &story_key, &action, &mut args).await {
+                        Ok(reply) => reply,
+                        Err(e) => {
+                            format!("Error with command arguments, try [help ooc]\nError:{}", e)
+                        }
+                    }
+                } else {
+                    format!(
+                        "Changing out of character rules is only available to those with the role {}",
+                        ALLOWED_ROLES[0]
+                    )
+                }
+            }
+            (Err(e), _) => format!("Error with command arguments, try [help ooc]\nError:{}", e),
+            (_, Err(e)) => format!("Error with command arguments, try [help ooc]\nError:{}", e),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = OOC_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = OocAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
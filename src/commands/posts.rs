@@ -0,0 +1,98 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::state::{ServerSettings, StoreData};
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+// Longer than this and separate posts would start being merged
+const MAX_WINDOW_MINUTES: u64 = 60;
+
+async fn set_post_window(ctx: &Context, server_id: &GuildId, minutes: u64) {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    store
+        .get_server_data_mut_maybe_create(server_id)
+        .settings
+        .post_window_minutes = minutes;
+}
+
+async fn get_post_window(ctx: &Context, server_id: &GuildId) -> u64 {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    match store.get_server_data(server_id) {
+        Some(server_data) => server_data.settings.post_window_minutes,
+        None => ServerSettings::default().post_window_minutes,
+    }
+}
+
+fn describe_post_window(minutes: u64) -> String {
+    match minutes {
+        0 => String::from("Every message counts as a post of its own on this server"),
+        1 => String::from("Messages from the same author at most a minute apart count as one post on this server"),
+        minutes => format!(
+            "Messages from the same author at most {} minutes apart count as one post on this server",
+            minutes
+        ),
+    }
+}
+
+fn parse_minutes(arg: &str) -> std::result::Result<u64, String> {
+    if arg == "off" {
+        return Ok(0);
+    }
+    match arg.parse::<u64>() {
+        Ok(minutes) if minutes <= MAX_WINDOW_MINUTES => Ok(minutes),
+        Ok(_) => Err(format!(
+            "The window can be at most {} minutes",
+            MAX_WINDOW_MINUTES
+        )),
+        Err(_) => Err(format!("Expected a number of minutes or off, got {}", arg)),
+    }
+}
+
+#[command("post-window")]
+#[usage("[<minutes>|off]")]
+#[description("Count a post split over several messages (e.g. because of Discord's length limit) as one post, when they're from the same author and no further apart than this. Used for message counts, turn nudges and exported manuscripts, word counts don't change. Without an argument, shows the current setting")]
+#[example("10")]
+#[example("off")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn post_window(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.len() {
+            0 => describe_post_window(get_post_window(ctx, &server_id).await),
+            1 => match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                true => match parse_minutes(&args.single::<String>().unwrap()) {
+                    Ok(minutes) => {
+                        set_post_window(ctx, &server_id, minutes).await;
+                        describe_post_window(minutes)
+                    }
+                    Err(e) => format!(
+                        "Error with command arguments, try [help post-window]\nError:{}",
+                        e
+                    ),
+                },
+                false => format!(
+                    "Changing this is only available to those with the role {}",
+                    ALLOWED_ROLES[0]
+                ),
+            },
+            _ => String::from("Invalid number of args, try [help post-window]"),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
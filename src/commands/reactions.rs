@@ -0,0 +1,320 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::reactions::{describe_reactions, StarboardSettings};
+use crate::state::{ChannelData, StoreData};
+use crate::stories::StatsTarget;
+use crate::utils::helpers::message_link;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+const DEFAULT_ENTRIES: usize = 10;
+const MAX_ENTRIES: usize = 25;
+// How much of each post is shown
+const EXCERPT_CHARS: usize = 120;
+
+// The start of [content], cut at a space if it's too long
+fn excerpt(content: &str) -> String {
+    let content = content.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if content.chars().count() <= EXCERPT_CHARS {
+        return content;
+    }
+    let cut: String = content.chars().take(EXCERPT_CHARS).collect();
+    match cut.rfind(' ') {
+        Some(space) => format!("{}...", &cut[..space]),
+        None => format!("{}...", cut),
+    }
+}
+
+fn make_hall_of_fame(
+    title: &str,
+    server_id: GuildId,
+    channels: &[(ChannelId, &ChannelData)],
+    entries: usize,
+) -> String {
+    let mut posts: Vec<_> = channels
+        .iter()
+        .flat_map(|(channel_id, channel_data)| {
+            channel_data
+                .reactions
+                .posts()
+                .map(move |(message_id, total, reactions)| {
+                    (*channel_id, channel_data, message_id, total, reactions)
+                })
+        })
+        .collect();
+    if posts.is_empty() {
+        return format!("Nobody has reacted to anything in {} yet", title);
+    }
+    // Newer posts first when they have the same number of reactions
+    posts.sort_by(|a, b| b.3.cmp(&a.3).then(b.2.cmp(&a.2)));
+    let mut builder = MessageBuilder::new();
+    builder.push("Hall of fame for ").push(title).newline();
+    for (i, (channel_id, channel_data, message_id, _, reactions)) in
+        posts.iter().take(entries).enumerate()
+    {
+        builder.push(format!("\n{}. {} ", i + 1, describe_reactions(reactions)));
+        // Posts from before the search index existed have no author or text to show
+        if let Some(post) = channel_data.search_index.get(message_id) {
+            builder.push_bold_safe(&post.author_name).push(" ");
+        }
+        if channels.len() > 1 {
+            builder.push("in ").channel(channel_id).push(" ");
+        }
+        builder.push_line(format!(
+            "<{}>",
+            message_link(server_id, *channel_id, *message_id)
+        ));
+        if let Some(post) = channel_data.search_index.get(message_id) {
+            builder.push_quote_line_safe(excerpt(&post.content));
+        }
+    }
+    builder.build()
+}
+
+async fn get_hall_of_fame(
+    ctx: &Context,
+    server_id: GuildId,
+    target: &StatsTarget,
+    entries: usize,
+) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let server_data = match store.get_server_data(&server_id) {
+        Some(server_data) => server_data,
+        None => return String::from("No channels have been initialised on this server"),
+    };
+    match target {
+        StatsTarget::Channel(channel_id) => match server_data.get_channel_data(channel_id) {
+            Some(channel_data) => make_hall_of_fame(
+                &MessageBuilder::new().channel(channel_id).build(),
+                server_id,
+                &[(*channel_id, channel_data)],
+                entries,
+            ),
+            None => String::from("Channel not initialised, use [init-channel] to add it"),
+        },
+        StatsTarget::Story(name) => match server_data.get_story(name) {
+            Some(story) => {
+                let channels: Vec<(ChannelId, &ChannelData)> = story
+                    .channels
+                    .iter()
+                    .filter_map(|channel_id| {
+                        Some((*channel_id, server_data.get_channel_data(channel_id)?))
+                    })
+                    .collect();
+                make_hall_of_fame(
+                    &MessageBuilder::new()
+                        .push("the story ")
+                        .push_bold_safe(&story.name)
+                        .build(),
+                    server_id,
+                    &channels,
+                    entries,
+                )
+            }
+            None => format!("There's no story called {}, see [story list]", name),
+        },
+        StatsTarget::Chapter(..) => {
+            String::from("The hall of fame is only kept for channels and stories")
+        }
+    }
+}
+
+#[command("hall-of-fame")]
+#[usage("<#channel name | story name> [<how many>]")]
+#[description("List the posts in a channel or story with the most reactions, with a link to each. Only reactions since this was added count")]
+#[example("#the-fall-of-rome")]
+#[example("\"Fall of Rome\" 20")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn hall_of_fame(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match StatsTarget::single(&mut args) {
+            Ok(target) => {
+                let entries = args
+                    .single::<usize>()
+                    .unwrap_or(DEFAULT_ENTRIES)
+                    .clamp(1, MAX_ENTRIES);
+                get_hall_of_fame(ctx, server_id, &target, entries).await
+            }
+            Err(e) => format!(
+                "Error with command arguments, try [help hall-of-fame]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum StarboardAction {
+    Show,
+    Channel,
+    Threshold,
+    Off,
+}
+impl StarboardAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::Channel => "channel",
+            Self::Threshold => "threshold",
+            Self::Off => "off",
+        }
+    }
+}
+impl FromStr for StarboardAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid starboard action {}", s)),
+        }
+    }
+}
+
+fn describe_settings(settings: &StarboardSettings) -> String {
+    let mut builder = MessageBuilder::new();
+    match settings.channel {
+        Some(channel_id) => builder.push("Reposting to ").channel(channel_id),
+        None => builder.push("There's no starboard, use [channel] to pick one"),
+    };
+    builder
+        .newline()
+        .push(format!(
+            "Posts are reposted once they have {} reactions",
+            settings.threshold
+        ))
+        .build()
+}
+
+// Applies [action] to the settings in place, returning the reply on success
+fn apply_action(
+    settings: &mut StarboardSettings,
+    action: &StarboardAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    match action {
+        StarboardAction::Show => Ok(String::new()),
+        StarboardAction::Channel => {
+            settings.channel = Some(args.single::<ChannelId>().map_err(|e| e.to_string())?);
+            Ok(String::from("Updated the starboard channel"))
+        }
+        StarboardAction::Threshold => match args.single::<usize>() {
+            Ok(threshold) if threshold > 0 => {
+                settings.threshold = threshold;
+                Ok(String::from("Updated the threshold"))
+            }
+            Ok(_) => Err(String::from("The threshold needs to be more than 0")),
+            Err(e) => Err(format!("Expected a number of reactions: {}", e)),
+        },
+        StarboardAction::Off => {
+            settings.channel = None;
+            Ok(String::from("Stopped reposting to the starboard"))
+        }
+    }
+}
+
+async fn update_starboard_settings(
+    ctx: &Context,
+    server_id: &GuildId,
+    action: &StarboardAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let settings = &mut store
+        .get_server_data_mut_maybe_create(server_id)
+        .settings
+        .starboard;
+    let mut new_settings = settings.clone();
+    let reply = apply_action(&mut new_settings, action, args)?;
+    *settings = new_settings;
+    let description = describe_settings(settings);
+    match reply.is_empty() {
+        true => Ok(description),
+        false => Ok(format!("{}\n{}", reply, description)),
+    }
+}
+
+#[command("starboard")]
+#[usage("<action> [<#channel name> | <reactions>]")]
+#[description("Repost story posts to a starboard channel once they get enough reactions, each post only once. Changes need the MasterScrivener role. Actions: show|channel|threshold|off")]
+#[example("channel #starboard")]
+#[example("threshold 7")]
+#[example("show")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn starboard(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<StarboardAction>() {
+            Ok(action) => {
+                let allowed = action == StarboardAction::Show
+                    || author_is_in_allowed_roles(ctx, &server_id, &msg.author).await;
+                if allowed {
+                    match update_starboard_settings(ctx, &server_id, &action, &mut args).await {
+                        Ok(reply) => reply,
+                        Err(e) => format!(
+                            "Error with command arguments, try [help starboard]\nError:{}",
+                            e
+                        ),
+                    }
+                } else {
+                    format!(
+                        "Changing the starboard is only available to those with the role {}",
+                        ALLOWED_ROLES[0]
+                    )
+                }
+            }
+            Err(e) => format!(
+                "Error with command arguments, try [help starboard]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = STARBOARD_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = StarboardAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
+
+#[test]
+fn long_posts_are_cut_between_words() {
+    assert_eq!(excerpt("Short\n\npost"), "Short post");
+    let long = "word ".repeat(40);
+    let cut = excerpt(&long);
+    assert!(cut.ends_with("word..."));
+    assert!(cut.chars().count() <= EXCERPT_CHARS + 3);
+}
@@ -0,0 +1,187 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::reminders::{ReminderSettings, MAX_REMINDERS};
+use crate::state::{ChannelData, StoreData, StoryKey};
+use crate::utils::helpers::parse_on_off;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum ReminderAction {
+    Show,
+    After,
+    Off,
+    Ping,
+}
+impl ReminderAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::After => "after",
+            Self::Off => "off",
+            Self::Ping => "ping",
+        }
+    }
+}
+impl FromStr for ReminderAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid reminder action {}", s)),
+        }
+    }
+}
+
+fn describe_settings(channel_id: ChannelId, channel_data: &ChannelData) -> String {
+    let settings = &channel_data.settings.reminders;
+    let mut builder = MessageBuilder::new();
+    builder
+        .push("Stall reminders for ")
+        .channel(channel_id)
+        .newline();
+    match settings.remind_after_days {
+        Some(days) => builder.push_line(format!(
+            "Reminding after {} day(s) without a post, then waiting twice as long each time, up to {} reminders",
+            days, MAX_REMINDERS
+        )),
+        None => builder.push_line("Reminders are off"),
+    };
+    builder.push_line(match settings.ping_participants {
+        true => "Pinging everyone who's been writing there",
+        false => "Not pinging anyone",
+    });
+    if let Some(due) = channel_data.next_reminder_due() {
+        builder.push(format!(
+            "Next reminder: {}",
+            due.format("%Y-%m-%d at %H:%M UTC")
+        ));
+    }
+    builder.build()
+}
+
+// Applies [action] to the settings in place, returning the reply on success
+fn apply_action(
+    settings: &mut ReminderSettings,
+    action: &ReminderAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    match action {
+        ReminderAction::Show => Ok(String::new()),
+        ReminderAction::After => {
+            let days = args.single::<u64>().map_err(|e| e.to_string())?;
+            if days == 0 {
+                return Err(String::from("Reminders need at least a day of quiet"));
+            }
+            settings.remind_after_days = Some(days);
+            Ok(String::from("Updated when to remind"))
+        }
+        ReminderAction::Off => {
+            settings.remind_after_days = None;
+            Ok(String::from("Stopped stall reminders"))
+        }
+        ReminderAction::Ping => {
+            let value = args.single::<String>().map_err(|e| e.to_string())?;
+            settings.ping_participants = parse_on_off(&value)?;
+            Ok(String::from("Updated pinging"))
+        }
+    }
+}
+
+async fn update_reminder_settings(
+    ctx: &Context,
+    story_key: &StoryKey,
+    action: &ReminderAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    match store.get_channel_data_mut(story_key) {
+        Some(channel_data) => {
+            let settings = &mut channel_data.settings.reminders;
+            let mut new_settings = settings.clone();
+            let reply = apply_action(&mut new_settings, action, args)?;
+            *settings = new_settings;
+            let description = describe_settings(story_key.1, channel_data);
+            match reply.is_empty() {
+                true => Ok(description),
+                false => Ok(format!("{}\n{}", reply, description)),
+            }
+        }
+        None => Err(String::from(
+            "Channel not initialised, use [init-channel] to add it",
+        )),
+    }
+}
+
+#[command("reminders")]
+#[usage("<#channel name> <action> [<days> | on | off]")]
+#[description("Post a gentle reminder in a story's channel once nobody has posted there for a number of days. After each reminder the wait doubles, and they stop after a few until someone posts again. [ping] also mentions everyone who's been writing there. Changes need the MasterScrivener role. Actions: show|after|off|ping")]
+#[example("#the-fall-of-rome after 10")]
+#[example("#the-fall-of-rome ping on")]
+#[example("#the-fall-of-rome off")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn reminders(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        let maybe_channel_id = args.single::<ChannelId>();
+        let maybe_action = args.single::<ReminderAction>();
+        match (maybe_channel_id, maybe_action) {
+            (Ok(channel_id), Ok(action)) => {
+                let story_key: StoryKey = (server_id, channel_id);
+                let allowed = action == ReminderAction::Show
+                    || author_is_in_allowed_roles(ctx, &server_id, &msg.author).await;
+                if allowed {
+                    match update_reminder_settings(ctx, &story_key, &action, &mut args).await {
+                        Ok(reply) => reply,
+                        Err(e) => format!(
+                            "Error with command arguments, try [help reminders]\nError:{}",
+                            e
+                        ),
+                    }
+                } else {
+                    format!(
+                        "Changing reminders is only available to those with the role {}",
+                        ALLOWED_ROLES[0]
+                    )
+                }
+            }
+            (Err(e), _) => format!(
+                "Error with command arguments, try [help reminders]\nError:{}",
+                e
+            ),
+            (_, Err(e)) => format!(
+                "Error with command arguments, try [help reminders]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = REMINDERS_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = ReminderAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
@@ -0,0 +1,125 @@
+use crate::search::{search as search_indexes, Query};
+use crate::state::StoreData;
+use crate::utils::helpers::message_link;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::{parse_channel, parse_username, MessageBuilder};
+
+const MAX_RESULTS: usize = 5;
+
+// Picks a channel and a user mention out of [text], anything else is what to search for
+fn split_filters(text: &str) -> (String, Option<ChannelId>, Option<UserId>) {
+    let mut channel_id = None;
+    let mut user_id = None;
+    let mut query_words = vec![];
+    for word in text.split_whitespace() {
+        if let Some(id) = parse_channel(word) {
+            channel_id = Some(ChannelId(id));
+        } else if let Some(id) = parse_username(word) {
+            user_id = Some(UserId(id));
+        } else {
+            query_words.push(word);
+        }
+    }
+    (query_words.join(" "), channel_id, user_id)
+}
+
+async fn search_posts(
+    ctx: &Context,
+    server_id: GuildId,
+    text: &str,
+) -> std::result::Result<String, String> {
+    let (query_text, channel_id, user_id) = split_filters(text);
+    let query = Query::parse(&query_text)?;
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let server_data = match store.get_server_data(&server_id) {
+        Some(server_data) => server_data,
+        None => {
+            return Err(String::from(
+                "No channels have been initialised on this server",
+            ))
+        }
+    };
+    if let Some(channel_id) = channel_id {
+        match server_data.get_channel_data(&channel_id) {
+            Some(channel_data) if channel_data.archive().is_none() => {
+                return Err(String::from(
+                    "Only archived channels can be searched, use [archive on] to start",
+                ))
+            }
+            Some(_) => {}
+            None => {
+                return Err(String::from(
+                    "Channel not initialised, use [init-channel] to add it",
+                ))
+            }
+        }
+    }
+    let indexes = server_data.search_indexes(channel_id);
+    let hits = search_indexes(&indexes, &query, user_id, MAX_RESULTS);
+    let mut builder = MessageBuilder::new();
+    if hits.is_empty() {
+        builder.push("Nothing found for ").push_safe(&query_text);
+    } else {
+        builder
+            .push("Best matches for ")
+            .push_safe(&query_text)
+            .push("\n");
+    }
+    for (i, hit) in hits.iter().enumerate() {
+        builder
+            .push(format!("\n{}. ", i + 1))
+            .push_bold_safe(&hit.message.author_name)
+            .push(" in ")
+            .channel(hit.channel_id)
+            .push(format!(
+                " on {} <{}>\n",
+                hit.message.timestamp.format("%Y-%m-%d"),
+                message_link(server_id, hit.channel_id, hit.message_id)
+            ))
+            .push_quote_line_safe(hit.snippet(&query));
+    }
+    Ok(builder.build())
+}
+
+#[command("search")]
+#[usage("<words or \"a phrase\"> [<#archived channel name>] [<@user mention>]")]
+#[description("Find story posts with all of the words given, across every archived channel or just one, and optionally only by one author. Put words in quotes to find them together in that order. Shows the best few matches with a link to each. Search is opt-in, story text is only kept for channels with [archive] on so only those can be searched")]
+#[example("drone blew up")]
+#[example("\"blew up the drone\" #the-fall-of-rome @Ana")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn search(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match search_posts(ctx, server_id, args.rest()).await {
+            Ok(reply) => reply,
+            Err(e) => format!(
+                "Error with command arguments, try [help search]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn mentions_are_filters_not_search_words() {
+    assert_eq!(
+        split_filters("\"blew up\" <#123>  drone <@!456>"),
+        (
+            String::from("\"blew up\" drone"),
+            Some(ChannelId(123)),
+            Some(UserId(456))
+        )
+    );
+}
@@ -1,5 +1,6 @@
 use crate::state::{StoreData, StoryKey};
 use crate::stats::WordStats;
+use crate::stories::StatsTarget;
 use crate::utils::iterators::SortedHashMap;
 use crate::utils::trait_extensions::MessageBuilderExt;
 use chrono::{DateTime, NaiveDateTime, Utc};
@@ -11,9 +12,12 @@ use serenity::utils::MessageBuilder;
 use std::hash::Hash;
 use std::{cmp, collections::HashMap};
 
-async fn get_stats(channel_id: ChannelId, ctx: &Context, truncate_limit: Option<usize>) -> String {
-    let text_channel = channel_id.to_channel(&ctx).await.unwrap().guild().unwrap();
-    let story_key: StoryKey = (text_channel.guild_id, channel_id);
+pub async fn get_stats(
+    server_id: GuildId,
+    target: &StatsTarget,
+    ctx: &Context,
+    truncate_limit: Option<usize>,
+) -> String {
     let store_lock = {
         let data_read = ctx.data.read().await;
         data_read
@@ -22,13 +26,57 @@ async fn get_stats(channel_id: ChannelId, ctx: &Context, truncate_limit: Option<
             .clone()
     };
     let store = store_lock.read().unwrap();
-    match store.get_channel_data(&story_key) {
-        Some(channel_data) => channel_data.make_stats_string(&text_channel, truncate_limit),
-        None => format!("Channel not initialised, use [init-channel] to add it"),
+    match target {
+        StatsTarget::Channel(channel_id) => {
+            let story_key: StoryKey = (server_id, *channel_id);
+            match store.get_channel_data(&story_key) {
+                Some(channel_data) => channel_data.make_stats_string(
+                    &MessageBuilder::new().channel(channel_id).build(),
+                    truncate_limit,
+                    &store.word_filter(&story_key),
+                ),
+                None => format!("Channel not initialised, use [init-channel] to add it"),
+            }
+        }
+        StatsTarget::Story(name) => {
+            let server_data = store.get_server_data(&server_id);
+            let story_data = server_data.and_then(|server_data| {
+                Some((server_data.get_story(name)?, server_data.story_data(name)?))
+            });
+            match story_data {
+                Some((story, story_data)) => story_data.make_stats_string(
+                    &MessageBuilder::new()
+                        .push("the story ")
+                        .push_bold_safe(&story.name)
+                        .build(),
+                    truncate_limit,
+                    &store.word_filter_for_channels(&server_id, &story.channels),
+                ),
+                None => format!("There's no story called {}, see [story list]", name),
+            }
+        }
+        StatsTarget::Chapter(channel_id, title) => {
+            let story_key: StoryKey = (server_id, *channel_id);
+            let chapter_data = store
+                .get_channel_data(&story_key)
+                .and_then(|channel_data| channel_data.chapter_data(title));
+            match chapter_data {
+                Some(chapter_data) => chapter_data.make_stats_string(
+                    &MessageBuilder::new()
+                        .push_bold_safe(title)
+                        .push(" in ")
+                        .channel(channel_id)
+                        .build(),
+                    truncate_limit,
+                    &store.word_filter(&story_key),
+                ),
+                None => format!("There's no chapter called {}, see [chapter list]", title),
+            }
+        }
     }
 }
 
-fn get_truncate_limit(args: &mut Args) -> Option<usize> {
+pub fn get_truncate_limit(args: &mut Args) -> Option<usize> {
     // TODO: This default should be somewhere central, pluck it out of Context when needed?
     let default = Some(5);
     if args.len() > 0 {
@@ -48,22 +96,23 @@ fn get_truncate_limit(args: &mut Args) -> Option<usize> {
 }
 
 #[command("show-stats")]
-#[usage("<#channel name> [-full]")]
-#[description("Display stats for an initialised channel by name. Returns an error if channel hasn't been initialised. If there are lots of users the results will be truncated, provide -full to show all")]
+#[usage("<#channel name | story name> [-full]")]
+#[description("Display stats for an initialised channel by name, or for every channel in a story made with [story create] (in quotes if it has spaces). Returns an error if channel hasn't been initialised. Shows how much of each author's writing is dialogue (in quotes) rather than narration, and their most used words in dialogue. If there are lots of users the results will be truncated, provide -full to show all")]
 #[example("#the-fall-of-rome")]
+#[example("\"Fall of Rome\" -full")]
 #[only_in("guilds")] // Reminder: guild = server
 async fn show_stats(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let reply = if let Some(_server_id) = msg.guild_id {
-        if let Ok(channel_id) = args.single::<ChannelId>() {
+    let reply = if let Some(server_id) = msg.guild_id {
+        if let Ok(target) = StatsTarget::single(&mut args) {
             let truncate_limit = get_truncate_limit(&mut args);
-            let response = get_stats(channel_id, ctx, truncate_limit).await;
+            let response = get_stats(server_id, &target, ctx, truncate_limit).await;
             //send it
             if let Err(why) = msg.channel_id.say(&ctx.http, &response).await {
                 println!("Error sending message: {:?}", why);
             }
             None
         } else {
-            Some(String::from("1 Arg expected: Channel"))
+            Some(String::from("1 Arg expected: Channel or story name"))
         }
     } else {
         Some(String::from(
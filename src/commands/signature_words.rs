@@ -0,0 +1,99 @@
+use crate::state::{StoreData, StoryKey};
+use crate::stats::top_scored_words;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+async fn make_signature_words(
+    ctx: &Context,
+    server_id: &GuildId,
+    user_id: &UserId,
+    channel_id: Option<ChannelId>,
+) -> String {
+    let scores = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoreData in TypeMap.")
+                .clone()
+        };
+        let store = store_lock.read().unwrap();
+        match channel_id {
+            Some(channel_id) => {
+                let story_key: StoryKey = (*server_id, channel_id);
+                match store.get_channel_data(&story_key) {
+                    Some(channel_data) => {
+                        channel_data.signature_word_scores(user_id, &store.word_filter(&story_key))
+                    }
+                    None => {
+                        return String::from(
+                            "Channel not initialised, use [init-channel] to add it",
+                        )
+                    }
+                }
+            }
+            None => store.server_signature_word_scores(server_id, user_id),
+        }
+    };
+    let mut builder = MessageBuilder::new();
+    builder.push("Signature words for ").mention(user_id);
+    if let Some(channel_id) = channel_id {
+        builder.push(" in ").channel(channel_id);
+    }
+    match scores {
+        None => builder.push(": no recorded activity").build(),
+        Some(scores) if scores.is_empty() => builder
+            .push(": nothing stands out yet, there needs to be other writers to compare against")
+            .build(),
+        Some(scores) => builder
+            .push_line(":")
+            .push_safe(top_scored_words(&scores, 15))
+            .build(),
+    }
+}
+
+#[command("signature-words")]
+#[usage("<@user mention> [<#channel name>]")]
+#[description("Display the words that most set a user's writing apart from everyone else's, across the whole server or in one channel if given")]
+#[example("@Caligula")]
+#[example("@Caligula #the-fall-of-rome")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn signature_words(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match parse_args(&mut args) {
+            Ok((user_id, channel_id)) => {
+                make_signature_words(ctx, &server_id, &user_id, channel_id).await
+            }
+            Err(e) => e,
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+fn parse_args(args: &mut Args) -> std::result::Result<(UserId, Option<ChannelId>), String> {
+    let error_help_text = |e: &dyn std::fmt::Display| {
+        format!(
+            "Error with command arguments, try [help signature-words]\nError:{}",
+            e
+        )
+    };
+    match args.len() {
+        1 => match args.single::<UserId>() {
+            Ok(user_id) => Ok((user_id, None)),
+            Err(e) => Err(error_help_text(&e)),
+        },
+        2 => match (args.single::<UserId>(), args.single::<ChannelId>()) {
+            (Ok(user_id), Ok(channel_id)) => Ok((user_id, Some(channel_id))),
+            (Err(e), _) => Err(error_help_text(&e)),
+            (_, Err(e)) => Err(error_help_text(&e)),
+        },
+        _ => Err(String::from(
+            "Invalid number of args, try [help signature-words]",
+        )),
+    }
+}
@@ -0,0 +1,303 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::proxies::CharacterOwners;
+use crate::speakers::SpeakerTags;
+use crate::state::{ChannelData, StoreData, StoryKey};
+use crate::stats::{WordFilter, WordStats};
+use crate::stories::StatsTarget;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::collections::HashMap;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+const TOP_WORDS: usize = 8;
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum SpeakerTagAction {
+    Show,
+    Common,
+    Clear,
+    Add,
+    Remove,
+}
+impl SpeakerTagAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::Common => "common",
+            Self::Clear => "clear",
+            Self::Add => "add",
+            Self::Remove => "remove",
+        }
+    }
+}
+impl FromStr for SpeakerTagAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid speaker tag action {}", s)),
+        }
+    }
+}
+
+fn describe_tags(channel_id: ChannelId, tags: &SpeakerTags) -> String {
+    let mut builder = MessageBuilder::new();
+    builder
+        .push("Speaker tags for ")
+        .channel(channel_id)
+        .newline();
+    if tags.patterns.is_empty() {
+        builder.push("None, lines aren't attributed to characters");
+    }
+    for pattern in tags.patterns.iter() {
+        builder.push_mono_line_safe(pattern);
+    }
+    builder.build()
+}
+
+// Applies [action] to the tags in place, returning the reply on success
+fn apply_action(
+    tags: &mut SpeakerTags,
+    action: &SpeakerTagAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    match action {
+        SpeakerTagAction::Show => Ok(String::new()),
+        SpeakerTagAction::Common => {
+            *tags = SpeakerTags::common();
+            Ok(String::from("Using the common speaker tags"))
+        }
+        SpeakerTagAction::Clear => {
+            *tags = SpeakerTags::default();
+            Ok(String::from("Cleared speaker tags"))
+        }
+        SpeakerTagAction::Add | SpeakerTagAction::Remove => {
+            // The rest of the message, since patterns often have spaces and quotes in them
+            let pattern = String::from(args.rest().trim());
+            if pattern.is_empty() {
+                return Err(String::from("Expected a pattern"));
+            }
+            tags.patterns.retain(|p| p != &pattern);
+            if action == &SpeakerTagAction::Add {
+                SpeakerTags::validate(&pattern)?;
+                tags.patterns.push(pattern);
+            }
+            Ok(String::from("Updated speaker tags"))
+        }
+    }
+}
+
+async fn update_speaker_tags(
+    ctx: &Context,
+    story_key: &StoryKey,
+    action: &SpeakerTagAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    match store.get_channel_data_mut(story_key) {
+        Some(channel_data) => {
+            let tags = &mut channel_data.settings.speaker_tags;
+            let mut new_tags = tags.clone();
+            let reply = apply_action(&mut new_tags, action, args)?;
+            *tags = new_tags;
+            let description = describe_tags(story_key.1, tags);
+            match reply.is_empty() {
+                true => Ok(description),
+                false => Ok(format!("{}\n{}", reply, description)),
+            }
+        }
+        None => Err(String::from(
+            "Channel not initialised, use [init-channel] to add it",
+        )),
+    }
+}
+
+#[command("speaker-tags")]
+#[usage("<#channel name> <action> [<pattern>]")]
+#[description("Configure the speaker tags that say which character a line is from, so what each character says is counted for them (see [cast]). In a pattern {name} is the character, {time} is a time like 19:51, and everything else has to match exactly at the start of a line. The rest of the line is what they said, and it still counts for whoever posted it too. Use [common] for chat log **{name}** {time}: tags, script style {name}: \" has to be added as it also matches prose. Tags only apply to messages from when they are set. Changes need the MasterScrivener role. Actions: show|common|clear|add|remove")]
+#[example("#the-fall-of-rome common")]
+#[example("#the-fall-of-rome add [{name}]")]
+#[example("#the-fall-of-rome remove {name}: \"")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn speaker_tags(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        let maybe_channel_id = args.single::<ChannelId>();
+        let maybe_action = args.single::<SpeakerTagAction>();
+        match (maybe_channel_id, maybe_action) {
+            (Ok(channel_id), Ok(action)) => {
+                let story_key: StoryKey = (server_id, channel_id);
+                let allowed = action == SpeakerTagAction::Show
+                    || author_is_in_allowed_roles(ctx, &server_id, &msg.author).await;
+                if allowed {
+                    match update_speaker_tags(ctx, &story_key, &action, &mut args).await {
+                        Ok(reply) => reply,
+                        Err(e) => format!(
+                            "Error with command arguments, try [help speaker-tags]\nError:{}",
+                            e
+                        ),
+                    }
+                } else {
+                    format!(
+                        "Changing speaker tags is only available to those with the role {}",
+                        ALLOWED_ROLES[0]
+                    )
+                }
+            }
+            (Err(e), _) => format!(
+                "Error with command arguments, try [help speaker-tags]\nError:{}",
+                e
+            ),
+            (_, Err(e)) => format!(
+                "Error with command arguments, try [help speaker-tags]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+fn make_cast(
+    title: &str,
+    data: &ChannelData,
+    owners: &CharacterOwners,
+    word_filter: &WordFilter,
+) -> String {
+    if data.character_stats.is_empty() && data.speaker_stats.is_empty() {
+        return format!(
+            "Nobody has spoken in {} yet, characters come from proxy bots and [speaker-tags]",
+            title
+        );
+    }
+    let mut builder = MessageBuilder::new();
+    builder.push("Cast of ").push(title).newline();
+    if !data.character_stats.is_empty() {
+        builder
+            .newline()
+            .push_underline_line("Posted through proxy bots");
+        push_characters(
+            &mut builder,
+            &data.character_stats,
+            Some(owners),
+            word_filter,
+        );
+    }
+    if !data.speaker_stats.is_empty() {
+        builder.newline().push_underline_line("From speaker tags");
+        push_characters(&mut builder, &data.speaker_stats, None, word_filter);
+    }
+    builder.build()
+}
+
+// Most words first. Speaker tags aren't claimed by anyone, so only proxy characters have owners
+fn push_characters(
+    builder: &mut MessageBuilder,
+    characters: &HashMap<String, WordStats>,
+    owners: Option<&CharacterOwners>,
+    word_filter: &WordFilter,
+) {
+    let mut characters: Vec<_> = characters.iter().collect();
+    characters.sort_by(|a, b| b.1.word_count.cmp(&a.1.word_count).then(a.0.cmp(b.0)));
+    for (character, stats) in characters {
+        builder
+            .newline()
+            .push_bold_safe(character)
+            .push(format!(": {} words", stats.word_count));
+        if let Some(owner) = owners.and_then(|owners| owners.owner(character)) {
+            builder.push(", played by ").mention(&owner);
+        }
+        builder.newline().push_line_safe(format!(
+            "Top words: {}",
+            stats.top_words(TOP_WORDS, word_filter)
+        ));
+    }
+}
+
+async fn get_cast(ctx: &Context, server_id: GuildId, target: &StatsTarget) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let server_data = match store.get_server_data(&server_id) {
+        Some(server_data) => server_data,
+        None => return String::from("No channels have been initialised on this server"),
+    };
+    let owners = &server_data.character_owners;
+    match target {
+        StatsTarget::Channel(channel_id) => match server_data.get_channel_data(channel_id) {
+            Some(channel_data) => make_cast(
+                &MessageBuilder::new().channel(channel_id).build(),
+                channel_data,
+                owners,
+                &store.word_filter(&(server_id, *channel_id)),
+            ),
+            None => String::from("Channel not initialised, use [init-channel] to add it"),
+        },
+        StatsTarget::Story(name) => {
+            match (server_data.get_story(name), server_data.story_data(name)) {
+                (Some(story), Some(story_data)) => make_cast(
+                    &MessageBuilder::new()
+                        .push("the story ")
+                        .push_bold_safe(&story.name)
+                        .build(),
+                    &story_data,
+                    owners,
+                    &store.word_filter_for_channels(&server_id, &story.channels),
+                ),
+                _ => format!("There's no story called {}, see [story list]", name),
+            }
+        }
+        StatsTarget::Chapter(..) => String::from("The cast is only kept for channels and stories"),
+    }
+}
+
+#[command("cast")]
+#[usage("<#channel name | story name>")]
+#[description("List the characters in a channel or story with their word counts and top words. Characters come from proxy bots (Tupperbox, PluralKit...) and lines picked out by [speaker-tags]")]
+#[example("#the-fall-of-rome")]
+#[example("\"Fall of Rome\"")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn cast(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match StatsTarget::single(&mut args) {
+            Ok(target) => get_cast(ctx, server_id, &target).await,
+            Err(e) => format!("Error with command arguments, try [help cast]\nError:{}", e),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = SPEAKER_TAGS_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = SpeakerTagAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
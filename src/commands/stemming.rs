@@ -0,0 +1,110 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::state::StoreData;
+use crate::stop_words::Language;
+use crate::utils::helpers::parse_on_off;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+// [language] is kept as it was when None
+async fn set_stemming(
+    ctx: &Context,
+    server_id: &GuildId,
+    stemming: bool,
+    language: Option<Language>,
+) -> Language {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let settings = &mut store.get_server_data_mut_maybe_create(server_id).settings;
+    settings.stemming = stemming;
+    if let Some(language) = language {
+        settings.stemming_language = language;
+    }
+    settings.stemming_language
+}
+
+async fn get_stemming(ctx: &Context, server_id: &GuildId) -> (bool, Language) {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    match store.get_server_data(server_id) {
+        Some(server_data) => (
+            server_data.settings.stemming,
+            server_data.settings.stemming_language,
+        ),
+        None => (false, Language::English),
+    }
+}
+
+fn stemming_str(stemming: bool, language: Language) -> String {
+    if stemming {
+        format!("on ({})", language.to_str())
+    } else {
+        String::from("off")
+    }
+}
+
+fn parse_args(args: &mut Args) -> Result<(bool, Option<Language>), String> {
+    let stemming = parse_on_off(&args.single::<String>().unwrap())?;
+    let language = match args.single::<String>() {
+        Ok(language) if stemming => Some(language.parse::<Language>()?),
+        Ok(_) => return Err(String::from("A language can only be given with on")),
+        Err(_) => None,
+    };
+    Ok((stemming, language))
+}
+
+#[command("stemming")]
+#[usage("[on|off] [<language>]")]
+#[description("Group different forms of the same word together (e.g. eye/eyes, narrow/narrows/narrowed) in top words and wordclouds for this server, showing the most common form. Forms are grouped by the rules of one language, english unless another is given. Without an argument, shows the current setting")]
+#[example("on")]
+#[example("on french")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn stemming(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.len() {
+            0 => {
+                let (stemming, language) = get_stemming(ctx, &server_id).await;
+                format!(
+                    "Stemming is {} for this server",
+                    stemming_str(stemming, language)
+                )
+            }
+            1 | 2 => match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                true => match parse_args(&mut args) {
+                    Ok((stemming, language)) => {
+                        let language = set_stemming(ctx, &server_id, stemming, language).await;
+                        format!(
+                            "Stemming is now {} for this server",
+                            stemming_str(stemming, language)
+                        )
+                    }
+                    Err(e) => format!(
+                        "Error with command arguments, try [help stemming]\nError:{}",
+                        e
+                    ),
+                },
+                false => format!(
+                    "Changing this is only available to those with the role {}",
+                    ALLOWED_ROLES[0]
+                ),
+            },
+            _ => String::from("Invalid number of args, try [help stemming]"),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
@@ -0,0 +1,278 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::state::{StoreData, StoryKey};
+use crate::stop_words::Language;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::collections::HashSet;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[derive(Debug, EnumIter)]
+enum StopWordsAction {
+    Add,
+    Remove,
+    List,
+    Languages,
+}
+impl StopWordsAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Remove => "remove",
+            Self::List => "list",
+            Self::Languages => "languages",
+        }
+    }
+}
+impl FromStr for StopWordsAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "add" => Ok(Self::Add),
+            "remove" => Ok(Self::Remove),
+            "list" => Ok(Self::List),
+            "languages" => Ok(Self::Languages),
+            _ => Err(format!(
+                "Invalid stopwords action {}, expected one of: {}",
+                s,
+                Self::iter()
+                    .map(|action| action.to_str())
+                    .collect::<Vec<&str>>()
+                    .join("|")
+            )),
+        }
+    }
+}
+
+fn sorted_words_string(words: &HashSet<String>) -> String {
+    if words.is_empty() {
+        String::from("none")
+    } else {
+        let mut words: Vec<&String> = words.iter().collect();
+        words.sort();
+        words
+            .iter()
+            .map(|w| w.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    }
+}
+
+async fn list_stop_words(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: Option<ChannelId>,
+) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let mut builder = MessageBuilder::new();
+    match store.get_server_data(server_id) {
+        Some(server_data) => {
+            let languages: Vec<&str> = server_data
+                .settings
+                .stop_word_languages
+                .iter()
+                .map(|language| language.to_str())
+                .collect();
+            builder
+                .push_line(format!("Stop word languages: {}", languages.join(", ")))
+                .push_line_safe(format!(
+                    "Extra stop words for this server: {}",
+                    sorted_words_string(&server_data.settings.stop_words)
+                ));
+        }
+        None => {
+            builder.push_line("No stop words configured for this server, using the english list");
+        }
+    }
+    if let Some(channel_id) = channel_id {
+        match store.get_channel_data(&(*server_id, channel_id)) {
+            Some(channel_data) => {
+                builder
+                    .push("Extra stop words for ")
+                    .channel(channel_id)
+                    .push_safe(format!(
+                        ": {}",
+                        sorted_words_string(&channel_data.settings.stop_words)
+                    ))
+                    .newline();
+            }
+            None => {
+                builder
+                    .channel(channel_id)
+                    .push_line(" is not initialised, use [init-channel] to add it");
+            }
+        }
+    }
+    builder.build()
+}
+
+async fn change_stop_words(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: Option<ChannelId>,
+    words: Vec<String>,
+    add: bool,
+) -> std::result::Result<String, String> {
+    if words.is_empty() {
+        return Err(String::from("No words given, try [help stopwords]"));
+    }
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let stop_words = match channel_id {
+        Some(channel_id) => {
+            let story_key: StoryKey = (*server_id, channel_id);
+            match store.get_channel_data_mut(&story_key) {
+                Some(channel_data) => &mut channel_data.settings.stop_words,
+                None => {
+                    return Err(String::from(
+                        "Channel not initialised, use [init-channel] to add it",
+                    ))
+                }
+            }
+        }
+        None => {
+            &mut store
+                .get_server_data_mut_maybe_create(server_id)
+                .settings
+                .stop_words
+        }
+    };
+    let changed = words
+        .into_iter()
+        .filter(|word| match add {
+            true => stop_words.insert(word.clone()),
+            false => stop_words.remove(word),
+        })
+        .count();
+    Ok(format!(
+        "{} {} stop word(s)",
+        if add { "Added" } else { "Removed" },
+        changed
+    ))
+}
+
+async fn set_languages(
+    ctx: &Context,
+    server_id: &GuildId,
+    words: Vec<String>,
+) -> std::result::Result<String, String> {
+    if words.is_empty() {
+        return Err(String::from(
+            "No languages given, try [help stopwords] to see the available ones",
+        ));
+    }
+    let mut languages: Vec<Language> = vec![];
+    for word in words.iter() {
+        let language = Language::from_str(word)?;
+        if !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let settings = &mut store.get_server_data_mut_maybe_create(server_id).settings;
+    settings.stop_word_languages = languages;
+    Ok(format!("Stop word languages set to: {}", words.join(", ")))
+}
+
+#[command("stopwords")]
+#[usage("<action> [#channel] [words...]")]
+#[description("Manage extra words left out of top words and wordclouds, for the whole server or for just one channel if one is given. [languages] instead picks which of the bundled lists apply to the server, out of english|french|german|spanish|italian|portuguese|dutch. Changes need the MasterScrivener role. Actions: add|remove|list|languages")]
+#[example("add lol brb")]
+#[example("add #the-fall-of-rome caligula")]
+#[example("list #the-fall-of-rome")]
+#[example("languages english french")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn stopwords(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<StopWordsAction>() {
+            Ok(StopWordsAction::List) => {
+                let channel_id = args.single::<ChannelId>().ok();
+                list_stop_words(ctx, &server_id, channel_id).await
+            }
+            Ok(action) => match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                true => {
+                    let channel_id = args.single::<ChannelId>().ok();
+                    let words: Vec<String> = args
+                        .iter::<String>()
+                        .filter_map(|word| word.ok())
+                        .map(|word| word.to_lowercase())
+                        .collect();
+                    let result = match action {
+                        StopWordsAction::Add => {
+                            change_stop_words(ctx, &server_id, channel_id, words, true).await
+                        }
+                        StopWordsAction::Remove => {
+                            change_stop_words(ctx, &server_id, channel_id, words, false).await
+                        }
+                        StopWordsAction::Languages => match channel_id {
+                            Some(_) => Err(String::from(
+                                "Languages can only be set for the whole server",
+                            )),
+                            None => set_languages(ctx, &server_id, words).await,
+                        },
+                        StopWordsAction::List => unreachable!(),
+                    };
+                    match result {
+                        Ok(s) => s,
+                        Err(e) => format!("Error: {}", e),
+                    }
+                }
+                false => format!(
+                    "Changing stop words is only available to those with the role {}",
+                    ALLOWED_ROLES[0]
+                ),
+            },
+            Err(e) => format!(
+                "Error with command arguments, try [help stopwords]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions_and_languages() {
+    let desc: &str = STOPWORDS_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = StopWordsAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+    let all_languages_from_enum_iter: String = Language::iter()
+        .map(|language| String::from(language.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert!(desc.contains(&all_languages_from_enum_iter));
+}
@@ -0,0 +1,215 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::state::{ServerData, StoreData};
+use crate::stories::Story;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum StoryAction {
+    List,
+    Create,
+    Add,
+    Remove,
+    Delete,
+}
+impl StoryAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::List => "list",
+            Self::Create => "create",
+            Self::Add => "add",
+            Self::Remove => "remove",
+            Self::Delete => "delete",
+        }
+    }
+}
+impl FromStr for StoryAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid story action {}", s)),
+        }
+    }
+}
+
+fn describe_stories(server_data: &ServerData) -> String {
+    if server_data.stories().is_empty() {
+        return String::from("There are no stories yet, use [story create] to make one");
+    }
+    let mut builder = MessageBuilder::new();
+    for story in server_data.stories() {
+        builder.push_bold_safe(&story.name).push(": ");
+        for (i, channel_id) in story.channels.iter().enumerate() {
+            if i > 0 {
+                builder.push(", ");
+            }
+            builder.channel(channel_id);
+        }
+        builder.push("\n");
+    }
+    builder.build()
+}
+
+// The rest of the args as channels, which all have to be initialised
+fn parse_channels(
+    server_data: &ServerData,
+    args: &mut Args,
+) -> std::result::Result<Vec<ChannelId>, String> {
+    let mut channel_ids = vec![];
+    for channel_id in args.iter::<ChannelId>() {
+        let channel_id = channel_id.map_err(|e| e.to_string())?;
+        if server_data.get_channel_data(&channel_id).is_none() {
+            return Err(String::from(
+                "Channel not initialised, use [init-channel] to add it",
+            ));
+        }
+        if !channel_ids.contains(&channel_id) {
+            channel_ids.push(channel_id);
+        }
+    }
+    if channel_ids.is_empty() {
+        return Err(String::from("Expected at least one channel"));
+    }
+    Ok(channel_ids)
+}
+
+fn story_name(args: &mut Args) -> std::result::Result<String, String> {
+    args.single_quoted::<String>()
+        .map_err(|_| String::from("Expected a story name"))
+}
+
+fn missing_story(name: &str) -> String {
+    format!("There's no story called {}", name)
+}
+
+// Applies [action] to the server's stories, returning the reply on success
+fn apply_action(
+    server_data: &mut ServerData,
+    action: &StoryAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    match action {
+        StoryAction::List => Ok(describe_stories(server_data)),
+        StoryAction::Create => {
+            let name = story_name(args)?;
+            let channels = parse_channels(server_data, args)?;
+            let story = Story {
+                name: name.clone(),
+                channels,
+            };
+            match server_data.add_story(story) {
+                true => Ok(format!("Created the story {}", name)),
+                false => Err(format!("There's already a story called {}", name)),
+            }
+        }
+        StoryAction::Add => {
+            let name = story_name(args)?;
+            let channels = parse_channels(server_data, args)?;
+            let story = server_data
+                .get_story_mut(&name)
+                .ok_or_else(|| missing_story(&name))?;
+            for channel_id in channels {
+                if !story.channels.contains(&channel_id) {
+                    story.channels.push(channel_id);
+                }
+            }
+            Ok(format!("Added to the story {}", story.name))
+        }
+        StoryAction::Remove => {
+            let name = story_name(args)?;
+            let channels = parse_channels(server_data, args)?;
+            let story = server_data
+                .get_story_mut(&name)
+                .ok_or_else(|| missing_story(&name))?;
+            story
+                .channels
+                .retain(|channel_id| !channels.contains(channel_id));
+            Ok(format!("Removed from the story {}", story.name))
+        }
+        StoryAction::Delete => {
+            let name = story_name(args)?;
+            match server_data.remove_story(&name) {
+                true => Ok(format!("Deleted the story {}", name)),
+                false => Err(missing_story(&name)),
+            }
+        }
+    }
+}
+
+async fn update_stories(
+    ctx: &Context,
+    server_id: &GuildId,
+    action: &StoryAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    apply_action(server_data, action, args)
+}
+
+#[command("story")]
+#[usage("<action> [<story name> [<#channel name>...]]")]
+#[description("Group several channels into one named story, e.g. one per arc plus an OOC channel. Stories can be used in place of a channel with [show-stats] and [gen-wordcloud], and with [leaderboard story <name>]. Put names with spaces in quotes. Changes need the MasterScrivener role. Actions: list|create|add|remove|delete")]
+#[example("create \"Fall of Rome\" #arc-1 #arc-2")]
+#[example("add \"Fall of Rome\" #arc-3")]
+#[example("list")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn story(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<StoryAction>() {
+            Ok(action) => {
+                let allowed = action == StoryAction::List
+                    || author_is_in_allowed_roles(ctx, &server_id, &msg.author).await;
+                if allowed {
+                    match update_stories(ctx, &server_id, &action, &mut args).await {
+                        Ok(reply) => reply,
+                        Err(e) => format!(
+                            "Error with command arguments, try [help story]\nError:{}",
+                            e
+                        ),
+                    }
+                } else {
+                    format!(
+                        "Changing stories is only available to those with the role {}",
+                        ALLOWED_ROLES[0]
+                    )
+                }
+            }
+            Err(e) => format!(
+                "Error with command arguments, try [help story]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = STORY_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = StoryAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
@@ -0,0 +1,268 @@
+use crate::commands::init_channel::{author_is_in_allowed_roles, ALLOWED_ROLES};
+use crate::state::{StoreData, StoryKey};
+use crate::turns::TurnSettings;
+use crate::utils::helpers::describe_duration;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use chrono::Utc;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+async fn make_whose_turn(ctx: &Context, story_key: &StoryKey) -> String {
+    let turn = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoreData in TypeMap.")
+                .clone()
+        };
+        let store = store_lock.read().unwrap();
+        match store.get_channel_data(story_key) {
+            Some(channel_data) => channel_data.turn(Utc::now()),
+            None => return String::from("Channel not initialised, use [init-channel] to add it"),
+        }
+    };
+    let turn = match turn {
+        Some(turn) => turn,
+        None => return String::from("Nobody has posted there yet"),
+    };
+    let mut builder = MessageBuilder::new();
+    builder
+        .push("The last post in ")
+        .channel(story_key.1)
+        .push(" was by ")
+        .mention(&turn.last_poster)
+        .push_line(format!(
+            ", {} ago",
+            describe_duration(Utc::now() - turn.last_post_time)
+        ));
+    match turn.next {
+        Some(next) => builder.push("Up next: ").mention(&next),
+        None => builder.push("There's nobody else in the rotation"),
+    };
+    builder.build()
+}
+
+#[command("whose-turn")]
+#[usage("[<#channel name>]")]
+#[description("Display who posted last in a round-robin story and who is up next, for this channel if none is given. The rotation is learned from who's posted in the last 30 days, unless one is set with [turn-order]")]
+#[example("#the-fall-of-rome")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn whose_turn(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        let channel_id = match args.len() {
+            0 => Ok(msg.channel_id),
+            1 => args.single::<ChannelId>().map_err(|e| {
+                format!(
+                    "Error with command arguments, try [help whose-turn]\nError:{}",
+                    e
+                )
+            }),
+            _ => Err(String::from(
+                "Invalid number of args, try [help whose-turn]",
+            )),
+        };
+        match channel_id {
+            Ok(channel_id) => make_whose_turn(ctx, &(server_id, channel_id)).await,
+            Err(e) => e,
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, EnumIter)]
+enum TurnOrderAction {
+    Show,
+    Set,
+    Learn,
+    Nudge,
+}
+impl TurnOrderAction {
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::Set => "set",
+            Self::Learn => "learn",
+            Self::Nudge => "nudge",
+        }
+    }
+}
+impl FromStr for TurnOrderAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|action| action.to_str() == s) {
+            Some(action) => Ok(action),
+            None => Err(format!("Invalid turn order action {}", s)),
+        }
+    }
+}
+
+fn describe_settings(channel_id: ChannelId, settings: &TurnSettings) -> String {
+    let mut builder = MessageBuilder::new();
+    builder
+        .push("Turn order for ")
+        .channel(channel_id)
+        .newline();
+    match settings.rotation.is_empty() {
+        true => builder.push_line("Rotation: learned from who's posting"),
+        false => {
+            builder.push("Rotation: ");
+            for (i, user_id) in settings.rotation.iter().enumerate() {
+                builder.apply_if(i > 0, |b| b.push(", ")).mention(user_id);
+            }
+            builder.newline()
+        }
+    };
+    match settings.nudge_after_hours {
+        Some(hours) => builder.push(format!(
+            "Nudging the next writer by DM after {} hour(s)",
+            hours
+        )),
+        None => builder.push("Nudges are off"),
+    };
+    builder.build()
+}
+
+// Applies [action] to the settings in place, returning the reply on success
+fn apply_action(
+    settings: &mut TurnSettings,
+    action: &TurnOrderAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    match action {
+        TurnOrderAction::Show => Ok(String::new()),
+        TurnOrderAction::Set => {
+            let mut rotation: Vec<UserId> = vec![];
+            for user_id in args.iter::<UserId>() {
+                let user_id = user_id.map_err(|e| e.to_string())?;
+                if rotation.contains(&user_id) {
+                    return Err(String::from("Each writer can only be in the rotation once"));
+                }
+                rotation.push(user_id);
+            }
+            if rotation.len() < 2 {
+                return Err(String::from("A rotation needs at least two writers"));
+            }
+            settings.rotation = rotation;
+            Ok(String::from("Updated the rotation"))
+        }
+        TurnOrderAction::Learn => {
+            settings.rotation.clear();
+            Ok(String::from(
+                "The rotation will be learned from who's posting",
+            ))
+        }
+        TurnOrderAction::Nudge => {
+            let value = args.single::<String>().map_err(|e| e.to_string())?;
+            settings.nudge_after_hours = match value.as_str() {
+                "off" => None,
+                hours => match hours.parse::<u64>() {
+                    Ok(hours) if hours > 0 => Some(hours),
+                    _ => return Err(format!("Expected a number of hours or off, got {}", hours)),
+                },
+            };
+            Ok(String::from("Updated nudges"))
+        }
+    }
+}
+
+async fn update_turn_settings(
+    ctx: &Context,
+    story_key: &StoryKey,
+    action: &TurnOrderAction,
+    args: &mut Args,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    match store.get_channel_data_mut(story_key) {
+        Some(channel_data) => {
+            let settings = &mut channel_data.settings.turns;
+            let mut new_settings = settings.clone();
+            let reply = apply_action(&mut new_settings, action, args)?;
+            *settings = new_settings;
+            let description = describe_settings(story_key.1, settings);
+            match reply.is_empty() {
+                true => Ok(description),
+                false => Ok(format!("{}\n{}", reply, description)),
+            }
+        }
+        None => Err(String::from(
+            "Channel not initialised, use [init-channel] to add it",
+        )),
+    }
+}
+
+#[command("turn-order")]
+#[usage("<#channel name> <action> [<@user mentions> | <hours> | off]")]
+#[description("Configure the rotation for a round-robin story, used by [whose-turn]. [set] takes the writers in order, [learn] goes back to working it out from who's posting, and [nudge] DMs the next writer once it's been their turn for that many hours. Changes need the MasterScrivener role. Actions: show|set|learn|nudge")]
+#[example("#the-fall-of-rome set @Caligula @Nero @Claudius")]
+#[example("#the-fall-of-rome nudge 48")]
+#[example("#the-fall-of-rome nudge off")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn turn_order(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        let maybe_channel_id = args.single::<ChannelId>();
+        let maybe_action = args.single::<TurnOrderAction>();
+        match (maybe_channel_id, maybe_action) {
+            (Ok(channel_id), Ok(action)) => {
+                let story_key: StoryKey = (server_id, channel_id);
+                let allowed = action == TurnOrderAction::Show
+                    || author_is_in_allowed_roles(ctx, &server_id, &msg.author).await;
+                if allowed {
+                    match update_turn_settings(ctx, &story_key, &action, &mut args).await {
+                        Ok(reply) => reply,
+                        Err(e) => format!(
+                            "Error with command arguments, try [help turn-order]\nError:{}",
+                            e
+                        ),
+                    }
+                } else {
+                    format!(
+                        "Changing the turn order is only available to those with the role {}",
+                        ALLOWED_ROLES[0]
+                    )
+                }
+            }
+            (Err(e), _) => format!(
+                "Error with command arguments, try [help turn-order]\nError:{}",
+                e
+            ),
+            (_, Err(e)) => format!(
+                "Error with command arguments, try [help turn-order]\nError:{}",
+                e
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[test]
+fn usage_matches_all_actions() {
+    let desc: &str = TURN_ORDER_COMMAND_OPTIONS.desc.unwrap();
+    let match_ = "Actions: ";
+    let actions_index = desc.rfind(match_).unwrap();
+    let (_, actions_from_desc) = desc.split_at(actions_index + match_.len());
+    let all_actions_from_enum_iter: String = TurnOrderAction::iter()
+        .map(|action| String::from(action.to_str()))
+        .collect::<Vec<String>>()
+        .join("|");
+    assert_eq!(all_actions_from_enum_iter, actions_from_desc);
+}
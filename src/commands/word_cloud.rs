@@ -1,8 +1,9 @@
 use crate::config::GeneralAppConfigData;
 use crate::state::{StoreData, StoryKey};
+use crate::stories::StatsTarget;
 use crate::utils::trait_extensions::MessageBuilderExt;
 use log::error;
-use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::framework::standard::{macros::command, Args, CommandResult, Delimiter};
 use serenity::http::AttachmentType;
 use serenity::model::prelude::*;
 use serenity::prelude::*;
@@ -21,7 +22,7 @@ use tokio::io::ErrorKind;
 use uuid::Uuid;
 
 #[derive(Debug, EnumIter)]
-enum MaskName {
+pub enum MaskName {
     Bunny,
     D20,
     Shield,
@@ -59,14 +60,14 @@ fn error_help_text(error: &impl Display) -> String {
 }
 fn parse_args(
     args: &mut Args,
-) -> std::result::Result<(ChannelId, Option<UserId>, Option<MaskName>), String> {
+) -> std::result::Result<(StatsTarget, Option<UserId>, Option<MaskName>), String> {
     match args.len() {
-        1 => match args.single::<ChannelId>() {
+        1 => match StatsTarget::single(args) {
             Ok(channel_name) => Ok((channel_name, None, None)),
             Err(e) => Err(error_help_text(&e)),
         },
         2 => {
-            let maybe_channel_name = args.single::<ChannelId>();
+            let maybe_channel_name = StatsTarget::single(args);
             let maybe_user = args.single::<UserId>();
             match (maybe_channel_name, maybe_user) {
                 (Ok(channel_name), Ok(user)) => Ok((channel_name, Some(user), None)),
@@ -84,7 +85,7 @@ fn parse_args(
             }
         }
         3 => {
-            let maybe_channel_name = args.single::<ChannelId>();
+            let maybe_channel_name = StatsTarget::single(args);
             let maybe_user = args.single::<UserId>();
             let maybe_mask_name = args.single::<MaskName>();
             match (maybe_channel_name, maybe_user, maybe_mask_name) {
@@ -100,7 +101,21 @@ fn parse_args(
     }
 }
 
-async fn wordcloud_is_enabled(ctx: &Context) -> bool {
+// Pulls [-signature] out from wherever it is in the args, leaving the rest for [parse_args]
+fn take_signature_flag(args: &Args) -> (Args, bool) {
+    let mut found = false;
+    let rest: Vec<&str> = args
+        .raw()
+        .filter(|arg| {
+            let is_flag = *arg == "-signature";
+            found |= is_flag;
+            !is_flag
+        })
+        .collect();
+    (Args::new(&rest.join(" "), &[Delimiter::Single(' ')]), found)
+}
+
+pub async fn wordcloud_is_enabled(ctx: &Context) -> bool {
     let config_lock = {
         let data_read = ctx.data.read().await;
         data_read
@@ -115,7 +130,7 @@ async fn wordcloud_is_enabled(ctx: &Context) -> bool {
 fn unicode_emoji(s: &str) -> ReactionType {
     ReactionType::Unicode(String::from(s))
 }
-async fn react_or_reply(msg: &Message, ctx: &Context) {
+pub async fn react_or_reply(msg: &Message, ctx: &Context) {
     match msg.react(ctx, unicode_emoji("🤖")).await {
         Ok(_) => {
             let _ = msg.react(ctx, unicode_emoji("⌚")).await.unwrap();
@@ -126,28 +141,35 @@ async fn react_or_reply(msg: &Message, ctx: &Context) {
     }
 }
 #[command("gen-wordcloud")]
-#[usage("<#channel name> [<@user mention>] [mask]")]
+#[usage("<#channel name | story name> [<@user mention>] [mask] [-signature]")]
 #[description(
-    "Generate a wordcloud from the given channel's general stats. If a user is given (via @mention) the wordcloud if for just that user's stats. Available masks: bunny|d20|shield|wolf|horse"
+    "Generate a wordcloud from the given channel's general stats, or from every channel in a story made with [story create]. If a user is given (via @mention) the wordcloud if for just that user's stats. With -signature the user's words are sized by how much they set them apart from everyone else in the channel. Available masks: bunny|d20|shield|wolf|horse"
 )]
 #[example("#war-and-peace")]
 #[example("#the-fall-of-rome @Caligula wolf")]
 #[example("#the-fall-of-rome wolf")]
+#[example("#the-fall-of-rome @Caligula -signature")]
+#[example("\"Fall of Rome\" @Caligula")]
 #[bucket("global-wordcloud-bucket")]
 #[only_in("guilds")] // Reminder: guild = server
-async fn gen_wordcloud(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+async fn gen_wordcloud(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (mut args, signature) = take_signature_flag(&args);
     let reply = if wordcloud_is_enabled(ctx).await {
         match parse_args(&mut args) {
-            Ok((channel_id, user_id, mask_name)) => {
+            Ok((_, None, _)) if signature => {
+                some_string!("Signature wordclouds need a user to compare against the channel")
+            }
+            Ok((target, user_id, mask_name)) => {
                 if let Some(server_id) = msg.guild_id {
                     react_or_reply(msg, ctx).await;
-                    let story_key = (server_id, channel_id);
                     request_and_fetch_wordcloud(
-                        &story_key,
+                        &server_id,
+                        &target,
                         ctx,
                         &msg.channel_id,
                         &user_id,
                         &mask_name,
+                        signature,
                     )
                     .await
                 } else {
@@ -167,17 +189,29 @@ async fn gen_wordcloud(ctx: &Context, msg: &Message, mut args: Args) -> CommandR
     Ok(())
 }
 
-async fn request_and_fetch_wordcloud(
-    story_key: &StoryKey,
+pub async fn request_and_fetch_wordcloud(
+    server_id: &GuildId,
+    target: &StatsTarget,
     ctx: &Context,
     send_to_channel: &ChannelId,
     user: &Option<UserId>,
     mask_name: &Option<MaskName>,
+    signature: bool,
 ) -> Option<String> {
     //Look up a specific user's frequencies in WordStats, dump to specific file, watch for response from the worker
-    let response_content = MessageBuilder::new()
-        .push("Word cloud for channel ")
-        .channel(story_key.1)
+    let mut response_builder = MessageBuilder::new();
+    response_builder
+        .apply_if(signature, |b| b.push("Signature "))
+        .push("Word cloud for ");
+    match target {
+        StatsTarget::Channel(channel_id) => response_builder.push("channel ").channel(channel_id),
+        StatsTarget::Story(name) => response_builder.push("story ").push_safe(name),
+        StatsTarget::Chapter(channel_id, title) => response_builder
+            .push_safe(title)
+            .push(" in ")
+            .channel(channel_id),
+    };
+    let response_content = response_builder
         .apply_if(user.is_some(), |b| b.push(" for user ").user(user.unwrap()))
         .build();
     let users_stats = {
@@ -189,17 +223,67 @@ async fn request_and_fetch_wordcloud(
                 .clone()
         };
         let store = store_lock.read().unwrap();
-        if let Some(story_data) = store.get_channel_data(story_key) {
-            let mut res: Option<HashMap<String, usize>> = None;
+        // A story's channels (or a chapter) are merged into one, which has to live somewhere while
+        // it's used
+        let merged_story_data;
+        let (maybe_story_data, word_filter) = match target {
+            StatsTarget::Channel(channel_id) => {
+                let story_key: StoryKey = (*server_id, *channel_id);
+                (
+                    store.get_channel_data(&story_key),
+                    store.word_filter(&story_key),
+                )
+            }
+            StatsTarget::Story(name) => {
+                let server_data = store.get_server_data(server_id);
+                match server_data.and_then(|server_data| server_data.get_story(name)) {
+                    Some(story) => {
+                        merged_story_data = server_data.unwrap().story_data(name);
+                        (
+                            merged_story_data.as_ref(),
+                            store.word_filter_for_channels(server_id, &story.channels),
+                        )
+                    }
+                    None => return Some(format!("There's no story called {}", name)),
+                }
+            }
+            StatsTarget::Chapter(channel_id, title) => {
+                let story_key: StoryKey = (*server_id, *channel_id);
+                merged_story_data = store
+                    .get_channel_data(&story_key)
+                    .and_then(|channel_data| channel_data.chapter_data(title));
+                match merged_story_data {
+                    Some(_) => (merged_story_data.as_ref(), store.word_filter(&story_key)),
+                    None => return Some(format!("There's no chapter called {}", title)),
+                }
+            }
+        };
+        if let Some(story_data) = maybe_story_data {
+            let to_weights = |frequencies: HashMap<String, usize>| -> HashMap<String, f64> {
+                frequencies
+                    .into_iter()
+                    .map(|(word, count)| (word, count as f64))
+                    .collect()
+            };
+            let mut res: Option<HashMap<String, f64>> = None;
             match user {
+                Some(user_id) if signature => {
+                    res = story_data.signature_word_scores(user_id, &word_filter)
+                }
                 Some(user_id) => {
                     for (author, stats) in story_data.author_stats.iter() {
                         if &author.id == user_id {
-                            res = Some(stats.filtered_word_frequencies())
+                            res = Some(to_weights(stats.filtered_word_frequencies(&word_filter)))
                         }
                     }
                 }
-                None => res = Some(story_data.general_stats.filtered_word_frequencies()),
+                None => {
+                    res = Some(to_weights(
+                        story_data
+                            .general_stats
+                            .filtered_word_frequencies(&word_filter),
+                    ))
+                }
             }
             res
         } else {
@@ -207,6 +291,9 @@ async fn request_and_fetch_wordcloud(
         }
     };
     if let Some(word_freqs) = users_stats {
+        if word_freqs.is_empty() {
+            return some_string!("Not enough words to make a word cloud from");
+        }
         let request_uuid = Uuid::new_v4();
         let mask_name_str = match mask_name {
             Some(mask_name) => format!("{}.", mask_name.to_str()),
@@ -0,0 +1,146 @@
+use crate::state::{StoreData, WordLookup};
+use crate::utils::helpers::message_link;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use chrono::{DateTime, Utc};
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+fn push_use(
+    builder: &mut MessageBuilder,
+    label: &str,
+    server_id: GuildId,
+    word_use: Option<(ChannelId, MessageId, DateTime<Utc>)>,
+) {
+    if let Some((channel_id, message_id, timestamp)) = word_use {
+        builder
+            .push(format!("{}: {} in ", label, timestamp.format("%Y-%m-%d")))
+            .channel(channel_id)
+            .push(" ")
+            .push_line(message_link(server_id, channel_id, message_id));
+    }
+}
+
+fn make_lookup_string(
+    word: &str,
+    server_id: GuildId,
+    channel_id: Option<ChannelId>,
+    user_id: Option<UserId>,
+    lookup: WordLookup,
+) -> String {
+    let mut builder = MessageBuilder::new();
+    builder.push_bold_safe(format!("\"{}\"", word));
+    if let Some(channel_id) = channel_id {
+        builder.push(" in ").channel(channel_id);
+    }
+    if let Some(user_id) = user_id {
+        builder.push(" by ").mention(&user_id);
+    }
+    builder.newline();
+    if lookup.count == 0 {
+        return builder.push("Never used").build();
+    }
+    builder.push_line(format!("Used {} times", lookup.count));
+    match (lookup.rank, lookup.stop_word) {
+        (Some((rank, out_of)), _) => {
+            builder.push_line(format!("Ranked #{} of {} words", rank, out_of))
+        }
+        (None, true) => builder.push_line("Not ranked, it's a stop word"),
+        (None, false) => builder.push_line("Not ranked"),
+    };
+    if !lookup.by_author.is_empty() {
+        builder.push("By: ");
+        for (i, (author, count)) in lookup.by_author.iter().take(10).enumerate() {
+            builder
+                .apply_if(i > 0, |b| b.push(", "))
+                .user(author)
+                .push(format!(" ({})", count));
+        }
+        builder.newline();
+    }
+    push_use(&mut builder, "First used", server_id, lookup.first);
+    push_use(&mut builder, "Last used", server_id, lookup.last);
+    if lookup.first.is_none() {
+        builder.push_line("First and last uses weren't recorded for these messages");
+    }
+    builder.build()
+}
+
+async fn look_up_word(
+    ctx: &Context,
+    server_id: GuildId,
+    word: &str,
+    channel_id: Option<ChannelId>,
+    user_id: Option<UserId>,
+) -> String {
+    let lookup = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoreData in TypeMap.")
+                .clone()
+        };
+        let store = store_lock.read().unwrap();
+        store.look_up_word(&server_id, channel_id, user_id, word)
+    };
+    match lookup {
+        Some(lookup) => make_lookup_string(word, server_id, channel_id, user_id, lookup),
+        None => match (channel_id, user_id) {
+            (Some(_), None) => {
+                String::from("Channel not initialised, use [init-channel] to add it")
+            }
+            (_, Some(_)) => String::from("User has no recorded activity there"),
+            (None, None) => String::from("There are no initialised channels on this server"),
+        },
+    }
+}
+
+#[command("word")]
+#[usage("<word> [<#channel name>] [<@user mention>]")]
+#[description("Look up how often a word has been used, by whom, and when it was first and last used, on the whole server or in one channel and/or by one user")]
+#[example("drone")]
+#[example("drone #the-fall-of-rome")]
+#[example("drone #the-fall-of-rome @Caligula")]
+#[example("drone @Caligula")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn word(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match parse_args(&mut args) {
+            Ok((word, channel_id, user_id)) => {
+                look_up_word(ctx, server_id, &word, channel_id, user_id).await
+            }
+            Err(e) => e,
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+fn parse_args(
+    args: &mut Args,
+) -> std::result::Result<(String, Option<ChannelId>, Option<UserId>), String> {
+    if args.is_empty() || args.len() > 3 {
+        return Err(String::from("Invalid number of args, try [help word]"));
+    }
+    let word = args.single::<String>().unwrap().to_lowercase();
+    let mut channel_id = None;
+    let mut user_id = None;
+    // Channel and user can come in either order
+    while !args.is_empty() {
+        if let Ok(id) = args.single::<ChannelId>() {
+            channel_id = Some(id);
+        } else if let Ok(id) = args.single::<UserId>() {
+            user_id = Some(id);
+        } else {
+            return Err(format!(
+                "Error with command arguments, try [help word]\nError: expected a channel or user, got {}",
+                args.rest()
+            ));
+        }
+    }
+    Ok((word, channel_id, user_id))
+}
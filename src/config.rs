@@ -22,6 +22,12 @@ pub struct GeneralAppConfig {
     pub prefix: String,
     pub wordcloud_config: Option<WordCloudConfig>,
     pub bot_admin: Option<UserId>,
+    #[serde(default)]
+    pub chart_config: ChartConfig,
+    #[serde(default)]
+    pub attachment_config: AttachmentConfig,
+    #[serde(default)]
+    pub proxy_config: ProxyConfig,
 }
 
 impl Default for GeneralAppConfig {
@@ -30,6 +36,9 @@ impl Default for GeneralAppConfig {
             prefix: String::from("!"),
             wordcloud_config: Some(WordCloudConfig::default()),
             bot_admin: None,
+            chart_config: ChartConfig::default(),
+            attachment_config: AttachmentConfig::default(),
+            proxy_config: ProxyConfig::default(),
         }
     }
 }
@@ -72,3 +81,55 @@ impl Default for WordCloudConfig {
         }
     }
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChartConfig {
+    // Any TrueType font, charts need one to draw their labels
+    pub font_path: PathBuf,
+}
+
+impl Default for ChartConfig {
+    fn default() -> Self {
+        Self {
+            font_path: PathBuf::from("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf"),
+        }
+    }
+}
+
+/// Text attachments (.txt and .md) up to [max_size_bytes] are counted as story text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentConfig {
+    pub max_size_bytes: u64,
+    // Where downloaded text is kept so it's only downloaded once
+    pub cache_path: PathBuf,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            max_size_bytes: 200_000,
+            cache_path: PathBuf::from("attachment_cache/"),
+        }
+    }
+}
+
+/// Messages from proxy bots count towards the character they were posted as
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxyConfig {
+    // PluralKit's API, e.g. "https://api.pluralkit.me/v2", to find out who plays characters
+    // nobody has claimed. [None] to only go by claims
+    pub pluralkit_url: Option<String>,
+    // How long messages are held back before being counted, in case a proxy bot deletes them to
+    // repost them as a character. Only on servers that use proxy bots, 0 to never hold them
+    pub hold_seconds: u64,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            pluralkit_url: None,
+            hold_seconds: 5,
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::milestones::{with_thousands_separators, Milestone};
+use crate::utils::helpers::split_into_messages;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serenity::constants::MESSAGE_CODE_LIMIT;
+use serenity::model::id::{ChannelId, GuildId};
+use serenity::model::user::User;
+use serenity::utils::MessageBuilder;
+
+/// When and where the weekly digest gets posted. Nothing is posted until [channel] is set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestSettings {
+    pub channel: Option<ChannelId>,
+    pub weekday: Weekday,
+    // 0-23, in [time_zone]
+    pub hour: u32,
+    // An IANA name like Europe/London
+    pub time_zone: String,
+    // The local date of the last digest, so it's only posted once a week even across restarts
+    pub last_posted: Option<NaiveDate>,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            channel: None,
+            weekday: Weekday::Mon,
+            hour: 9,
+            time_zone: String::from("UTC"),
+            last_posted: None,
+        }
+    }
+}
+
+impl DigestSettings {
+    pub fn tz(&self) -> Tz {
+        self.time_zone.parse().unwrap_or(Tz::UTC)
+    }
+
+    /// The local date the digest is due for at [now], or [None] if it's not due
+    pub fn due(&self, now: DateTime<Utc>) -> Option<NaiveDate> {
+        self.channel?;
+        let local_now = now.with_timezone(&self.tz());
+        let local_date = local_now.date().naive_local();
+        let due = local_now.weekday() == self.weekday
+            && local_now.hour() >= self.hour
+            && self.last_posted != Some(local_date);
+        match due {
+            true => Some(local_date),
+            false => None,
+        }
+    }
+}
+
+/// A digest that's due, it's only marked as posted (see [Store::mark_digest_posted]) once all
+/// of [messages] have been sent, so one that fails is tried again
+#[derive(Debug)]
+pub struct DueDigest {
+    pub server_id: GuildId,
+    pub channel_id: ChannelId,
+    // The local date it's due on
+    pub date: NaiveDate,
+    pub messages: Vec<String>,
+}
+
+/// A week of activity on a server. Days are UTC days, like the counts they come from
+#[derive(Debug)]
+pub struct Digest {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub channel_words: Vec<(ChannelId, usize)>,
+    pub top_authors: Vec<(User, usize)>,
+    pub new_authors: Vec<User>,
+    pub milestones: Vec<Milestone>,
+}
+
+impl Digest {
+    pub fn to_message(&self) -> String {
+        let mut builder = MessageBuilder::new();
+        builder.push_bold_line(format!(
+            "Weekly digest for {} to {}",
+            self.start.format("%Y-%m-%d"),
+            self.end.format("%Y-%m-%d")
+        ));
+        if self.channel_words.is_empty() {
+            return builder
+                .push("No words written this week, hopefully next week's a better one")
+                .build();
+        }
+        let total: usize = self.channel_words.iter().map(|(_, words)| words).sum();
+        builder
+            .push_line(format!(
+                "{} words written this week",
+                with_thousands_separators(total)
+            ))
+            .newline()
+            .push_bold_line("Words per channel");
+        for (channel_id, words) in self.channel_words.iter() {
+            builder
+                .channel(channel_id)
+                .push_line(format!(" -> {}", with_thousands_separators(*words)));
+        }
+        builder.newline().push_bold_line("Most active authors");
+        for (i, (author, words)) in self.top_authors.iter().enumerate() {
+            builder
+                .push(format!("{}. ", i + 1))
+                .user(author)
+                .push_line(format!(" -> {}", with_thousands_separators(*words)));
+        }
+        builder.apply_if(!self.new_authors.is_empty(), |b| {
+            b.newline().push_bold("Welcome to our new authors: ");
+            for (i, author) in self.new_authors.iter().enumerate() {
+                b.apply_if(i > 0, |b| b.push(", ")).user(author);
+            }
+            b.newline()
+        });
+        builder.apply_if(!self.milestones.is_empty(), |b| {
+            b.newline().push_bold_line("Milestones");
+            for milestone in self.milestones.iter() {
+                b.push_line(milestone.announcement());
+            }
+            b
+        });
+        builder.build()
+    }
+
+    /// [Digest::to_message] split into as many messages as Discord needs, for busy servers
+    pub fn to_messages(&self) -> Vec<String> {
+        split_into_messages(&self.to_message(), MESSAGE_CODE_LIMIT)
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::digest::{Digest, DigestSettings};
+    use chrono::{NaiveDate, TimeZone, Utc, Weekday};
+    use serenity::constants::MESSAGE_CODE_LIMIT;
+    use serenity::model::id::ChannelId;
+
+    #[test]
+    fn long_digests_are_split_between_lines() {
+        let digest = Digest {
+            start: NaiveDate::from_ymd(2026, 10, 26),
+            end: NaiveDate::from_ymd(2026, 11, 1),
+            channel_words: (1..200).map(|id| (ChannelId(id), 1000)).collect(),
+            top_authors: vec![],
+            new_authors: vec![],
+            milestones: vec![],
+        };
+        let messages = digest.to_messages();
+        assert!(messages.len() > 1);
+        assert!(messages.iter().all(
+            |message| message.chars().count() <= MESSAGE_CODE_LIMIT && message.ends_with('\n')
+        ));
+        assert_eq!(messages.concat(), digest.to_message());
+    }
+
+    #[test]
+    fn due_once_at_the_local_time() {
+        let mut settings = DigestSettings {
+            channel: Some(ChannelId(1)),
+            weekday: Weekday::Mon,
+            hour: 9,
+            time_zone: String::from("America/New_York"),
+            last_posted: None,
+        };
+        // 2026-11-02 is a Monday, and 13:30 UTC is 08:30 in New York
+        assert_eq!(settings.due(Utc.ymd(2026, 11, 2).and_hms(13, 30, 0)), None);
+        let monday = NaiveDate::from_ymd(2026, 11, 2);
+        assert_eq!(
+            settings.due(Utc.ymd(2026, 11, 2).and_hms(14, 30, 0)),
+            Some(monday)
+        );
+        settings.last_posted = Some(monday);
+        assert_eq!(settings.due(Utc.ymd(2026, 11, 2).and_hms(15, 0, 0)), None);
+        // Still Monday in UTC but already Tuesday in Tokyo
+        settings.time_zone = String::from("Asia/Tokyo");
+        settings.last_posted = None;
+        assert_eq!(settings.due(Utc.ymd(2026, 11, 2).and_hms(16, 0, 0)), None);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::language_parsing::tokenise_with_sentence_starts;
+use crate::stats::WordFilter;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::model::channel::Message;
+use serenity::model::id::{ChannelId, MessageId};
+use std::collections::{HashMap, HashSet};
+
+// A capitalised word that isn't all capitals, so shouting and "I" aren't taken as names.
+// Possessives are counted as the name itself
+fn as_proper_noun(word: &str) -> Option<&str> {
+    let word = word
+        .strip_suffix("'s")
+        .or_else(|| word.strip_suffix("’s"))
+        .unwrap_or(word);
+    let starts_capitalised = word.chars().next()?.is_uppercase();
+    match starts_capitalised && word.chars().any(char::is_lowercase) {
+        true => Some(word),
+        false => None,
+    }
+}
+
+/// How often a capitalised word has been used, and where it was first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Term {
+    pub count: usize,
+    // Uses that weren't at the start of a sentence, a word that's only ever capitalised there
+    // isn't a name
+    mid_sentence: usize,
+    pub first: (ChannelId, MessageId, DateTime<Utc>),
+}
+
+/// Capitalised words in a channel, as they were written. Most of these are ordinary words that
+/// started a sentence, [Glossary::entries] picks out the proper nouns
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Glossary {
+    terms: HashMap<String, Term>,
+    // So backfilling the channel's history doesn't count anything twice
+    included_messages: HashSet<MessageId>,
+}
+
+/// A proper noun and how it's been used, with any aliases merged in by [GlossaryCuration]
+#[derive(Debug, PartialEq)]
+pub struct GlossaryEntry {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub count: usize,
+    pub first: (ChannelId, MessageId, DateTime<Utc>),
+}
+
+impl Glossary {
+    /// Adds the capitalised words in [content], unless the message has been added already
+    pub fn update(&mut self, message: &Message, content: &str) {
+        self.add(message.channel_id, message.id, message.timestamp, content);
+    }
+
+    pub fn add(
+        &mut self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        timestamp: DateTime<Utc>,
+        content: &str,
+    ) {
+        if !self.included_messages.insert(message_id) {
+            return;
+        }
+        for (word, sentence_start) in tokenise_with_sentence_starts(content) {
+            if let Some(word) = as_proper_noun(word) {
+                let term = self.terms.entry(String::from(word)).or_insert(Term {
+                    count: 0,
+                    mid_sentence: 0,
+                    first: (channel_id, message_id, timestamp),
+                });
+                term.count += 1;
+                if !sentence_start {
+                    term.mid_sentence += 1;
+                }
+                // Messages arrive out of order when backfilling
+                if timestamp < term.first.2 {
+                    term.first = (channel_id, message_id, timestamp);
+                }
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &Glossary) {
+        for (word, other_term) in other.terms.iter() {
+            match self.terms.get_mut(word) {
+                Some(term) => {
+                    term.count += other_term.count;
+                    term.mid_sentence += other_term.mid_sentence;
+                    if other_term.first.2 < term.first.2 {
+                        term.first = other_term.first;
+                    }
+                }
+                None => {
+                    self.terms.insert(word.clone(), other_term.clone());
+                }
+            }
+        }
+        self.included_messages
+            .extend(other.included_messages.iter().cloned());
+    }
+
+    /// The proper nouns, most used first. Words capitalised somewhere other than the start of a
+    /// sentence, less stop words and whatever [curation] hides, with its aliases merged
+    pub fn entries(&self, filter: &WordFilter, curation: &GlossaryCuration) -> Vec<GlossaryEntry> {
+        let mut grouped: HashMap<String, (GlossaryEntry, usize)> = HashMap::new();
+        for (word, term) in self.terms.iter() {
+            let name = curation.name_for(word);
+            // The stop word lists only have straight apostrophes, as in "i'm"
+            let as_stop_word = word.to_lowercase().replace('’', "'");
+            if curation.is_hidden(word)
+                || curation.is_hidden(name)
+                || filter.is_stop_word(&as_stop_word)
+            {
+                continue;
+            }
+            let (entry, mid_sentence) = grouped.entry(name.to_lowercase()).or_insert_with(|| {
+                let entry = GlossaryEntry {
+                    name: String::from(name),
+                    aliases: vec![],
+                    count: 0,
+                    first: term.first,
+                };
+                (entry, 0)
+            });
+            entry.count += term.count;
+            *mid_sentence += term.mid_sentence;
+            if term.first.2 < entry.first.2 {
+                entry.first = term.first;
+            }
+            if word != &entry.name {
+                entry.aliases.push(word.clone());
+            }
+        }
+        let mut entries: Vec<GlossaryEntry> = grouped
+            .into_values()
+            .filter(|(_, mid_sentence)| *mid_sentence > 0)
+            .map(|(mut entry, _)| {
+                entry.aliases.sort();
+                entry
+            })
+            .collect();
+        entries.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(&b.name)));
+        entries
+    }
+}
+
+/// Per-server fixes to what the glossary finds, matched case-insensitively
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlossaryCuration {
+    // Lowercase alias to the name it's shown under
+    pub aliases: HashMap<String, String>,
+    // Lowercase words that aren't names, whatever the glossary thinks
+    pub hidden: HashSet<String>,
+}
+
+impl GlossaryCuration {
+    /// The name [word] is listed under, itself unless it's an alias
+    pub fn name_for<'a>(&'a self, word: &'a str) -> &'a str {
+        match self.aliases.get(&word.to_lowercase()) {
+            Some(name) => name,
+            None => word,
+        }
+    }
+
+    pub fn is_hidden(&self, word: &str) -> bool {
+        self.hidden.contains(&word.to_lowercase())
+    }
+
+    /// Lists [alias] under [name] from now on
+    pub fn merge(&mut self, name: &str, alias: &str) -> Result<(), String> {
+        if name.to_lowercase() == alias.to_lowercase() {
+            return Err(format!("{} can't be an alias of itself", name));
+        }
+        if let Some(other_name) = self.aliases.get(&name.to_lowercase()) {
+            return Err(format!(
+                "{} is already an alias of {}, merge into that instead",
+                name, other_name
+            ));
+        }
+        if self
+            .aliases
+            .values()
+            .any(|n| n.to_lowercase() == alias.to_lowercase())
+        {
+            return Err(format!(
+                "{} has aliases of its own, unmerge them first",
+                alias
+            ));
+        }
+        self.aliases
+            .insert(alias.to_lowercase(), String::from(name));
+        Ok(())
+    }
+
+    /// Returns the name [alias] was listed under
+    pub fn unmerge(&mut self, alias: &str) -> Option<String> {
+        self.aliases.remove(&alias.to_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::glossary::{Glossary, GlossaryCuration};
+    use crate::stats::WordFilter;
+    use chrono::{TimeZone, Utc};
+    use serenity::model::id::{ChannelId, MessageId};
+
+    #[test]
+    fn proper_nouns_are_found_and_curated() {
+        let mut glossary = Glossary::default();
+        let posts = [
+            "Then Anastasia rode past the Noodle King's stall.",
+            "She waved at Ana. \"Then go,\" said Marcus. \"Quickly!\"",
+            "Marcus's horse was faster. I'm sure THE END is near, and I’ll say so",
+        ];
+        // Added newest first, like a backfill
+        for (i, post) in posts.iter().enumerate().rev() {
+            glossary.add(
+                ChannelId(1),
+                MessageId(i as u64),
+                Utc.ymd(2026, 10, 1).and_hms(12, i as u32, 0),
+                post,
+            );
+        }
+        // Backfilling goes over messages that were already added, they aren't counted twice
+        glossary.add(
+            ChannelId(1),
+            MessageId(1),
+            Utc.ymd(2026, 10, 1).and_hms(12, 1, 0),
+            posts[1],
+        );
+        let mut curation = GlossaryCuration::default();
+        let names = |glossary: &Glossary, curation: &GlossaryCuration| {
+            glossary
+                .entries(&WordFilter::default(), curation)
+                .into_iter()
+                .map(|entry| (entry.name, entry.aliases, entry.count, entry.first.1))
+                .collect::<Vec<_>>()
+        };
+        // "Then", "She" and "Quickly" only start sentences, "I'm", "I’ll" and "THE" aren't names
+        assert_eq!(
+            names(&glossary, &curation),
+            vec![
+                (String::from("Marcus"), vec![], 2, MessageId(1)),
+                (String::from("Ana"), vec![], 1, MessageId(1)),
+                (String::from("Anastasia"), vec![], 1, MessageId(0)),
+                (String::from("King"), vec![], 1, MessageId(0)),
+                (String::from("Noodle"), vec![], 1, MessageId(0)),
+            ]
+        );
+        curation.merge("Anastasia", "ana").unwrap();
+        assert!(curation.merge("Ana", "Marcus").is_err());
+        assert!(curation.merge("Anastasia", "anastasia").is_err());
+        curation.hidden.insert(String::from("noodle"));
+        assert_eq!(
+            names(&glossary, &curation),
+            vec![
+                (
+                    String::from("Anastasia"),
+                    vec![String::from("Ana")],
+                    2,
+                    MessageId(0)
+                ),
+                (String::from("Marcus"), vec![], 2, MessageId(1)),
+                (String::from("King"), vec![], 1, MessageId(0)),
+            ]
+        );
+    }
+}
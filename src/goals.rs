@@ -0,0 +1,96 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, UserId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GoalOwner {
+    User(UserId),
+    // Counts everyone's words in the channel
+    Channel(ChannelId),
+}
+
+/// A word target to hit between [start] and [deadline], both inclusive. Progress isn't stored
+/// here, it's worked out from the daily counts in [crate::stats::WordStats] when asked for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Goal {
+    pub owner: GoalOwner,
+    pub target: usize,
+    pub start: NaiveDate,
+    pub deadline: NaiveDate,
+}
+
+impl Goal {
+    fn total_days(&self) -> i64 {
+        (self.deadline - self.start).num_days() + 1
+    }
+
+    pub fn status_string(&self, progress: usize, today: NaiveDate) -> String {
+        let percent = 100.0 * progress as f64 / self.target as f64;
+        let summary = format!("{} / {} words ({:.1}%)", progress, self.target, percent);
+        if progress >= self.target {
+            return format!("{}\nGoal reached, congratulations!", summary);
+        }
+        if today > self.deadline {
+            return format!("{}\nThe deadline of {} has passed", summary, self.deadline);
+        }
+        let total_days = self.total_days();
+        // Today counts as elapsed, since words written today already count
+        let elapsed_days = ((today - self.start).num_days() + 1).max(1).min(total_days);
+        let days_left = (self.deadline - today).num_days() + 1;
+        let daily_pace = progress as f64 / elapsed_days as f64;
+        let projected = (daily_pace * total_days as f64).round() as usize;
+        let words_left = (self.target - progress) as f64;
+        let needed_pace = (words_left / days_left as f64).ceil() as usize;
+        format!(
+            "{}, {} day(s) left until {}\nAveraging {:.0} words a day, on pace for {} words by the deadline ({})\n{} words a day from today would reach it",
+            summary,
+            days_left,
+            self.deadline,
+            daily_pace,
+            projected,
+            if projected >= self.target {
+                "on track"
+            } else {
+                "behind"
+            },
+            needed_pace,
+        )
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::goals::{Goal, GoalOwner};
+    use chrono::NaiveDate;
+    use serenity::model::id::UserId;
+
+    fn make_goal() -> Goal {
+        Goal {
+            owner: GoalOwner::User(UserId(1)),
+            target: 30000,
+            start: NaiveDate::from_ymd(2026, 11, 1),
+            deadline: NaiveDate::from_ymd(2026, 11, 30),
+        }
+    }
+
+    #[test]
+    fn pace_projection() {
+        let goal = make_goal();
+        let status = goal.status_string(10000, NaiveDate::from_ymd(2026, 11, 10));
+        assert_eq!(
+            status,
+            "10000 / 30000 words (33.3%), 21 day(s) left until 2026-11-30\nAveraging 1000 words a day, on pace for 30000 words by the deadline (on track)\n953 words a day from today would reach it"
+        );
+        let status = goal.status_string(5000, NaiveDate::from_ymd(2026, 11, 10));
+        assert!(status.contains("on pace for 15000 words by the deadline (behind)"));
+    }
+
+    #[test]
+    fn reached_and_missed() {
+        let goal = make_goal();
+        let status = goal.status_string(30500, NaiveDate::from_ymd(2026, 11, 20));
+        assert!(status.ends_with("Goal reached, congratulations!"));
+        let status = goal.status_string(100, NaiveDate::from_ymd(2026, 12, 2));
+        assert!(status.ends_with("The deadline of 2026-11-30 has passed"));
+    }
+}
@@ -6,18 +6,95 @@ pub fn tokenise(text: &str) -> Vec<&str> {
         .collect()
 }
 
+/// [tokenise], keeping track of whether each word starts a sentence (or a line, or a quote).
+/// Words aren't lowercased, so this is for anything that cares about capitalisation
+pub fn tokenise_with_sentence_starts(text: &str) -> Vec<(&str, bool)> {
+    let mut sentence_start = true;
+    tokens(text)
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Word(word) => {
+                let word_starts_sentence = sentence_start;
+                sentence_start = false;
+                Some((word, word_starts_sentence))
+            }
+            Token::Separator(c) => {
+                if Splitter::starts_sentence(c) {
+                    sentence_start = true;
+                }
+                None
+            }
+        })
+        .collect()
+}
+
+/// [tokenise], keeping track of whether each word is in quotes (dialogue) or not (narration).
+/// Quotes don't carry over to the next line, so an unclosed quote only covers its paragraph
+pub fn tokenise_with_quotes(text: &str) -> Vec<(&str, bool)> {
+    let mut in_quote = false;
+    tokens(text)
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Word(word) => Some((word, in_quote)),
+            Token::Separator(c) => {
+                match c {
+                    '"' => in_quote = !in_quote,
+                    '“' => in_quote = true,
+                    '”' | '\n' => in_quote = false,
+                    _ => {}
+                }
+                None
+            }
+        })
+        .collect()
+}
+
+enum Token<'a> {
+    Word(&'a str),
+    Separator(char),
+}
+
+// Like [tokenise], but keeping the separators between the words
+fn tokens(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut word_start = None;
+    // A trailing split so the last word is finished like the rest
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        if Splitter::is_match(c) {
+            if let Some(start) = word_start.take() {
+                let word = Splitter::strip_leading_trailing_apostrophes(&text[start..i]);
+                if !word.is_empty() {
+                    tokens.push(Token::Word(word));
+                }
+            }
+            tokens.push(Token::Separator(c));
+        } else if word_start.is_none() {
+            word_start = Some(i);
+        }
+    }
+    tokens
+}
+
 struct Splitter;
 
 impl Splitter {
     fn is_match(c: char) -> bool {
         match c {
-            ' ' | ',' | '.' | '!' | '?' | ';' | '"' | ':' | '\t' | '\n' | '(' | ')'
+            ' ' | ',' | '.' | '!' | '?' | ';' | '"' | '“' | '”' | ':' | '\t' | '\n' | '(' | ')'
             // single quote is specifically not included here and special-cased later
             | '*' | '-' => true,
             _ => false,
         }
     }
 
+    // Whatever comes after one of these is capitalised anyway
+    fn starts_sentence(c: char) -> bool {
+        matches!(c, '.' | '!' | '?' | '\n' | '"' | '“')
+    }
+
     fn strip_leading_trailing_apostrophes(s: &str) -> &str {
         let patt = '\'';
         let prefix_stripped: &str = match s.strip_prefix(patt) {
@@ -33,7 +110,7 @@ impl Splitter {
 
 #[cfg(test)]
 mod testing {
-    use crate::language_parsing::tokenise;
+    use crate::language_parsing::{tokenise, tokenise_with_quotes, tokenise_with_sentence_starts};
 
     #[test]
     fn basic_tokenising() {
@@ -53,6 +130,38 @@ mod testing {
         );
     }
 
+    #[test]
+    fn sentence_starts_are_marked() {
+        let input = "Ana saw Marcus. \"Where's the **Noodle King** box?\" she asked\nNobody";
+        let words = tokenise_with_sentence_starts(input);
+        let starts: Vec<&str> = words
+            .iter()
+            .filter(|(_, sentence_start)| *sentence_start)
+            .map(|(word, _)| *word)
+            .collect();
+        // Closing a quote with a question mark still counts, capitals are only looked for elsewhere
+        assert_eq!(starts, vec!["Ana", "Where's", "she", "Nobody"]);
+        let words: Vec<&str> = words.iter().map(|(word, _)| *word).collect();
+        assert_eq!(words, tokenise(input));
+    }
+
+    #[test]
+    fn dialogue_is_told_apart_from_narration() {
+        let input = "\"Run,\" she said. “Now!”\n\"Where to? The \"docks\"?\nNo answer";
+        let dialogue: Vec<&str> = tokenise_with_quotes(input)
+            .into_iter()
+            .filter(|(_, in_quote)| *in_quote)
+            .map(|(word, _)| word)
+            .collect();
+        // Nested straight quotes can't be told apart from closing ones
+        assert_eq!(dialogue, vec!["Run", "Now", "Where", "to", "The"]);
+        let words: Vec<&str> = tokenise_with_quotes(input)
+            .into_iter()
+            .map(|(word, _)| word)
+            .collect();
+        assert_eq!(words, tokenise(input));
+    }
+
     #[test]
     fn tokenising_with_apostrophes() {
         let input = "the cats, they're sat on the mat";
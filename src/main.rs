@@ -3,7 +3,7 @@ use std::env;
 use std::sync::atomic::Ordering;
 use std::sync::{Arc, RwLock};
 
-use log::{debug, info, LevelFilter};
+use log::{debug, error, info, LevelFilter};
 use serenity::async_trait;
 use serenity::client::{Client, Context, EventHandler};
 use serenity::framework::standard::{
@@ -20,16 +20,45 @@ use simplelog::SimpleLogger;
 use sysinfo::get_current_pid;
 use tokio::time::Duration;
 
+use commands::archive::{ARCHIVE_COMMAND, EXPORT_COMMAND};
+use commands::chapters::CHAPTER_COMMAND;
+use commands::characters::{claim_from_pluralkit, CHARACTER_COMMAND};
+use commands::charts::CHART_COMMAND;
+use commands::digest::DIGEST_COMMAND;
 use commands::dump_messages::DUMP_MESSAGES_COMMAND;
+use commands::glossary::{GLOSSARY_BACKFILL_COMMAND, GLOSSARY_COMMAND, GLOSSARY_EDIT_COMMAND};
+use commands::goals::GOAL_COMMAND;
+use commands::imitate::{IMITATE_COMMAND, IMITATION_COMMAND};
 use commands::init_channel::INIT_CHANNEL_COMMAND;
+use commands::leaderboard::LEADERBOARD_COMMAND;
+use commands::milestones::MILESTONES_COMMAND;
+use commands::mood::MOOD_COMMAND;
+use commands::ooc::OOC_COMMAND;
+use commands::posts::POST_WINDOW_COMMAND;
+use commands::reactions::{HALL_OF_FAME_COMMAND, STARBOARD_COMMAND};
+use commands::reminders::REMINDERS_COMMAND;
+use commands::search::SEARCH_COMMAND;
 use commands::server_summary::SERVER_SUMMARY_COMMAND;
 use commands::show_channels::SHOW_CHANNELS_COMMAND;
 use commands::show_stats::SHOW_STATS_COMMAND;
+use commands::signature_words::SIGNATURE_WORDS_COMMAND;
+use commands::speakers::{CAST_COMMAND, SPEAKER_TAGS_COMMAND};
+use commands::stemming::STEMMING_COMMAND;
+use commands::stop_words::STOPWORDS_COMMAND;
+use commands::stories::STORY_COMMAND;
+use commands::turns::{TURN_ORDER_COMMAND, WHOSE_TURN_COMMAND};
 use commands::word_cloud::GEN_WORDCLOUD_COMMAND;
+use commands::word_lookup::WORD_COMMAND;
 use commands::feedback::FEEDBACK_COMMAND;
 
+use crate::attachments::{with_attachment_text, with_attachment_texts};
 use crate::config::{GeneralAppConfig, GeneralAppConfigData};
+use crate::milestones::Milestone;
+use crate::proxies::{character_name, HeldMessages, HeldMessagesData};
+use crate::reactions::Star;
 use crate::state::{Store, StoreData, StoryKey};
+use crate::utils::helpers::{describe_duration, message_link};
+use crate::utils::trait_extensions::MessageBuilderExt;
 use serenity::futures::StreamExt;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
@@ -37,19 +66,41 @@ use tokio::io::{AsyncBufReadExt, BufReader};
 
 #[macro_use]
 mod macros;
+mod archive;
+mod attachments;
 mod commands;
+mod chapters;
+mod charts;
 mod config;
+mod digest;
+mod glossary;
+mod goals;
 mod language_parsing;
+mod manuscript;
+mod markov;
+mod milestones;
+mod ooc;
+mod posts;
+mod proxies;
+mod reactions;
+mod reminders;
+mod search;
+mod sentiment;
+mod sentiment_lexicon;
+mod speakers;
 mod state;
 mod stats;
+mod stop_words;
+mod stories;
+mod turns;
 mod utils;
 
 #[group]
-#[commands(init_channel, show_stats, show_channels, server_summary, feedback)]
+#[commands(init_channel, show_stats, show_channels, server_summary, feedback, stemming, stopwords, ooc, signature_words, word, goal, leaderboard, milestones, digest, whose_turn, turn_order, reminders, story, chapter, archive, search, imitation, imitate, mood, hall_of_fame, starboard, post_window, character, speaker_tags, cast, glossary, glossary_edit, glossary_backfill)]
 struct General;
 
 #[group]
-#[commands(gen_wordcloud)]
+#[commands(gen_wordcloud, chart, export)]
 struct WordCloud;
 
 #[group]
@@ -57,6 +108,9 @@ struct WordCloud;
 #[help_available(false)]
 struct Debug;
 
+// Long posts are cut short on the starboard, well under Discord's message limit
+const STARBOARD_MAX_CHARS: usize = 1500;
+
 struct Handler {
     tasks_running: AtomicBool,
 }
@@ -77,6 +131,18 @@ impl EventHandler for Handler {
             let _ = tokio::spawn(async move {
                 dump_state(ctx2).await;
             });
+            let ctx3 = Arc::clone(&ctx);
+            tokio::spawn(async move {
+                post_digests(ctx3).await;
+            });
+            let ctx4 = Arc::clone(&ctx);
+            tokio::spawn(async move {
+                nudge_next_writers(ctx4).await;
+            });
+            let ctx5 = Arc::clone(&ctx);
+            tokio::spawn(async move {
+                remind_stalled_stories(ctx5).await;
+            });
             self.tasks_running.swap(true, Ordering::Relaxed);
         }
     }
@@ -84,6 +150,135 @@ impl EventHandler for Handler {
     async fn ready(&self, _ctx: Context, ready: Ready) {
         println!("{} is connected!", ready.user.name);
     }
+
+    // The framework ignores webhooks, but that's how proxy bots post
+    async fn message(&self, ctx: Context, message: Message) {
+        if message.webhook_id.is_some() {
+            on_proxied_message(&ctx, &message).await;
+        }
+    }
+
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        _channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        _guild_id: Option<GuildId>,
+    ) {
+        let held_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<HeldMessagesData>()
+                .expect("Expected HeldMessagesData in TypeMap.")
+                .clone()
+        };
+        held_lock.write().unwrap().deleted(deleted_message_id);
+    }
+
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        track_reaction(&ctx, &reaction, true).await;
+    }
+
+    async fn reaction_remove(&self, ctx: Context, reaction: Reaction) {
+        track_reaction(&ctx, &reaction, false).await;
+    }
+
+    async fn reaction_remove_all(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) {
+        let server_id = match ctx.cache.guild_channel(channel_id).await {
+            Some(channel) => channel.guild_id,
+            None => return,
+        };
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        if let Some(server_data) = store.get_server_data_mut(&server_id) {
+            server_data.clear_reactions(&channel_id, message_id);
+        }
+    }
+}
+
+// Counts reactions on posts in initialised channels, reposting any that cross the starboard
+// threshold. The bot's own reactions (like the ones showing it's busy) don't count
+async fn track_reaction(ctx: &Context, reaction: &Reaction, added: bool) {
+    let server_id = match reaction.guild_id {
+        Some(server_id) => server_id,
+        None => return,
+    };
+    if reaction.user_id == Some(ctx.cache.current_user_id().await) {
+        return;
+    }
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let emoji = reaction.emoji.to_string();
+    let star = {
+        let mut store = store_lock.write().unwrap();
+        let server_data = match store.get_server_data_mut(&server_id) {
+            Some(server_data) => server_data,
+            None => return,
+        };
+        let star = match added {
+            true => server_data.add_reaction(&reaction.channel_id, reaction.message_id, &emoji),
+            false => {
+                server_data.remove_reaction(&reaction.channel_id, reaction.message_id, &emoji);
+                None
+            }
+        };
+        // Saved straight away so a restart can't repost it
+        if star.is_some() {
+            if let Err(e) = store.dump() {
+                error!("Failed to save state after starring a post: {}", e);
+            }
+        }
+        star
+    };
+    if let Some(star) = star {
+        post_to_starboard(ctx, server_id, star).await;
+    }
+}
+
+async fn post_to_starboard(ctx: &Context, server_id: GuildId, star: Star) {
+    let message = match star.channel_id.message(&ctx.http, star.message_id).await {
+        Ok(message) => message,
+        Err(e) => {
+            error!("Failed to fetch starred post {}: {}", star.message_id, e);
+            return;
+        }
+    };
+    let content: String = message.content.chars().take(STARBOARD_MAX_CHARS).collect();
+    let mut builder = MessageBuilder::new();
+    builder
+        .push(format!("⭐ {} reactions for ", star.reactions))
+        .push_bold_safe(&message.author.name)
+        .push(" in ")
+        .channel(star.channel_id)
+        .push(format!(
+            " <{}>\n",
+            message_link(server_id, star.channel_id, star.message_id)
+        ));
+    for line in content.lines() {
+        builder.push_quote_line_safe(line);
+    }
+    if message.content.chars().count() > STARBOARD_MAX_CHARS {
+        builder.push("...");
+    }
+    if let Err(e) = star.starboard.say(&ctx.http, builder.build()).await {
+        error!("Failed to post {} to the starboard: {}", star.message_id, e);
+    }
 }
 
 async fn set_bot_activity(ctx: &Context) {
@@ -127,7 +322,7 @@ async fn store_replay(ctx: &Context) {
             .unwrap();
         info!("Got {} messages", msgs.len());
         if msgs.len() > 0 {
-            new_messages.insert(story_key, msgs);
+            new_messages.insert(story_key, with_attachment_texts(ctx, msgs).await);
         }
     }
     info!(
@@ -141,16 +336,40 @@ async fn store_replay(ctx: &Context) {
             .expect("Expected StoryData in TypeMap.")
             .clone()
     };
-    let mut store = store_lock.write().unwrap();
-    for (story_key, messages) in new_messages {
-        //Since we got these messages from the store, we can expect the key to exist
-        let channel_data = store.get_channel_data_mut(&story_key).unwrap();
-        for message in messages {
-            channel_data.update(&message);
+    let announcements = {
+        let mut store = store_lock.write().unwrap();
+        let mut announcements = vec![];
+        for (story_key, messages) in new_messages {
+            for message in messages {
+                announcements.extend(store.update_channel_data(&story_key, &message));
+            }
         }
-    }
-    store.finish_replay();
+        announcements.extend(store.finish_replay());
+        save_announced_milestones(&store, &announcements);
+        announcements
+    };
     info!("Finished initialising");
+    announce_milestones(ctx, announcements).await;
+}
+
+// Saved straight away rather than on the next [dump_state], so a restart can't announce them twice
+fn save_announced_milestones(store: &Store, announcements: &[(ChannelId, Milestone)]) {
+    if !announcements.is_empty() {
+        if let Err(e) = store.dump() {
+            error!("Failed to save state after milestones: {}", e);
+        }
+    }
+}
+
+async fn announce_milestones(ctx: &Context, announcements: Vec<(ChannelId, Milestone)>) {
+    for (announcement_channel, milestone) in announcements {
+        if let Err(e) = announcement_channel
+            .say(&ctx.http, milestone.announcement())
+            .await
+        {
+            error!("Failed to announce milestone {:?}: {}", milestone, e);
+        }
+    }
 }
 
 async fn dictionary_update_worker(_ctx: Arc<Context>) {
@@ -178,6 +397,129 @@ async fn dump_state(ctx: Arc<Context>) {
     }
 }
 
+async fn post_digests(ctx: Arc<Context>) {
+    loop {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let digests = store_lock.read().unwrap().due_digests(chrono::Utc::now());
+        for digest in digests {
+            let mut posted = true;
+            for message in digest.messages.iter() {
+                if let Err(e) = digest.channel_id.say(&ctx.http, message).await {
+                    // It's still due, so it'll be tried again next time round
+                    error!("Failed to post digest in {}: {}", digest.channel_id, e);
+                    posted = false;
+                    break;
+                }
+            }
+            if posted {
+                let mut store = store_lock.write().unwrap();
+                store.mark_digest_posted(&digest);
+                // Saved straight away so a restart can't post the same digest twice
+                if let Err(e) = store.dump() {
+                    error!("Failed to save state after digests: {}", e);
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(60)).await;
+    }
+}
+
+async fn nudge_next_writers(ctx: Arc<Context>) {
+    loop {
+        let nudges = {
+            let store_lock = {
+                let data_read = ctx.data.read().await;
+                data_read
+                    .get::<StoreData>()
+                    .expect("Expected StoryData in TypeMap.")
+                    .clone()
+            };
+            let mut store = store_lock.write().unwrap();
+            let nudges = store.take_due_nudges(chrono::Utc::now());
+            if !nudges.is_empty() {
+                if let Err(e) = store.dump() {
+                    error!("Failed to save state after nudges: {}", e);
+                }
+            }
+            nudges
+        };
+        for nudge in nudges {
+            // [take_due_nudges] only returns turns with someone up next
+            let next_writer = nudge.turn.next.unwrap();
+            let content = MessageBuilder::new()
+                .push("It's your turn in ")
+                .channel(nudge.channel_id)
+                .push("! The last post was by ")
+                .mention(&nudge.turn.last_poster)
+                .push(format!(
+                    " on {}",
+                    nudge.turn.last_post_time.format("%Y-%m-%d at %H:%M UTC")
+                ))
+                .build();
+            let sent = match next_writer.create_dm_channel(&ctx.http).await {
+                Ok(dm_channel) => dm_channel.say(&ctx.http, content).await.map(|_| ()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = sent {
+                error!(
+                    "Failed to nudge {} in {} on {}: {}",
+                    next_writer, nudge.channel_id, nudge.server_id, e
+                );
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(60)).await;
+    }
+}
+
+async fn remind_stalled_stories(ctx: Arc<Context>) {
+    loop {
+        let due_reminders = {
+            let store_lock = {
+                let data_read = ctx.data.read().await;
+                data_read
+                    .get::<StoreData>()
+                    .expect("Expected StoryData in TypeMap.")
+                    .clone()
+            };
+            let mut store = store_lock.write().unwrap();
+            let due_reminders = store.take_due_reminders(chrono::Utc::now());
+            if !due_reminders.is_empty() {
+                if let Err(e) = store.dump() {
+                    error!("Failed to save state after stall reminders: {}", e);
+                }
+            }
+            due_reminders
+        };
+        for reminder in due_reminders {
+            let mut builder = MessageBuilder::new();
+            for user_id in reminder.participants.iter() {
+                builder.mention(user_id).push(" ");
+            }
+            builder
+                .push(format!(
+                    "It's been {} since anyone posted here, pick the story back up when you can!",
+                    describe_duration(reminder.idle_for)
+                ))
+                .apply_if(reminder.is_last, |b| {
+                    b.push(" This is the last reminder until someone posts again")
+                });
+            if let Err(e) = reminder.channel_id.say(&ctx.http, builder.build()).await {
+                error!(
+                    "Failed to post a stall reminder in {} on {}: {}",
+                    reminder.channel_id, reminder.server_id, e
+                );
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(60)).await;
+    }
+}
+
 fn maybe_start_python_wordcloud_worker(config: &GeneralAppConfig) {
     if let Some(word_cloud_config) = &config.wordcloud_config {
         let default_python_path = PathBuf::from(".");
@@ -256,6 +598,7 @@ async fn main() {
         };
         data.insert::<StoreData>(Arc::new(RwLock::new(store)));
         data.insert::<GeneralAppConfigData>(Arc::new(RwLock::new(config)));
+        data.insert::<HeldMessagesData>(Arc::new(RwLock::new(HeldMessages::default())));
     }
 
     // start listening for events by starting a single shard
@@ -291,6 +634,19 @@ async fn help(
     Ok(())
 }
 
+// Checked before anything is done with a message, like downloading its attachments
+async fn channel_is_initialised(ctx: &Context, story_key: &StoryKey) -> bool {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    store.channel_data_exists(story_key)
+}
+
 async fn update_stats_if_exist(story_key: StoryKey, ctx: &Context, message: &Message) {
     let store_lock = {
         let data_read = ctx.data.read().await;
@@ -299,8 +655,61 @@ async fn update_stats_if_exist(story_key: StoryKey, ctx: &Context, message: &Mes
             .expect("Expected StoryData in TypeMap.")
             .clone()
     };
-    let mut store = store_lock.write().unwrap();
-    store.process_message(&story_key, message);
+    let announcements = {
+        let mut store = store_lock.write().unwrap();
+        let announcements = store.process_message(&story_key, message);
+        save_announced_milestones(&store, &announcements);
+        announcements
+    };
+    announce_milestones(ctx, announcements).await;
+}
+
+// Waits to see whether a proxy bot deletes [message] to repost it as a character, returning
+// whether it's still there to be counted. Servers that have never used a proxy bot don't wait
+async fn wait_for_proxy(ctx: &Context, message: &Message) -> bool {
+    let uses_proxies = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoreData in TypeMap.")
+                .clone()
+        };
+        let store = store_lock.read().unwrap();
+        message
+            .guild_id
+            .and_then(|server_id| store.get_server_data(&server_id))
+            .map(|server_data| server_data.uses_proxies())
+            .unwrap_or(false)
+    };
+    if !uses_proxies {
+        return true;
+    }
+    let hold_seconds = {
+        let config_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<GeneralAppConfigData>()
+                .expect("Expected GeneralAppConfigData in TypeMap.")
+                .clone()
+        };
+        let config = config_lock.read().unwrap();
+        config.proxy_config.hold_seconds
+    };
+    if hold_seconds == 0 {
+        return true;
+    }
+    let held_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<HeldMessagesData>()
+            .expect("Expected HeldMessagesData in TypeMap.")
+            .clone()
+    };
+    held_lock.write().unwrap().hold(message.id);
+    tokio::time::sleep(Duration::from_secs(hold_seconds)).await;
+    let still_there = held_lock.write().unwrap().release(message.id);
+    still_there
 }
 
 #[hook]
@@ -308,7 +717,55 @@ async fn on_regular_message(ctx: &Context, message: &Message) {
     //Update a stats if this channel is initialised
     if let Some(server_id) = message.guild_id {
         let story_key = (server_id, message.channel_id);
-        update_stats_if_exist(story_key, ctx, message).await;
+        if !channel_is_initialised(ctx, &story_key).await {
+            return;
+        }
+        if !wait_for_proxy(ctx, message).await {
+            debug!("Message {} was deleted, most likely by a proxy bot", message.id);
+            return;
+        }
+        let message = with_attachment_text(ctx, message).await;
+        update_stats_if_exist(story_key, ctx, &message).await;
+    }
+}
+
+async fn on_proxied_message(ctx: &Context, message: &Message) {
+    let server_id = match message.guild_id {
+        Some(server_id) => server_id,
+        None => return,
+    };
+    let story_key = (server_id, message.channel_id);
+    if !channel_is_initialised(ctx, &story_key).await {
+        return;
+    }
+    let with_text = with_attachment_text(ctx, message).await;
+    update_stats_if_exist(story_key, ctx, &with_text).await;
+    let character = match character_name(message) {
+        Some(character) => character,
+        None => return,
+    };
+    let unclaimed = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let store = store_lock.read().unwrap();
+        match store.get_server_data(&server_id) {
+            Some(server_data) => server_data.character_owners.owner(character).is_none(),
+            None => false,
+        }
+    };
+    if unclaimed {
+        // Fails when PluralKit lookups aren't set up, then it's up to [character claim]
+        let characters = vec![(String::from(character), message.id)];
+        if let Ok(claimed) = claim_from_pluralkit(ctx, &server_id, characters).await {
+            for (character, owner) in claimed {
+                info!("PluralKit says {} is played by {}", character, owner);
+            }
+        }
     }
 }
 
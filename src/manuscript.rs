@@ -0,0 +1,430 @@
+use crate::archive::{Archive, ArchivedMessage};
+use crate::posts::continues_post;
+use chrono::Duration;
+use serenity::model::id::MessageId;
+use std::io::{Cursor, Write};
+use strum_macros::EnumIter;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Epub,
+}
+impl ExportFormat {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+            Self::Html => "html",
+            Self::Epub => "epub",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Epub => "epub",
+        }
+    }
+}
+
+/// How posts are credited to whoever wrote them
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter)]
+pub enum AuthorStyle {
+    // The author's name in front of each post
+    Attribution,
+    // A heading whenever someone else takes over, like a change of POV character
+    Headings,
+    // Just the text
+    Anonymous,
+}
+impl AuthorStyle {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Attribution => "attribution",
+            Self::Headings => "headings",
+            Self::Anonymous => "anonymous",
+        }
+    }
+}
+
+/// A run of posts under one (optional) chapter title. Posts split over several messages are
+/// put back together
+pub struct Section {
+    pub title: Option<String>,
+    pub posts: Vec<ArchivedMessage>,
+}
+
+// What a section is laid out as, whatever the format
+enum Block<'a> {
+    Heading(&'a str),
+    Post(Option<&'a str>, &'a str),
+}
+
+/// An archive laid out as a book, split into sections at each chapter start
+pub struct Manuscript {
+    pub title: String,
+    pub sections: Vec<Section>,
+    pub author_style: AuthorStyle,
+}
+
+impl Manuscript {
+    /// [chapter_starts] are (first message, title) in order. Anything before the first chapter
+    /// goes in an untitled section, and chapters with nothing archived are left out. Messages
+    /// are merged into posts using [post_window], see [continues_post]
+    pub fn new(
+        title: &str,
+        archive: &Archive,
+        chapter_starts: &[(MessageId, String)],
+        author_style: AuthorStyle,
+        post_window: Duration,
+    ) -> Self {
+        let mut sections: Vec<Section> = vec![Section {
+            title: None,
+            posts: vec![],
+        }];
+        let mut next_chapters = chapter_starts.iter().peekable();
+        for (message_id, post) in archive.messages() {
+            while let Some((start, chapter_title)) = next_chapters.peek() {
+                if start > message_id {
+                    break;
+                }
+                sections.push(Section {
+                    title: Some(chapter_title.clone()),
+                    posts: vec![],
+                });
+                next_chapters.next();
+            }
+            let posts = &mut sections.last_mut().unwrap().posts;
+            match posts.last_mut() {
+                Some(previous)
+                    if continues_post(
+                        Some((previous.author_id, previous.timestamp)),
+                        post.author_id,
+                        post.timestamp,
+                        post_window,
+                    ) =>
+                {
+                    previous.content.push_str("\n\n");
+                    previous.content.push_str(&post.content);
+                    // So the window runs from the latest part
+                    previous.timestamp = post.timestamp;
+                }
+                _ => posts.push(post.clone()),
+            }
+        }
+        sections.retain(|section| !section.posts.is_empty());
+        Self {
+            title: String::from(title),
+            sections,
+            author_style,
+        }
+    }
+
+    // Author headings start again with each section, so every chapter opens with one
+    fn blocks<'b>(&self, section: &'b Section) -> Vec<Block<'b>> {
+        let mut blocks = vec![];
+        let mut last_author = None;
+        for post in section.posts.iter() {
+            match self.author_style {
+                AuthorStyle::Attribution => {
+                    blocks.push(Block::Post(Some(&post.author_name), &post.content))
+                }
+                AuthorStyle::Headings => {
+                    if last_author != Some(post.author_id) {
+                        blocks.push(Block::Heading(&post.author_name));
+                        last_author = Some(post.author_id);
+                    }
+                    blocks.push(Block::Post(None, &post.content));
+                }
+                AuthorStyle::Anonymous => blocks.push(Block::Post(None, &post.content)),
+            }
+        }
+        blocks
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.title);
+        for section in self.sections.iter() {
+            if let Some(title) = &section.title {
+                out.push_str(&format!("## {}\n\n", title));
+            }
+            for block in self.blocks(section) {
+                match block {
+                    Block::Heading(author) => out.push_str(&format!("### {}\n\n", author)),
+                    Block::Post(Some(author), content) => {
+                        out.push_str(&format!("**{}:** {}\n\n", author, content))
+                    }
+                    Block::Post(None, content) => out.push_str(&format!("{}\n\n", content)),
+                }
+            }
+        }
+        out
+    }
+
+    // The body of one section as XHTML, which is also valid HTML
+    fn section_html(&self, section: &Section) -> String {
+        let mut out = String::new();
+        if let Some(title) = &section.title {
+            out.push_str(&format!("<h2>{}</h2>\n", escape_html(title)));
+        }
+        for block in self.blocks(section) {
+            match block {
+                Block::Heading(author) => {
+                    out.push_str(&format!("<h3>{}</h3>\n", escape_html(author)))
+                }
+                Block::Post(author, content) => {
+                    let attribution = match author {
+                        Some(author) => format!("<strong>{}:</strong> ", escape_html(author)),
+                        None => String::new(),
+                    };
+                    for (i, paragraph) in paragraphs(content).iter().enumerate() {
+                        out.push_str(&format!(
+                            "<p>{}{}</p>\n",
+                            if i == 0 { attribution.as_str() } else { "" },
+                            paragraph
+                        ));
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let body: String = self
+            .sections
+            .iter()
+            .map(|section| self.section_html(section))
+            .collect();
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\"/>\n<title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n",
+            title = escape_html(&self.title),
+            body = body
+        )
+    }
+
+    /// An EPUB 3 book with one file per section
+    pub fn to_epub(&self) -> Result<Vec<u8>, String> {
+        let title = escape_html(&self.title);
+        let section_titles: Vec<String> = self
+            .sections
+            .iter()
+            // Only what comes before the first chapter has no title
+            .map(|section| match &section.title {
+                Some(section_title) => escape_html(section_title),
+                None => String::from("Prologue"),
+            })
+            .collect();
+        let mut files: Vec<(String, String)> = vec![(
+            String::from("META-INF/container.xml"),
+            String::from(CONTAINER_XML),
+        )];
+        let mut manifest = String::new();
+        let mut spine = String::new();
+        let mut nav = String::new();
+        for (i, section) in self.sections.iter().enumerate() {
+            let file_name = format!("section-{}.xhtml", i + 1);
+            files.push((
+                format!("OEBPS/{}", file_name),
+                xhtml_page(&section_titles[i], &self.section_html(section)),
+            ));
+            manifest.push_str(&format!(
+                "<item id=\"section-{i}\" href=\"{file}\" media-type=\"application/xhtml+xml\"/>\n",
+                i = i + 1,
+                file = file_name
+            ));
+            spine.push_str(&format!("<itemref idref=\"section-{}\"/>\n", i + 1));
+            nav.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                file_name, section_titles[i]
+            ));
+        }
+        files.push((
+            String::from("OEBPS/nav.xhtml"),
+            xhtml_page(
+                &title,
+                &format!(
+                    "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n<ol>\n{}</ol>\n</nav>\n",
+                    title, nav
+                ),
+            ),
+        ));
+        files.push((
+            String::from("OEBPS/content.opf"),
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+<dc:identifier id="book-id">urn:uuid:{identifier}</dc:identifier>
+<dc:title>{title}</dc:title>
+<dc:language>en</dc:language>
+<meta property="dcterms:modified">{modified}</meta>
+</metadata>
+<manifest>
+<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+{manifest}</manifest>
+<spine>
+{spine}</spine>
+</package>
+"#,
+                title = title,
+                identifier = uuid::Uuid::new_v4(),
+                modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+                manifest = manifest,
+                spine = spine
+            ),
+        ));
+
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        let to_error = |e: &dyn std::fmt::Display| format!("Failed writing the EPUB: {}", e);
+        // Readers check the mimetype comes first and isn't compressed
+        zip.start_file(
+            "mimetype",
+            FileOptions::default().compression_method(CompressionMethod::Stored),
+        )
+        .map_err(|e| to_error(&e))?;
+        zip.write_all(b"application/epub+zip")
+            .map_err(|e| to_error(&e))?;
+        for (path, contents) in files {
+            zip.start_file(path, FileOptions::default())
+                .map_err(|e| to_error(&e))?;
+            zip.write_all(contents.as_bytes())
+                .map_err(|e| to_error(&e))?;
+        }
+        let cursor = zip.finish().map_err(|e| to_error(&e))?;
+        Ok(cursor.into_inner())
+    }
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles>
+</container>
+"#;
+
+fn xhtml_page(title: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n<head>\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        title, body
+    )
+}
+
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Blank lines split paragraphs, single line breaks are kept within them
+fn paragraphs(content: &str) -> Vec<String> {
+    content
+        .split("\n\n")
+        .map(|paragraph| paragraph.trim())
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| {
+            paragraph
+                .lines()
+                .map(escape_html)
+                .collect::<Vec<String>>()
+                .join("<br/>")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::archive::{Archive, ArchivedMessage};
+    use crate::manuscript::{paragraphs, AuthorStyle, Manuscript};
+    use chrono::{Duration, TimeZone, Utc};
+    use serenity::model::id::{MessageId, UserId};
+
+    fn archive() -> Archive {
+        let mut archive = Archive::default();
+        for (id, author, content) in [
+            (1, "Nero", "Rome burns."),
+            (2, "Nero", "I play on."),
+            (3, "Seneca", "We should leave."),
+        ]
+        .iter()
+        {
+            archive.insert(
+                MessageId(*id),
+                ArchivedMessage {
+                    author_id: UserId(author.len() as u64),
+                    author_name: String::from(*author),
+                    timestamp: Utc.ymd(2026, 10, 1).and_hms(20, 0, *id as u32),
+                    content: String::from(*content),
+                },
+            );
+        }
+        archive
+    }
+
+    #[test]
+    fn markdown_splits_chapters_and_credits_authors() {
+        let archive = archive();
+        let chapters = vec![(MessageId(2), String::from("The Fire"))];
+        let manuscript = Manuscript::new(
+            "Rome",
+            &archive,
+            &chapters,
+            AuthorStyle::Headings,
+            Duration::zero(),
+        );
+        assert_eq!(
+            manuscript.to_markdown(),
+            "# Rome\n\n### Nero\n\nRome burns.\n\n## The Fire\n\n### Nero\n\nI play on.\n\n### Seneca\n\nWe should leave.\n\n"
+        );
+        let manuscript = Manuscript::new(
+            "Rome",
+            &archive,
+            &[],
+            AuthorStyle::Attribution,
+            Duration::zero(),
+        );
+        assert!(manuscript
+            .to_markdown()
+            .ends_with("**Nero:** I play on.\n\n**Seneca:** We should leave.\n\n"));
+    }
+
+    #[test]
+    fn split_posts_are_put_back_together() {
+        let archive = archive();
+        let manuscript = Manuscript::new(
+            "Rome",
+            &archive,
+            &[],
+            AuthorStyle::Attribution,
+            Duration::minutes(5),
+        );
+        assert_eq!(
+            manuscript.to_markdown(),
+            "# Rome\n\n**Nero:** Rome burns.\n\nI play on.\n\n**Seneca:** We should leave.\n\n"
+        );
+        // A chapter starting part way through still splits it
+        let chapters = vec![(MessageId(2), String::from("The Fire"))];
+        let manuscript = Manuscript::new(
+            "Rome",
+            &archive,
+            &chapters,
+            AuthorStyle::Attribution,
+            Duration::minutes(5),
+        );
+        assert_eq!(manuscript.sections.len(), 2);
+    }
+
+    #[test]
+    fn html_paragraphs_are_escaped() {
+        assert_eq!(
+            paragraphs("<b>one</b>\ntwo\n\n\nthree & four"),
+            vec!["&lt;b&gt;one&lt;/b&gt;<br/>two", "three &amp; four"]
+        );
+    }
+}
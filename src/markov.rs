@@ -0,0 +1,152 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::MessageId;
+use std::collections::HashMap;
+
+/// Keeps a model from growing without limit, once it knows this many pairs of words it only
+/// learns more about the ones it knows
+pub const MAX_STATES: usize = 10_000;
+// Below this there's so little to go on the output is mostly quotes
+pub const MIN_STATES: usize = 50;
+// Stands in for the start and end of a post
+const BOUNDARY: &str = "";
+
+// The two words before the next one, kept as one string so the model pickles simply
+fn state_key(first: &str, second: &str) -> String {
+    format!("{} {}", first, second)
+}
+
+/// An order-2 Markov chain over someone's posts: for each pair of words, what came next and how
+/// often. Words keep their punctuation and capitals so the output reads like the writer's own
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarkovModel {
+    transitions: HashMap<String, HashMap<String, u32>>,
+    // Messages arrive in order, so anything at or before this has been learned from already
+    last_learned: Option<MessageId>,
+}
+
+impl MarkovModel {
+    pub fn learn(&mut self, message_id: MessageId, content: &str) {
+        if let Some(last_learned) = self.last_learned {
+            if message_id <= last_learned {
+                return;
+            }
+        }
+        self.last_learned = Some(message_id);
+        for paragraph in content.split("\n\n") {
+            self.learn_words(paragraph.split_whitespace().collect());
+        }
+    }
+
+    fn learn_words(&mut self, words: Vec<&str>) {
+        if words.is_empty() {
+            return;
+        }
+        let mut previous = (BOUNDARY, BOUNDARY);
+        for next in words.into_iter().chain(std::iter::once(BOUNDARY)) {
+            let key = state_key(previous.0, previous.1);
+            previous = (previous.1, next);
+            if self.transitions.len() >= MAX_STATES && !self.transitions.contains_key(&key) {
+                continue;
+            }
+            *self
+                .transitions
+                .entry(key)
+                .or_default()
+                .entry(String::from(next))
+                .or_insert(0) += 1;
+        }
+    }
+
+    pub fn states(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Adds what [other] has learned to this one, for imitating someone across channels
+    pub fn merge(&mut self, other: &MarkovModel) {
+        for (key, next_words) in other.transitions.iter() {
+            let own_next_words = self.transitions.entry(key.clone()).or_default();
+            for (word, count) in next_words.iter() {
+                *own_next_words.entry(word.clone()).or_insert(0) += count;
+            }
+        }
+    }
+
+    fn pick_next<R: Rng>(&self, rng: &mut R, key: &str) -> Option<&str> {
+        let next_words = self.transitions.get(key)?;
+        let total: u32 = next_words.values().sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.gen_range(0, total);
+        for (word, count) in next_words.iter() {
+            if pick < *count {
+                return Some(word);
+            }
+            pick -= count;
+        }
+        None
+    }
+
+    /// Strings together posts until there are at least [min_words], stopping at [max_words]
+    pub fn generate<R: Rng>(&self, rng: &mut R, min_words: usize, max_words: usize) -> String {
+        let mut words: Vec<&str> = vec![];
+        let mut previous = (BOUNDARY, BOUNDARY);
+        while words.len() < max_words {
+            match self.pick_next(rng, &state_key(previous.0, previous.1)) {
+                Some(next) if next != BOUNDARY => {
+                    words.push(next);
+                    previous = (previous.1, next);
+                }
+                // The end of a post, or somewhere only seen at the end of a model that filled up
+                _ => {
+                    let at_start = previous == (BOUNDARY, BOUNDARY);
+                    if at_start || words.len() >= min_words {
+                        break;
+                    }
+                    previous = (BOUNDARY, BOUNDARY);
+                }
+            }
+        }
+        words.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::markov::{MarkovModel, MAX_STATES};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use serenity::model::id::MessageId;
+
+    #[test]
+    fn generated_text_only_uses_learned_transitions() {
+        let mut model = MarkovModel::default();
+        model.learn(MessageId(1), "The cat sat on the mat.");
+        model.learn(MessageId(2), "The cat ran off.");
+        // Already learned from
+        model.learn(MessageId(1), "Something else entirely.");
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..20 {
+            let text = model.generate(&mut rng, 1, 50);
+            assert!(
+                text == "The cat sat on the mat." || text == "The cat ran off.",
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn models_stop_learning_new_states_when_full() {
+        let mut model = MarkovModel::default();
+        let words: Vec<String> = (0..MAX_STATES + 10).map(|i| i.to_string()).collect();
+        model.learn(MessageId(1), &words.join(" "));
+        assert_eq!(model.states(), MAX_STATES);
+        let mut other = MarkovModel::default();
+        other.learn(MessageId(1), "Just a few words");
+        model.merge(&other);
+        assert_eq!(model.states(), MAX_STATES + 4);
+    }
+}
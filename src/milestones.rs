@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, UserId};
+use serenity::utils::MessageBuilder;
+
+/// Which word count a [Milestone] is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MilestoneScope {
+    Channel(ChannelId),
+    AuthorInChannel(ChannelId, UserId),
+    // An author's words across all the server's initialised channels
+    AuthorOnServer(UserId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Milestone {
+    pub scope: MilestoneScope,
+    pub threshold: usize,
+}
+
+impl Milestone {
+    pub fn announcement(&self) -> String {
+        let mut builder = MessageBuilder::new();
+        match self.scope {
+            MilestoneScope::Channel(channel_id) => builder.channel(channel_id),
+            MilestoneScope::AuthorInChannel(_, user_id)
+            | MilestoneScope::AuthorOnServer(user_id) => builder.mention(&user_id),
+        };
+        builder.push(format!(
+            " just passed {} words",
+            with_thousands_separators(self.threshold)
+        ));
+        match self.scope {
+            MilestoneScope::Channel(_) => builder.push("!"),
+            MilestoneScope::AuthorInChannel(channel_id, _) => {
+                builder.push(" in ").channel(channel_id).push("!")
+            }
+            MilestoneScope::AuthorOnServer(_) => builder.push(" across the server!"),
+        };
+        builder.push(" Congratulations!").build()
+    }
+}
+
+/// Milestones are only announced once there's a channel to announce them in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MilestoneSettings {
+    pub announcement_channel: Option<ChannelId>,
+    pub channel_thresholds: Vec<usize>,
+    pub author_channel_thresholds: Vec<usize>,
+    pub author_server_thresholds: Vec<usize>,
+}
+
+impl Default for MilestoneSettings {
+    fn default() -> Self {
+        Self {
+            announcement_channel: None,
+            channel_thresholds: vec![10_000, 50_000, 100_000, 250_000, 500_000, 1_000_000],
+            author_channel_thresholds: vec![10_000, 50_000, 100_000],
+            author_server_thresholds: vec![10_000, 50_000, 100_000, 250_000, 500_000, 1_000_000],
+        }
+    }
+}
+
+/// Word counts from before and after a message, for each kind of milestone
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MilestoneCounts {
+    pub channel: usize,
+    pub author_in_channel: usize,
+    pub author_on_server: usize,
+}
+
+impl MilestoneSettings {
+    pub fn crossed(
+        &self,
+        channel_id: ChannelId,
+        user_id: UserId,
+        before: MilestoneCounts,
+        after: MilestoneCounts,
+    ) -> Vec<Milestone> {
+        let kinds = [
+            (
+                MilestoneScope::Channel(channel_id),
+                &self.channel_thresholds,
+                before.channel,
+                after.channel,
+            ),
+            (
+                MilestoneScope::AuthorInChannel(channel_id, user_id),
+                &self.author_channel_thresholds,
+                before.author_in_channel,
+                after.author_in_channel,
+            ),
+            (
+                MilestoneScope::AuthorOnServer(user_id),
+                &self.author_server_thresholds,
+                before.author_on_server,
+                after.author_on_server,
+            ),
+        ];
+        let mut milestones = vec![];
+        for (scope, thresholds, before, after) in kinds.iter() {
+            for threshold in thresholds.iter() {
+                if before < threshold && after >= threshold {
+                    milestones.push(Milestone {
+                        scope: *scope,
+                        threshold: *threshold,
+                    });
+                }
+            }
+        }
+        milestones
+    }
+}
+
+pub fn with_thousands_separators(n: usize) -> String {
+    let digits: Vec<char> = n.to_string().chars().collect();
+    digits
+        .rchunks(3)
+        .rev()
+        .map(|group| group.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::milestones::{
+        with_thousands_separators, Milestone, MilestoneCounts, MilestoneScope, MilestoneSettings,
+    };
+    use serenity::model::id::{ChannelId, UserId};
+
+    #[test]
+    fn only_thresholds_crossed_by_the_message_count() {
+        let settings = MilestoneSettings::default();
+        let before = MilestoneCounts {
+            channel: 99_990,
+            author_in_channel: 9_000,
+            author_on_server: 120_000,
+        };
+        let after = MilestoneCounts {
+            channel: 100_010,
+            author_in_channel: 9_020,
+            author_on_server: 120_020,
+        };
+        let crossed = settings.crossed(ChannelId(1), UserId(2), before, after);
+        assert_eq!(
+            crossed,
+            [Milestone {
+                scope: MilestoneScope::Channel(ChannelId(1)),
+                threshold: 100_000
+            }]
+        );
+        // Already past every threshold before the message, nothing new to announce
+        let crossed = settings.crossed(ChannelId(1), UserId(2), after, after);
+        assert!(crossed.is_empty());
+    }
+
+    #[test]
+    fn thousands_separators() {
+        assert_eq!(with_thousands_separators(999), "999");
+        assert_eq!(with_thousands_separators(100_000), "100,000");
+        assert_eq!(with_thousands_separators(1_000_000), "1,000,000");
+    }
+}
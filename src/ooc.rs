@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use serenity::model::channel::Message;
+use serenity::model::id::UserId;
+use std::collections::HashSet;
+
+/// Per-channel rules for picking out-of-character (OOC) chatter from story posts, so only the
+/// prose counts towards the main stats. The default is to treat everything as in-character
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OocRules {
+    // (open, close) pairs, e.g. ("((", "))"). An unclosed bracket runs to the end of the message
+    pub brackets: Vec<(String, String)>,
+    // Lines starting with any of these are OOC, matched case-insensitively
+    pub line_prefixes: Vec<String>,
+    // Messages with fewer in-character words than this are OOC entirely. 0 disables it
+    pub min_words: usize,
+    pub ignore_bots: bool,
+    pub ignored_users: HashSet<UserId>,
+}
+
+impl OocRules {
+    /// The conventions most role-play servers use, a reasonable starting point
+    pub fn common() -> Self {
+        Self {
+            brackets: vec![
+                (String::from("(("), String::from("))")),
+                (String::from("[["), String::from("]]")),
+            ],
+            line_prefixes: vec![String::from("//"), String::from("ooc:")],
+            min_words: 0,
+            ignore_bots: true,
+            ignored_users: HashSet::new(),
+        }
+    }
+
+    /// Splits a message into its (in-character, out-of-character) text
+    pub fn split_message(&self, message: &Message) -> (String, String) {
+        // Proxy bots post through webhooks, which are bot users, but they're story posts
+        let from_bot = message.author.bot && message.webhook_id.is_none();
+        if (self.ignore_bots && from_bot)
+            || self.ignored_users.contains(&message.author.id)
+        {
+            (String::new(), message.content.clone())
+        } else {
+            self.split_content(&message.content)
+        }
+    }
+
+    pub fn split_content(&self, content: &str) -> (String, String) {
+        let (in_character, mut out_of_character) = self.split_brackets(content);
+        let mut prose_lines = vec![];
+        for line in in_character.lines() {
+            let trimmed_line = line.trim_start();
+            let lowercase_line = trimmed_line.to_lowercase();
+            match self
+                .line_prefixes
+                .iter()
+                .find(|prefix| lowercase_line.starts_with(prefix.as_str()))
+            {
+                Some(prefix) => {
+                    out_of_character.push('\n');
+                    out_of_character.push_str(trimmed_line.get(prefix.len()..).unwrap_or(""));
+                }
+                None => prose_lines.push(line),
+            }
+        }
+        let in_character = prose_lines.join("\n");
+        if self.min_words > 0
+            && crate::language_parsing::tokenise(&in_character).len() < self.min_words
+        {
+            out_of_character.push('\n');
+            out_of_character.push_str(&in_character);
+            (String::new(), out_of_character)
+        } else {
+            (in_character, out_of_character)
+        }
+    }
+
+    fn split_brackets(&self, content: &str) -> (String, String) {
+        let mut in_character = String::new();
+        let mut out_of_character = String::new();
+        let mut rest = content;
+        loop {
+            // Find whichever opening bracket comes first in what's left
+            let first_open = self
+                .brackets
+                .iter()
+                .filter_map(|(open, close)| rest.find(open.as_str()).map(|i| (i, open, close)))
+                .min_by_key(|(i, _, _)| *i);
+            match first_open {
+                Some((i, open, close)) => {
+                    in_character.push_str(&rest[..i]);
+                    let inside = &rest[i + open.len()..];
+                    match inside.find(close.as_str()) {
+                        Some(j) => {
+                            out_of_character.push_str(&inside[..j]);
+                            out_of_character.push('\n');
+                            rest = &inside[j + close.len()..];
+                        }
+                        None => {
+                            out_of_character.push_str(inside);
+                            break;
+                        }
+                    }
+                }
+                None => {
+                    in_character.push_str(rest);
+                    break;
+                }
+            }
+        }
+        (in_character, out_of_character)
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::ooc::OocRules;
+
+    #[test]
+    fn default_rules_keep_everything() {
+        let rules = OocRules::default();
+        let (ic, ooc) = rules.split_content("(( brb dinner ))\n// sorry for the delay");
+        assert_eq!(ic, "(( brb dinner ))\n// sorry for the delay");
+        assert_eq!(ooc, "");
+    }
+
+    #[test]
+    fn brackets_and_prefixes() {
+        let rules = OocRules::common();
+        let (ic, ooc) = rules.split_content(
+            "Ana kicks the robot. (( brb dinner ))\n// sorry for the delay\nOOC: lol\nShe rides off",
+        );
+        assert_eq!(ic, "Ana kicks the robot. \nShe rides off");
+        assert_eq!(
+            crate::language_parsing::tokenise(&ooc),
+            vec!["brb", "dinner", "sorry", "for", "the", "delay", "lol"]
+        );
+    }
+
+    #[test]
+    fn unclosed_bracket_runs_to_end() {
+        let rules = OocRules::common();
+        let (ic, ooc) = rules.split_content("The end. ((gotta go");
+        assert_eq!(ic, "The end. ");
+        assert_eq!(ooc, "gotta go");
+    }
+
+    #[test]
+    fn short_messages_are_ooc() {
+        let rules = OocRules {
+            min_words: 3,
+            ..OocRules::default()
+        };
+        let (ic, _) = rules.split_content("ok sure");
+        assert_eq!(ic, "");
+        let (ic, _) = rules.split_content("Ana looks up slowly");
+        assert_eq!(ic, "Ana looks up slowly");
+    }
+}
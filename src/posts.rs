@@ -0,0 +1,150 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::model::channel::Message;
+use serenity::model::id::{MessageId, UserId};
+use std::collections::{BTreeMap, HashMap};
+
+/// Writers often split one post over several messages to get around Discord's length limit.
+/// A message carries on the post before it when it's from the same author and sent within
+/// [window] of it. A zero window never merges anything
+pub fn continues_post(
+    previous: Option<(UserId, DateTime<Utc>)>,
+    author: UserId,
+    timestamp: DateTime<Utc>,
+    window: Duration,
+) -> bool {
+    match previous {
+        Some((previous_author, previous_time)) => {
+            window > Duration::zero()
+                && previous_author == author
+                && timestamp - previous_time <= window
+        }
+        None => false,
+    }
+}
+
+/// Who sent each of a channel's messages and when, so they can be grouped into logical posts
+/// whatever order they were seen in. Only covers messages processed since this was added
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostTimeline {
+    messages: BTreeMap<MessageId, (UserId, DateTime<Utc>)>,
+}
+
+impl PostTimeline {
+    /// [author] is who [message] counts for, which isn't the webhook for proxied posts
+    pub fn add(&mut self, message: &Message, author: UserId) {
+        self.messages
+            .insert(message.id, (author, message.timestamp));
+    }
+
+    /// The messages that carried on a post rather than starting one, oldest first
+    pub fn continuations(&self, window: Duration) -> Vec<(MessageId, UserId, DateTime<Utc>)> {
+        let mut previous = None;
+        let mut continuations = vec![];
+        for (message_id, (author, timestamp)) in self.messages.iter() {
+            if continues_post(previous, *author, *timestamp, window) {
+                continuations.push((*message_id, *author, *timestamp));
+            }
+            previous = Some((*author, *timestamp));
+        }
+        continuations
+    }
+
+    /// How many of each author's messages carried on a post, only those sent on the days in
+    /// [dates] (inclusive) if given. Authors without any aren't included
+    pub fn continuation_counts(
+        &self,
+        dates: Option<(NaiveDate, NaiveDate)>,
+        window: Duration,
+    ) -> HashMap<UserId, usize> {
+        let mut counts = HashMap::new();
+        for (_, author, timestamp) in self.continuations(window) {
+            let day = timestamp.naive_utc().date();
+            let in_dates = match dates {
+                Some((start, end)) => start <= day && day <= end,
+                None => true,
+            };
+            if in_dates {
+                *counts.entry(author).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// The latest message, [None] if no messages have been seen
+    pub fn latest(&self) -> Option<(MessageId, DateTime<Utc>)> {
+        self.messages
+            .iter()
+            .next_back()
+            .map(|(message_id, (_, timestamp))| (*message_id, *timestamp))
+    }
+
+    /// The first message of the latest post, [None] if no messages have been seen
+    pub fn latest_post_start(&self, window: Duration) -> Option<MessageId> {
+        let mut messages = self.messages.iter().rev();
+        let (mut start, mut next) = messages.next()?;
+        for (message_id, previous) in messages {
+            if !continues_post(Some(*previous), next.0, next.1, window) {
+                break;
+            }
+            start = message_id;
+            next = previous;
+        }
+        Some(*start)
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::posts::PostTimeline;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use serenity::model::id::{MessageId, UserId};
+
+    fn timeline() -> PostTimeline {
+        let at = |minutes: i64| -> DateTime<Utc> {
+            Utc.ymd(2026, 10, 1).and_hms(20, 0, 0) + Duration::minutes(minutes)
+        };
+        let mut timeline = PostTimeline::default();
+        for (id, author, minutes) in [
+            (1, 1, 0),
+            (2, 1, 1),
+            (3, 1, 2),
+            (4, 2, 3),
+            (5, 1, 4),
+            (6, 1, 30),
+        ]
+        .iter()
+        {
+            timeline
+                .messages
+                .insert(MessageId(*id), (UserId(*author), at(*minutes)));
+        }
+        timeline
+    }
+
+    #[test]
+    fn split_posts_merge_until_someone_else_posts_or_the_window_passes() {
+        let timeline = timeline();
+        let window = Duration::minutes(5);
+        let continued: Vec<u64> = timeline
+            .continuations(window)
+            .iter()
+            .map(|(message_id, _, _)| message_id.0)
+            .collect();
+        assert_eq!(continued, vec![2, 3]);
+        let counts = timeline.continuation_counts(None, window);
+        assert_eq!(counts.get(&UserId(1)), Some(&2));
+        assert_eq!(counts.get(&UserId(2)), None);
+        let day = Utc.ymd(2026, 10, 2).naive_utc();
+        assert!(timeline
+            .continuation_counts(Some((day, day)), window)
+            .is_empty());
+        assert_eq!(timeline.latest_post_start(window), Some(MessageId(6)));
+        assert_eq!(
+            timeline.latest_post_start(Duration::minutes(30)),
+            Some(MessageId(5))
+        );
+        assert!(timeline.continuations(Duration::zero()).is_empty());
+    }
+}
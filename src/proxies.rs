@@ -0,0 +1,290 @@
+use crate::stats::WordStats;
+use log::error;
+use serde::{Deserialize, Serialize};
+use serenity::async_trait;
+use serenity::model::channel::Message;
+use serenity::model::id::{MessageId, UserId};
+use serenity::model::user::User;
+use serenity::prelude::TypeMapKey;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Proxy bots (Tupperbox, PluralKit...) repost a writer's message through a webhook named after
+/// their character. [None] for anything that wasn't sent through a webhook
+pub fn character_name(message: &Message) -> Option<&str> {
+    message.webhook_id?;
+    let name = message.author.name.trim();
+    match name.is_empty() {
+        true => None,
+        false => Some(name),
+    }
+}
+
+/// Messages being held back to see if a proxy bot deletes them. Proxy bots delete the writer's
+/// message (e.g. `ana: Hello`) and repost it through a webhook as the character, so counting
+/// messages straight away would count every proxied post twice
+#[derive(Debug, Default)]
+pub struct HeldMessages {
+    // Whether each has been deleted while it was held
+    held: HashMap<MessageId, bool>,
+}
+
+impl HeldMessages {
+    pub fn hold(&mut self, message_id: MessageId) {
+        self.held.insert(message_id, false);
+    }
+
+    /// Only held messages are remembered, so this can be called for any deleted message
+    pub fn deleted(&mut self, message_id: MessageId) {
+        if let Some(deleted) = self.held.get_mut(&message_id) {
+            *deleted = true;
+        }
+    }
+
+    /// Stops holding [message_id], returning whether it's still there to be counted
+    pub fn release(&mut self, message_id: MessageId) -> bool {
+        self.held.remove(&message_id) == Some(false)
+    }
+}
+
+pub struct HeldMessagesData;
+
+impl TypeMapKey for HeldMessagesData {
+    type Value = Arc<RwLock<HeldMessages>>;
+}
+
+/// Who plays each character on a server, matched case-insensitively by name
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CharacterOwners {
+    // Lowercase name to (name as claimed, owner). The whole user is kept so their proxied posts
+    // can be counted as theirs, like anything else they write
+    owners: HashMap<String, (String, User)>,
+}
+
+impl CharacterOwners {
+    pub fn owner(&self, character: &str) -> Option<UserId> {
+        self.owner_user(character).map(|owner| owner.id)
+    }
+
+    pub fn owner_user(&self, character: &str) -> Option<&User> {
+        self.owners
+            .get(&character.to_lowercase())
+            .map(|(_, owner)| owner)
+    }
+
+    /// Who [message] counts for: whoever plays the character for proxied posts, [None] if nobody
+    /// has claimed them yet. Everything else is the author's
+    pub fn credited_author<'a>(&'a self, message: &'a Message) -> Option<&'a User> {
+        match character_name(message) {
+            Some(character) => self.owner_user(character),
+            None => Some(&message.author),
+        }
+    }
+
+    /// Returns whoever played them before, if it was someone else
+    pub fn claim(&mut self, character: &str, owner: &User) -> Option<UserId> {
+        self.owners
+            .insert(
+                character.to_lowercase(),
+                (String::from(character), owner.clone()),
+            )
+            .map(|(_, previous)| previous.id)
+            .filter(|previous| *previous != owner.id)
+    }
+
+    pub fn unclaim(&mut self, character: &str) -> Option<UserId> {
+        self.owners
+            .remove(&character.to_lowercase())
+            .map(|(_, owner)| owner.id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.owners.is_empty()
+    }
+
+    /// (name as claimed, owner)
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &UserId)> {
+        self.owners
+            .values()
+            .map(|(character, owner)| (character, &owner.id))
+    }
+}
+
+/// Someone's characters, most words first
+#[derive(Debug, PartialEq)]
+pub struct Player {
+    pub owner: UserId,
+    pub word_count: usize,
+    pub characters: Vec<(String, usize)>,
+}
+
+/// Characters' word counts rolled up under whoever plays them, most words first, along with
+/// the characters nobody has claimed
+pub fn roll_up(
+    character_stats: &HashMap<String, WordStats>,
+    owners: &CharacterOwners,
+) -> (Vec<Player>, Vec<(String, usize)>) {
+    let mut players: HashMap<UserId, Player> = HashMap::new();
+    let mut unclaimed = vec![];
+    for (character, stats) in character_stats.iter() {
+        let entry = (character.clone(), stats.word_count);
+        match owners.owner(character) {
+            Some(owner) => {
+                let player = players.entry(owner).or_insert(Player {
+                    owner,
+                    word_count: 0,
+                    characters: vec![],
+                });
+                player.word_count += stats.word_count;
+                player.characters.push(entry);
+            }
+            None => unclaimed.push(entry),
+        }
+    }
+    let by_words = |a: &(String, usize), b: &(String, usize)| b.1.cmp(&a.1).then(a.0.cmp(&b.0));
+    let mut players: Vec<Player> = players.into_values().collect();
+    for player in players.iter_mut() {
+        player.characters.sort_by(by_words);
+    }
+    players.sort_by(|a, b| b.word_count.cmp(&a.word_count).then(a.owner.cmp(&b.owner)));
+    unclaimed.sort_by(by_words);
+    (players, unclaimed)
+}
+
+/// A proxy bot that can say who really sent a message it proxied
+#[async_trait]
+pub trait ProxyApi {
+    /// [None] if it doesn't know the message, or couldn't be asked
+    async fn sender(&self, message_id: MessageId) -> Option<UserId>;
+}
+
+/// PluralKit's API, see https://pluralkit.me/api/endpoints/#get-proxied-message-information.
+/// Tupperbox doesn't have one, so its characters have to be claimed by hand
+pub struct PluralKit {
+    pub url: String,
+}
+
+#[async_trait]
+impl ProxyApi for PluralKit {
+    async fn sender(&self, message_id: MessageId) -> Option<UserId> {
+        let url = format!("{}/messages/{}", self.url.trim_end_matches('/'), message_id);
+        let response = match reqwest::get(&url).await {
+            Ok(response) => response,
+            Err(e) => {
+                error!(
+                    "Failed asking PluralKit about message {}: {}",
+                    message_id, e
+                );
+                return None;
+            }
+        };
+        // Not found when it wasn't a PluralKit message
+        if !response.status().is_success() {
+            return None;
+        }
+        let body: serde_json::Value = response.json().await.ok()?;
+        body.get("sender")?
+            .as_str()?
+            .parse::<u64>()
+            .ok()
+            .map(UserId)
+    }
+}
+
+/// Asks [api] who plays each of [characters], from a message each of them sent. Returns the
+/// ones it knew
+pub async fn look_up_owners<A: ProxyApi + Sync>(
+    api: &A,
+    characters: Vec<(String, MessageId)>,
+) -> Vec<(String, UserId)> {
+    let mut found = vec![];
+    for (character, message_id) in characters {
+        if let Some(owner) = api.sender(message_id).await {
+            found.push((character, owner));
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::proxies::{
+        look_up_owners, roll_up, CharacterOwners, HeldMessages, Player, ProxyApi,
+    };
+    use crate::stats::WordStats;
+    use serenity::async_trait;
+    use serenity::model::id::{MessageId, UserId};
+    use serenity::model::user::User;
+    use std::collections::HashMap;
+
+    fn user(id: u64) -> User {
+        let mut user = User::default();
+        user.id = UserId(id);
+        user
+    }
+
+    // Stands in for the proxy bot, knowing the senders of a fixed set of messages
+    struct KnownSenders(HashMap<MessageId, UserId>);
+
+    #[async_trait]
+    impl ProxyApi for KnownSenders {
+        async fn sender(&self, message_id: MessageId) -> Option<UserId> {
+            self.0.get(&message_id).cloned()
+        }
+    }
+
+    #[tokio::test]
+    async fn owners_come_from_the_proxy_api() {
+        let api = KnownSenders(vec![(MessageId(1), UserId(10))].into_iter().collect());
+        let found = look_up_owners(
+            &api,
+            vec![
+                (String::from("Aria"), MessageId(1)),
+                (String::from("Bex"), MessageId(2)),
+            ],
+        )
+        .await;
+        assert_eq!(found, vec![(String::from("Aria"), UserId(10))]);
+    }
+
+    #[test]
+    fn messages_deleted_while_held_are_dropped() {
+        let mut held = HeldMessages::default();
+        held.hold(MessageId(1));
+        held.hold(MessageId(2));
+        // Like a proxy bot replacing the first one
+        held.deleted(MessageId(1));
+        held.deleted(MessageId(3));
+        assert!(!held.release(MessageId(1)));
+        assert!(held.release(MessageId(2)));
+        // Deleting something that wasn't held leaves nothing behind to release
+        assert!(!held.release(MessageId(3)));
+        assert!(held.held.is_empty());
+    }
+
+    #[test]
+    fn characters_roll_up_under_their_owners() {
+        let mut owners = CharacterOwners::default();
+        assert_eq!(owners.claim("Aria", &user(10)), None);
+        assert_eq!(owners.claim("bex", &user(11)), None);
+        assert_eq!(owners.claim("BEX", &user(10)), Some(UserId(11)));
+        let mut character_stats = HashMap::new();
+        for (character, word_count) in [("Aria", 300), ("Bex", 500), ("Cole", 50)].iter() {
+            character_stats
+                .entry(String::from(*character))
+                .or_insert_with(WordStats::default)
+                .word_count = *word_count;
+        }
+        let (players, unclaimed) = roll_up(&character_stats, &owners);
+        assert_eq!(
+            players,
+            vec![Player {
+                owner: UserId(10),
+                word_count: 800,
+                characters: vec![(String::from("Bex"), 500), (String::from("Aria"), 300)],
+            }]
+        );
+        assert_eq!(unclaimed, vec![(String::from("Cole"), 50)]);
+    }
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, MessageId};
+use std::collections::{BTreeMap, HashMap};
+
+/// How many of each reaction a channel's posts have, only posts with at least one are kept.
+/// Reactions are keyed by how they're displayed, e.g. "⭐" or "<:custom:123>"
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReactionCounts {
+    posts: HashMap<MessageId, BTreeMap<String, usize>>,
+}
+
+impl ReactionCounts {
+    /// Returns the post's new total
+    pub fn add(&mut self, message_id: MessageId, emoji: &str) -> usize {
+        let reactions = self.posts.entry(message_id).or_default();
+        *reactions.entry(String::from(emoji)).or_default() += 1;
+        reactions.values().sum()
+    }
+
+    pub fn remove(&mut self, message_id: MessageId, emoji: &str) {
+        if let Some(reactions) = self.posts.get_mut(&message_id) {
+            if let Some(count) = reactions.get_mut(emoji) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    reactions.remove(emoji);
+                }
+            }
+            if reactions.is_empty() {
+                self.posts.remove(&message_id);
+            }
+        }
+    }
+
+    pub fn clear(&mut self, message_id: MessageId) {
+        self.posts.remove(&message_id);
+    }
+
+    /// Every post with reactions, along with its total
+    pub fn posts(&self) -> impl Iterator<Item = (MessageId, usize, &BTreeMap<String, usize>)> {
+        self.posts
+            .iter()
+            .map(|(message_id, reactions)| (*message_id, reactions.values().sum(), reactions))
+    }
+}
+
+/// e.g. "⭐×3 🔥×2", most used first
+pub fn describe_reactions(reactions: &BTreeMap<String, usize>) -> String {
+    let mut reactions: Vec<(&String, &usize)> = reactions.iter().collect();
+    reactions.sort_by(|a, b| b.1.cmp(a.1));
+    reactions
+        .iter()
+        .map(|(emoji, count)| format!("{}×{}", emoji, count))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Posts are reposted to the starboard once they have [threshold] reactions, if there's a
+/// channel for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StarboardSettings {
+    pub channel: Option<ChannelId>,
+    pub threshold: usize,
+}
+
+impl Default for StarboardSettings {
+    fn default() -> Self {
+        Self {
+            channel: None,
+            threshold: 5,
+        }
+    }
+}
+
+/// A post that just crossed the starboard threshold
+#[derive(Debug, PartialEq)]
+pub struct Star {
+    pub starboard: ChannelId,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub reactions: usize,
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::reactions::{describe_reactions, ReactionCounts};
+    use serenity::model::id::MessageId;
+
+    #[test]
+    fn counts_follow_adds_and_removes() {
+        let mut counts = ReactionCounts::default();
+        assert_eq!(counts.add(MessageId(1), "⭐"), 1);
+        assert_eq!(counts.add(MessageId(1), "🔥"), 2);
+        assert_eq!(counts.add(MessageId(1), "🔥"), 3);
+        counts.remove(MessageId(1), "⭐");
+        // Removing what was never there changes nothing
+        counts.remove(MessageId(1), "⭐");
+        counts.remove(MessageId(2), "⭐");
+        let posts: Vec<(MessageId, usize, String)> = counts
+            .posts()
+            .map(|(id, total, reactions)| (id, total, describe_reactions(reactions)))
+            .collect();
+        assert_eq!(posts, vec![(MessageId(1), 2, String::from("🔥×2"))]);
+        counts.remove(MessageId(1), "🔥");
+        counts.remove(MessageId(1), "🔥");
+        assert_eq!(counts.posts().count(), 0);
+    }
+}
@@ -0,0 +1,116 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, GuildId, MessageId, UserId};
+
+// Stop reminding after this many, a story that's ignored them all isn't coming back
+pub const MAX_REMINDERS: u32 = 4;
+// Authors who posted this long before the story stalled count as its participants
+const PARTICIPANT_DAYS: i64 = 90;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReminderSettings {
+    // Remind once nobody has posted for this long, [None] to never remind
+    pub remind_after_days: Option<u64>,
+    // Mention everyone who's been writing in the story, rather than just posting in the channel
+    pub ping_participants: bool,
+}
+
+/// Reminders sent since the story stalled, reset once somebody posts again
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReminderState {
+    // The last story post when the reminders were sent, a newer one means the story is moving
+    pub stalled_at: Option<MessageId>,
+    pub sent: u32,
+    pub last_sent: Option<DateTime<Utc>>,
+}
+
+/// A reminder to post in [channel_id], that nobody has for [idle_for]
+#[derive(Debug)]
+pub struct Reminder {
+    pub server_id: GuildId,
+    pub channel_id: ChannelId,
+    pub idle_for: Duration,
+    // Empty unless [ReminderSettings::ping_participants] is on
+    pub participants: Vec<UserId>,
+    // Whether this is the last one before giving up
+    pub is_last: bool,
+}
+
+impl ReminderSettings {
+    /// When the next reminder is due, backing off by doubling the wait after each one, e.g. 10
+    /// days of quiet, then another 20, then another 40. [None] once they've all been sent
+    pub fn next_due(
+        &self,
+        state: &ReminderState,
+        last_message_time: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let remind_after_days = self.remind_after_days? as i64;
+        if state.sent >= MAX_REMINDERS {
+            return None;
+        }
+        let wait = Duration::days(remind_after_days * 2_i64.pow(state.sent));
+        match state.last_sent {
+            Some(last_sent) if state.sent > 0 => Some(last_sent + wait),
+            _ => Some(last_message_time + wait),
+        }
+    }
+}
+
+/// Authors whose last post was within [PARTICIPANT_DAYS] of the story's last message
+pub fn participants(
+    last_posts: &[(UserId, DateTime<Utc>)],
+    last_message_time: DateTime<Utc>,
+) -> Vec<UserId> {
+    let mut participants: Vec<(UserId, DateTime<Utc>)> = last_posts
+        .iter()
+        .filter(|(_, time)| last_message_time - *time <= Duration::days(PARTICIPANT_DAYS))
+        .cloned()
+        .collect();
+    participants.sort_by_key(|(_, time)| std::cmp::Reverse(*time));
+    participants
+        .into_iter()
+        .map(|(user_id, _)| user_id)
+        .collect()
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::reminders::{ReminderSettings, ReminderState, MAX_REMINDERS};
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use serenity::model::id::MessageId;
+
+    fn last_message_time() -> DateTime<Utc> {
+        Utc.ymd(2026, 10, 1).and_hms(20, 0, 0)
+    }
+
+    #[test]
+    fn reminders_back_off() {
+        let settings = ReminderSettings {
+            remind_after_days: Some(10),
+            ping_participants: false,
+        };
+        let mut state = ReminderState::default();
+        assert_eq!(
+            settings.next_due(&state, last_message_time()),
+            Some(last_message_time() + Duration::days(10))
+        );
+        let first_sent = last_message_time() + Duration::days(10);
+        state = ReminderState {
+            stalled_at: Some(MessageId(1)),
+            sent: 1,
+            last_sent: Some(first_sent),
+        };
+        assert_eq!(
+            settings.next_due(&state, last_message_time()),
+            Some(first_sent + Duration::days(20))
+        );
+        state.sent = MAX_REMINDERS;
+        assert_eq!(settings.next_due(&state, last_message_time()), None);
+        assert_eq!(
+            ReminderSettings::default().next_due(&ReminderState::default(), last_message_time()),
+            None
+        );
+    }
+}
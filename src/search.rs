@@ -0,0 +1,319 @@
+use crate::archive::ArchivedMessage;
+use crate::language_parsing::tokenise;
+use serde::{Deserialize, Serialize};
+use serenity::model::channel::Message;
+use serenity::model::id::{ChannelId, MessageId, UserId};
+use std::collections::{BTreeMap, HashMap};
+
+// Roughly how much of a post is shown around the first match
+const SNIPPET_BEFORE: usize = 60;
+const SNIPPET_AFTER: usize = 160;
+
+// Lowercased words along with where they start in [text], in bytes
+fn words_with_offsets(text: &str) -> Vec<(String, usize)> {
+    tokenise(text)
+        .into_iter()
+        .map(|word| {
+            let offset = word.as_ptr() as usize - text.as_ptr() as usize;
+            (word.to_lowercase(), offset)
+        })
+        .collect()
+}
+
+/// An inverted index over a channel's story posts, kept with the rest of its stats. The posts
+/// themselves are kept too, so results can be shown without fetching them again
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchIndex {
+    messages: BTreeMap<MessageId, ArchivedMessage>,
+    // For each word, the positions (in words) it's at in each post it's in
+    postings: HashMap<String, BTreeMap<MessageId, Vec<u32>>>,
+}
+
+impl SearchIndex {
+    /// Adds the [content] of [message], unless it's been added before or has no words
+    pub fn add(&mut self, message: &Message, content: &str) {
+        if self.messages.contains_key(&message.id) {
+            return;
+        }
+        let archived_message = ArchivedMessage {
+            author_id: message.author.id,
+            author_name: message.author.name.clone(),
+            timestamp: message.timestamp,
+            content: String::from(content.trim()),
+        };
+        self.insert(message.id, archived_message);
+    }
+
+    pub fn insert(&mut self, message_id: MessageId, archived_message: ArchivedMessage) {
+        let words = words_with_offsets(&archived_message.content);
+        if words.is_empty() {
+            return;
+        }
+        for (position, (word, _)) in words.into_iter().enumerate() {
+            self.postings
+                .entry(word)
+                .or_default()
+                .entry(message_id)
+                .or_default()
+                .push(position as u32);
+        }
+        self.messages.insert(message_id, archived_message);
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn get(&self, message_id: &MessageId) -> Option<&ArchivedMessage> {
+        self.messages.get(message_id)
+    }
+
+    // How many times [phrase] is in each post it's in
+    fn phrase_counts(&self, phrase: &[String]) -> BTreeMap<MessageId, usize> {
+        let mut counts = BTreeMap::new();
+        let (first, rest) = match phrase.split_first() {
+            Some(split) => split,
+            None => return counts,
+        };
+        let first_postings = match self.postings.get(first) {
+            Some(postings) => postings,
+            None => return counts,
+        };
+        let rest_postings: Option<Vec<&BTreeMap<MessageId, Vec<u32>>>> =
+            rest.iter().map(|word| self.postings.get(word)).collect();
+        let rest_postings = match rest_postings {
+            Some(rest_postings) => rest_postings,
+            None => return counts,
+        };
+        for (message_id, starts) in first_postings.iter() {
+            // Each following word has to come straight after the one before
+            let count = starts
+                .iter()
+                .filter(|&&start| {
+                    rest_postings.iter().enumerate().all(|(i, postings)| {
+                        match postings.get(message_id) {
+                            Some(positions) => {
+                                positions.binary_search(&(start + i as u32 + 1)).is_ok()
+                            }
+                            None => false,
+                        }
+                    })
+                })
+                .count();
+            if count > 0 {
+                counts.insert(*message_id, count);
+            }
+        }
+        counts
+    }
+}
+
+/// What to look for, every phrase has to be in a post for it to match. Single words are
+/// phrases of one word
+#[derive(Debug, PartialEq)]
+pub struct Query {
+    phrases: Vec<Vec<String>>,
+}
+
+impl Query {
+    /// Words in double quotes are a phrase, any others are searched for on their own
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut phrases = vec![];
+        for (i, part) in text.split('"').enumerate() {
+            let words: Vec<String> = words_with_offsets(part)
+                .into_iter()
+                .map(|(word, _)| word)
+                .collect();
+            // Odd parts are inside quotes
+            if i % 2 == 1 {
+                if !words.is_empty() {
+                    phrases.push(words);
+                }
+            } else {
+                phrases.extend(words.into_iter().map(|word| vec![word]));
+            }
+        }
+        if phrases.is_empty() {
+            return Err(String::from("Expected something to search for"));
+        }
+        Ok(Self { phrases })
+    }
+
+    fn words(&self) -> impl Iterator<Item = &String> {
+        self.phrases.iter().flatten()
+    }
+}
+
+pub struct SearchHit<'a> {
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub message: &'a ArchivedMessage,
+    pub score: f64,
+}
+
+impl<'a> SearchHit<'a> {
+    /// Part of the post around the first word searched for, with ... where it's been cut
+    pub fn snippet(&self, query: &Query) -> String {
+        let content = &self.message.content;
+        let first_match = words_with_offsets(content)
+            .into_iter()
+            .find(|(word, _)| query.words().any(|query_word| query_word == word))
+            .map_or(0, |(_, offset)| offset);
+        let mut start = first_match.saturating_sub(SNIPPET_BEFORE);
+        while !content.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = (first_match + SNIPPET_AFTER).min(content.len());
+        while !content.is_char_boundary(end) {
+            end += 1;
+        }
+        // Don't cut words in half
+        if start > 0 {
+            start = content[start..first_match]
+                .find(char::is_whitespace)
+                .map_or(start, |space| start + space + 1);
+        }
+        if end < content.len() {
+            end = content[first_match..end]
+                .rfind(char::is_whitespace)
+                .map_or(end, |space| first_match + space);
+        }
+        let text = content[start..end]
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        format!(
+            "{}{}{}",
+            if start > 0 { "..." } else { "" },
+            text,
+            if end < content.len() { "..." } else { "" }
+        )
+    }
+}
+
+/// Posts across [indexes] matching [query], by [author_id] if there is one, best first. Each
+/// phrase scores more the more often it's in a post and the rarer it is across all of them
+pub fn search<'a>(
+    indexes: &[(ChannelId, &'a SearchIndex)],
+    query: &Query,
+    author_id: Option<UserId>,
+    limit: usize,
+) -> Vec<SearchHit<'a>> {
+    let total_messages: usize = indexes.iter().map(|(_, index)| index.len()).sum();
+    // Counts for every phrase in every channel, worked out up front so how rare each phrase is
+    // can be judged across all of them
+    let counts: Vec<Vec<BTreeMap<MessageId, usize>>> = indexes
+        .iter()
+        .map(|(_, index)| {
+            query
+                .phrases
+                .iter()
+                .map(|phrase| index.phrase_counts(phrase))
+                .collect()
+        })
+        .collect();
+    let inverse_frequencies: Vec<f64> = (0..query.phrases.len())
+        .map(|phrase| {
+            let containing: usize = counts.iter().map(|channel| channel[phrase].len()).sum();
+            (1.0 + total_messages as f64 / containing.max(1) as f64).ln()
+        })
+        .collect();
+    let mut hits = vec![];
+    for ((channel_id, index), phrase_counts) in indexes.iter().zip(counts.iter()) {
+        // Only posts with the first phrase can have all of them
+        for message_id in phrase_counts[0].keys() {
+            let message = &index.messages[message_id];
+            match author_id {
+                Some(author_id) if author_id != message.author_id => continue,
+                _ => {}
+            }
+            let scores: Option<Vec<f64>> = phrase_counts
+                .iter()
+                .zip(inverse_frequencies.iter())
+                .map(|(counts, inverse_frequency)| {
+                    counts
+                        .get(message_id)
+                        .map(|&count| (1.0 + (count as f64).ln()) * inverse_frequency)
+                })
+                .collect();
+            if let Some(scores) = scores {
+                hits.push(SearchHit {
+                    channel_id: *channel_id,
+                    message_id: *message_id,
+                    message,
+                    score: scores.iter().sum(),
+                });
+            }
+        }
+    }
+    // Newer posts first when they score the same
+    hits.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.message_id.cmp(&a.message_id))
+    });
+    hits.truncate(limit);
+    hits
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::archive::ArchivedMessage;
+    use crate::search::{search, Query, SearchIndex};
+    use chrono::Utc;
+    use serenity::model::id::{ChannelId, MessageId, UserId};
+
+    fn index() -> SearchIndex {
+        let mut index = SearchIndex::default();
+        for (id, author, content) in [
+            (1, 1, "Ana blew up the drone, then the other drone."),
+            (2, 2, "The drone blew up Ana's bike."),
+            (3, 1, "Nobody mentions anything here."),
+        ]
+        .iter()
+        {
+            index.insert(
+                MessageId(*id),
+                ArchivedMessage {
+                    author_id: UserId(*author),
+                    author_name: String::from("Someone"),
+                    timestamp: Utc::now(),
+                    content: String::from(*content),
+                },
+            );
+        }
+        index
+    }
+
+    fn hit_ids(index: &SearchIndex, query: &str, author_id: Option<UserId>) -> Vec<u64> {
+        let query = Query::parse(query).unwrap();
+        search(&[(ChannelId(1), index)], &query, author_id, 10)
+            .iter()
+            .map(|hit| hit.message_id.0)
+            .collect()
+    }
+
+    #[test]
+    fn phrases_need_words_in_order() {
+        let index = index();
+        assert_eq!(hit_ids(&index, "drone blew", None), vec![1, 2]);
+        assert_eq!(hit_ids(&index, "\"blew up the drone\"", None), vec![1]);
+        assert_eq!(hit_ids(&index, "\"drone blew\" bike", None), vec![2]);
+        assert_eq!(hit_ids(&index, "drone", Some(UserId(2))), vec![2]);
+        assert!(hit_ids(&index, "\"the ana\"", None).is_empty());
+        assert!(Query::parse("\"\" ...").is_err());
+    }
+
+    #[test]
+    fn snippets_are_cut_around_the_first_match() {
+        let index = index();
+        let query = Query::parse("other").unwrap();
+        let hits = search(&[(ChannelId(1), &index)], &query, None, 10);
+        assert_eq!(
+            hits[0].snippet(&query),
+            "Ana blew up the drone, then the other drone."
+        );
+    }
+}
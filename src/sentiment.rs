@@ -0,0 +1,159 @@
+use crate::language_parsing::tokenise;
+use crate::sentiment_lexicon::LEXICON;
+use serde::{Deserialize, Serialize};
+
+// Adjustments from the VADER rules, see https://github.com/cjhutto/vaderSentiment
+const NEGATION_SCALAR: f64 = -0.74;
+const BOOSTER_INCREMENT: f64 = 0.293;
+const CAPS_INCREMENT: f64 = 0.733;
+// Controls how quickly the compound score approaches +-1 as valences add up
+const NORMALISATION_ALPHA: f64 = 15.0;
+// How many words back a negation or booster still applies to
+const LOOKBACK: usize = 3;
+
+const NEGATIONS: [&str; 20] = [
+    "ain't", "aint", "cannot", "can't", "didn't", "doesn't", "don't", "hardly", "isn't", "neither",
+    "never", "no", "nobody", "none", "nor", "not", "nothing", "nowhere", "wasn't", "without",
+];
+const BOOSTERS: [&str; 16] = [
+    "absolutely",
+    "completely",
+    "deeply",
+    "enormously",
+    "entirely",
+    "extremely",
+    "hugely",
+    "incredibly",
+    "most",
+    "particularly",
+    "really",
+    "so",
+    "terribly",
+    "totally",
+    "utterly",
+    "very",
+];
+const DAMPENERS: [&str; 9] = [
+    "almost",
+    "barely",
+    "kinda",
+    "less",
+    "little",
+    "marginally",
+    "partly",
+    "slightly",
+    "somewhat",
+];
+
+fn valence(word: &str) -> Option<f64> {
+    LEXICON
+        .binary_search_by(|(lexicon_word, _)| lexicon_word.cmp(&word))
+        .ok()
+        .map(|i| LEXICON[i].1 as f64)
+}
+
+fn is_shouted(word: &str) -> bool {
+    word.chars().any(char::is_alphabetic) && !word.chars().any(char::is_lowercase)
+}
+
+/// How positive or negative [text] reads from -1 to 1, [None] if none of its words carry any
+/// sentiment. Words are scored from the lexicon, then nudged by the words around them: negations
+/// flip them, boosters and dampeners strengthen or weaken them, and shouting strengthens them
+/// (unless everything is shouted)
+pub fn score(text: &str) -> Option<f64> {
+    let words = tokenise(text);
+    let all_shouted = words.iter().all(|word| is_shouted(word));
+    let lowercase_words: Vec<String> = words.iter().map(|word| word.to_lowercase()).collect();
+    let mut total = 0.0;
+    let mut scored_any = false;
+    for (i, word) in lowercase_words.iter().enumerate() {
+        let mut word_valence = match valence(word) {
+            Some(word_valence) => word_valence,
+            None => continue,
+        };
+        scored_any = true;
+        let direction = word_valence.signum();
+        if !all_shouted && is_shouted(words[i]) {
+            word_valence += CAPS_INCREMENT * direction;
+        }
+        let before = &lowercase_words[i.saturating_sub(LOOKBACK)..i];
+        if let Some(previous) = before.last() {
+            if BOOSTERS.contains(&previous.as_str()) {
+                word_valence += BOOSTER_INCREMENT * direction;
+            } else if DAMPENERS.contains(&previous.as_str()) {
+                word_valence -= BOOSTER_INCREMENT * direction;
+            }
+        }
+        if before
+            .iter()
+            .any(|word| NEGATIONS.contains(&word.as_str()) || word.ends_with("n't"))
+        {
+            word_valence *= NEGATION_SCALAR;
+        }
+        total += word_valence;
+    }
+    match scored_any {
+        true => Some(total / (total * total + NORMALISATION_ALPHA).sqrt()),
+        false => None,
+    }
+}
+
+/// Sentiment scores added up over some messages, like a day's worth
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SentimentTotals {
+    pub messages: usize,
+    pub score_sum: f64,
+}
+
+impl SentimentTotals {
+    pub fn add(&mut self, score: f64) {
+        self.messages += 1;
+        self.score_sum += score;
+    }
+
+    pub fn merge(&mut self, other: &SentimentTotals) {
+        self.messages += other.messages;
+        self.score_sum += other.score_sum;
+    }
+
+    /// From -1 (darkest) to 1 (lightest), [None] if there's nothing to go on
+    pub fn average(&self) -> Option<f64> {
+        match self.messages {
+            0 => None,
+            messages => Some(self.score_sum / messages as f64),
+        }
+    }
+}
+
+/// A word for roughly how [average] reads
+pub fn describe_tone(average: f64) -> &'static str {
+    match average {
+        a if a <= -0.3 => "dark",
+        a if a <= -0.1 => "gloomy",
+        a if a < 0.1 => "even",
+        a if a < 0.3 => "upbeat",
+        _ => "light",
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::sentiment::score;
+    use crate::sentiment_lexicon::LEXICON;
+
+    #[test]
+    fn lexicon_is_sorted_for_searching() {
+        assert!(LEXICON.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn rules_adjust_lexicon_scores() {
+        let happy = score("She was happy to see him").unwrap();
+        assert!(happy > 0.0);
+        assert!(score("She was not happy to see him").unwrap() < 0.0);
+        assert!(score("She was very happy to see him").unwrap() > happy);
+        assert!(score("She was HAPPY to see him").unwrap() > happy);
+        assert!(score("The blade found his heart, and he died screaming").unwrap() < -0.5);
+        assert_eq!(score("The cube of an automated ticket machine"), None);
+    }
+}
@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const NAME: &str = "{name}";
+const TIME: &str = "{time}";
+// Anything longer than this isn't taken as a name, to keep ordinary prose from matching
+const MAX_NAME_CHARS: usize = 32;
+const MAX_NAME_WORDS: usize = 4;
+
+// A pattern broken up into what has to be matched in turn
+#[derive(Debug, PartialEq)]
+enum Part<'a> {
+    Literal(&'a str),
+    Name,
+    Time,
+}
+
+fn parse_pattern(pattern: &str) -> Result<Vec<Part<'_>>, String> {
+    let mut parts = vec![];
+    let mut rest = pattern;
+    while !rest.is_empty() {
+        let next = [NAME, TIME]
+            .iter()
+            .filter_map(|placeholder| rest.find(placeholder).map(|i| (i, *placeholder)))
+            .min_by_key(|(i, _)| *i);
+        match next {
+            Some((i, placeholder)) => {
+                if i > 0 {
+                    parts.push(Part::Literal(&rest[..i]));
+                }
+                parts.push(match placeholder {
+                    NAME => Part::Name,
+                    _ => Part::Time,
+                });
+                rest = &rest[i + placeholder.len()..];
+            }
+            None => {
+                parts.push(Part::Literal(rest));
+                rest = "";
+            }
+        }
+    }
+    match parts.iter().filter(|part| **part == Part::Name).count() {
+        1 => Ok(parts),
+        _ => Err(format!(
+            "The pattern {} needs exactly one {}",
+            pattern, NAME
+        )),
+    }
+}
+
+fn is_name(name: &str) -> bool {
+    let name = name.trim();
+    !name.is_empty()
+        && name.chars().count() <= MAX_NAME_CHARS
+        && name.split_whitespace().count() <= MAX_NAME_WORDS
+        && name.chars().any(char::is_alphabetic)
+        && !name.contains(['*', '_', '"', ':'])
+}
+
+// How long the time (digits separated by colons, like 19:51) at the start of [text] is
+fn time_length(text: &str) -> Option<usize> {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let mut end = digits(text);
+    if end == 0 {
+        return None;
+    }
+    while let Some(rest) = text[end..].strip_prefix(':') {
+        match digits(rest) {
+            0 => break,
+            more => end += 1 + more,
+        }
+    }
+    Some(end)
+}
+
+// Matches [parts] at the start of [text], returning the name and whatever follows the tag.
+// Names are kept as short as possible, so they stop at the first thing that can follow them
+fn match_parts<'a>(
+    parts: &[Part],
+    text: &'a str,
+    name: Option<&'a str>,
+) -> Option<(&'a str, &'a str)> {
+    let (part, remaining_parts) = match parts.split_first() {
+        Some(split) => split,
+        None => return name.map(|name| (name, text)),
+    };
+    match part {
+        Part::Literal(literal) => {
+            let rest = text.strip_prefix(literal)?;
+            match_parts(remaining_parts, rest, name)
+        }
+        Part::Time => {
+            let end = time_length(text)?;
+            match_parts(remaining_parts, &text[end..], name)
+        }
+        Part::Name => text
+            .char_indices()
+            .skip(1)
+            .map(|(i, _)| i)
+            .chain(std::iter::once(text.len()))
+            .take(MAX_NAME_CHARS)
+            .filter(|end| is_name(&text[..*end]))
+            .find_map(|end| match_parts(remaining_parts, &text[end..], Some(text[..end].trim()))),
+    }
+}
+
+/// Per-channel patterns for speaker tags, which say which character a line is from, e.g.
+/// `**{name}** {time}:` or `{name}: "`. `{name}` is the character, `{time}` a time like 19:51,
+/// and everything else has to match exactly. Tags are looked for at the start of each line
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeakerTags {
+    pub patterns: Vec<String>,
+}
+
+impl SpeakerTags {
+    /// Chat log style tags. Script style `{name}: "` isn't one of these, as it also matches
+    /// prose like `She said: "Go"`, so it has to be added on purpose
+    pub fn common() -> Self {
+        Self {
+            patterns: vec![String::from("**{name}** {time}:")],
+        }
+    }
+
+    /// Checks [pattern] can be used, see [SpeakerTags]
+    pub fn validate(pattern: &str) -> Result<(), String> {
+        parse_pattern(pattern).map(|_| ())
+    }
+
+    /// What each character said in [content], from the rest of each line that starts with one
+    /// of their tags. Lines without a tag aren't anyone's
+    pub fn split_content(&self, content: &str) -> BTreeMap<String, String> {
+        let patterns: Vec<Vec<Part>> = self
+            .patterns
+            .iter()
+            .filter_map(|pattern| parse_pattern(pattern).ok())
+            .collect();
+        let mut said: BTreeMap<String, String> = BTreeMap::new();
+        if patterns.is_empty() {
+            return said;
+        }
+        for line in content.lines() {
+            let line = line.trim_start();
+            let tagged = patterns
+                .iter()
+                .find_map(|parts| match_parts(parts, line, None));
+            if let Some((name, rest)) = tagged {
+                let lines = said.entry(String::from(name)).or_default();
+                if !lines.is_empty() {
+                    lines.push('\n');
+                }
+                lines.push_str(rest.trim());
+            }
+        }
+        said
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::speakers::SpeakerTags;
+
+    #[test]
+    fn tagged_lines_go_to_their_speakers() {
+        let mut tags = SpeakerTags::common();
+        tags.patterns.push(String::from("{name}: \""));
+        let said = tags.split_content(
+            "Ana starts the bike.\n**Oculus** 19:51: *Drone issue. Won't be long.*\nMarcus Aurelius: \"Patience.\"\n**Oculus** 19:52: Fixed",
+        );
+        let said: Vec<(&str, &str)> = said
+            .iter()
+            .map(|(name, lines)| (name.as_str(), lines.as_str()))
+            .collect();
+        assert_eq!(
+            said,
+            vec![
+                ("Marcus Aurelius", "Patience.\""),
+                ("Oculus", "*Drone issue. Won't be long.*\nFixed"),
+            ]
+        );
+        // Prose that happens to have a colon in it isn't a tag
+        let said = tags.split_content("The note on the door said only this: \"Gone fishing\"");
+        assert!(said.is_empty());
+    }
+
+    #[test]
+    fn common_tags_leave_prose_alone() {
+        let said = SpeakerTags::common().split_content("She said: \"Go\"\n**Oculus** 19:51: Going");
+        assert_eq!(said.keys().collect::<Vec<_>>(), vec!["Oculus"]);
+    }
+
+    #[test]
+    fn patterns_need_one_name() {
+        assert!(SpeakerTags::validate("{name} says:").is_ok());
+        assert!(SpeakerTags::validate("{time}:").is_err());
+        assert!(SpeakerTags::validate("{name} and {name}:").is_err());
+    }
+}
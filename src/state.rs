@@ -1,9 +1,27 @@
-use crate::stats::WordStats;
+use crate::archive::Archive;
+use crate::chapters::{Chapters, ReplaySpan};
+use crate::digest::{Digest, DigestSettings, DueDigest};
+use crate::glossary::{Glossary, GlossaryCuration};
+use crate::goals::{Goal, GoalOwner};
+use crate::markov::MarkovModel;
+use crate::milestones::{Milestone, MilestoneCounts, MilestoneSettings};
+use crate::ooc::OocRules;
+use crate::posts::PostTimeline;
+use crate::proxies::{self, CharacterOwners};
+use crate::reactions::{ReactionCounts, Star, StarboardSettings};
+use crate::reminders::{self, Reminder, ReminderSettings, ReminderState, MAX_REMINDERS};
+use crate::search::SearchIndex;
+use crate::speakers::SpeakerTags;
+use crate::stats::{signature_word_scores, top_scored_words, WordFilter, WordStats};
+use crate::stop_words::Language;
+use crate::stories::Story;
+use crate::turns::{Nudge, Turn, TurnSettings};
 use crate::utils::iterators::helpers::sort_by_last_message_and_maybe_truncate;
 use crate::utils::trait_extensions::MessageBuilderExt;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
 use log::debug;
 use serde::{Deserialize, Serialize};
-use serenity::model::channel::{GuildChannel, Message};
+use serenity::model::channel::Message;
 use serenity::model::id::{ChannelId, GuildId, MessageId, UserId};
 use serenity::model::prelude::Channel;
 use serenity::model::user::User;
@@ -12,7 +30,10 @@ use serenity::utils::MessageBuilder;
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{ErrorKind, Read, Write};
+use std::str::FromStr;
 use std::sync::{Arc, RwLock};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
 
 pub struct StoreData;
 
@@ -85,28 +106,120 @@ impl Store {
             .map_or(vec![], |server| server.get_all_channel_ids())
     }
 
-    pub fn finish_replay(&mut self) {
+    pub fn finish_replay(&mut self) -> Vec<(ChannelId, Milestone)> {
         let replay_queue: Vec<(StoryKey, Message)> =
             self.queued_messages_until_replay.drain(..).collect();
+        let mut announcements = vec![];
         for (key, message) in replay_queue {
-            match self.get_channel_data_mut(&key) {
-                Some(story_data) => story_data.update(&message),
-                None => debug!("Message not in a channel that's been initialised"),
-            }
+            announcements.extend(self.update_channel_data(&key, &message));
         }
         //self.queued_messages_until_replay.clear();
         self.replay_needed = false;
+        announcements
     }
 
-    pub fn process_message(&mut self, story_key: &StoryKey, message: &Message) {
+    /// Returns any milestones the message crossed, along with the channel to announce them in
+    pub fn process_message(
+        &mut self,
+        story_key: &StoryKey,
+        message: &Message,
+    ) -> Vec<(ChannelId, Milestone)> {
         match self.replay_needed {
-            true => self
-                .queued_messages_until_replay
-                .push((story_key.clone(), message.clone())),
-            false => match self.get_channel_data_mut(&story_key) {
-                Some(story_data) => story_data.update(message),
-                None => debug!("Message not in a channel that's been initialised"),
+            true => {
+                self.queued_messages_until_replay
+                    .push((story_key.clone(), message.clone()));
+                vec![]
+            }
+            false => self.update_channel_data(story_key, message),
+        }
+    }
+
+    pub fn update_channel_data(
+        &mut self,
+        (server_id, channel_id): &StoryKey,
+        message: &Message,
+    ) -> Vec<(ChannelId, Milestone)> {
+        let server_data = match self.data.get_mut(server_id) {
+            Some(server_data) => server_data,
+            None => {
+                debug!("Message not in a channel that's been initialised");
+                return vec![];
+            }
+        };
+        match server_data.update_channel(channel_id, message) {
+            Some(milestones) => match server_data.settings.milestones.announcement_channel {
+                Some(announcement_channel) => milestones
+                    .into_iter()
+                    .map(|milestone| (announcement_channel, milestone))
+                    .collect(),
+                None => vec![],
             },
+            None => {
+                debug!("Message not in a channel that's been initialised");
+                vec![]
+            }
+        }
+    }
+
+    /// Finds every channel where the next writer is due a nudge at [now], marking them as sent
+    pub fn take_due_nudges(&mut self, now: DateTime<Utc>) -> Vec<Nudge> {
+        let mut nudges = vec![];
+        for (server_id, server_data) in self.data.iter_mut() {
+            let post_window = server_data.settings.post_window();
+            for (channel_id, channel_data) in server_data.channels.iter_mut() {
+                if let Some(turn) = channel_data.take_due_nudge(now, post_window) {
+                    nudges.push(Nudge {
+                        server_id: *server_id,
+                        channel_id: *channel_id,
+                        turn,
+                    });
+                }
+            }
+        }
+        nudges
+    }
+
+    /// Finds every channel that's stalled long enough to be due a reminder at [now], marking
+    /// them as sent
+    pub fn take_due_reminders(&mut self, now: DateTime<Utc>) -> Vec<Reminder> {
+        let mut due_reminders = vec![];
+        for (server_id, server_data) in self.data.iter_mut() {
+            for (channel_id, channel_data) in server_data.channels.iter_mut() {
+                let story_key = (*server_id, *channel_id);
+                if let Some(reminder) = channel_data.take_due_reminder(story_key, now) {
+                    due_reminders.push(reminder);
+                }
+            }
+        }
+        due_reminders
+    }
+
+    /// Builds the digest for every server that's due one at [now]. They stay due until marked
+    /// with [Store::mark_digest_posted]
+    pub fn due_digests(&self, now: DateTime<Utc>) -> Vec<DueDigest> {
+        let mut digests = vec![];
+        for (server_id, server_data) in self.data.iter() {
+            let settings = &server_data.settings.digest;
+            if let (Some(date), Some(channel_id)) = (settings.due(now), settings.channel) {
+                // The 7 days before the one it's posted on
+                let digest = server_data.make_digest(
+                    date - chrono::Duration::days(7),
+                    date - chrono::Duration::days(1),
+                );
+                digests.push(DueDigest {
+                    server_id: *server_id,
+                    channel_id,
+                    date,
+                    messages: digest.to_messages(),
+                });
+            }
+        }
+        digests
+    }
+
+    pub fn mark_digest_posted(&mut self, digest: &DueDigest) {
+        if let Some(server_data) = self.data.get_mut(&digest.server_id) {
+            server_data.settings.digest.last_posted = Some(digest.date);
         }
     }
 
@@ -150,25 +263,192 @@ impl Store {
         }
     }
 
+    pub fn word_filter(&self, (server_id, channel_id): &StoryKey) -> WordFilter {
+        self.word_filter_for_channels(server_id, &[*channel_id])
+    }
+
+    // The server's filter plus every stop word set on any of [channel_ids]
+    pub fn word_filter_for_channels(
+        &self,
+        server_id: &GuildId,
+        channel_ids: &[ChannelId],
+    ) -> WordFilter {
+        let mut word_filter = WordFilter::default();
+        if let Some(server_data) = self.data.get(server_id) {
+            let settings = &server_data.settings;
+            word_filter.stemming = settings.stemming;
+            word_filter.stemming_language = settings.stemming_language;
+            word_filter.stop_word_languages = settings.stop_word_languages.clone();
+            word_filter
+                .extra_stop_words
+                .extend(settings.stop_words.iter().cloned());
+            for channel_id in channel_ids {
+                if let Some(channel_data) = server_data.channels.get(channel_id) {
+                    word_filter
+                        .extra_stop_words
+                        .extend(channel_data.settings.stop_words.iter().cloned());
+                }
+            }
+        }
+        word_filter
+    }
+
+    // Like [ChannelData::signature_word_scores] but against every channel on the server
+    pub fn server_signature_word_scores(
+        &self,
+        server_id: &GuildId,
+        user_id: &UserId,
+    ) -> Option<HashMap<String, f64>> {
+        let server_data = self.data.get(server_id)?;
+        let mut author_frequencies: HashMap<String, usize> = HashMap::new();
+        let mut baseline_frequencies: HashMap<String, usize> = HashMap::new();
+        let mut found_user = false;
+        for (channel_id, channel_data) in server_data.channels.iter() {
+            let word_filter = self.word_filter(&(*server_id, *channel_id));
+            if let Some(stats) = channel_data.get_user_by_id(user_id) {
+                found_user = true;
+                for (word, count) in stats.filtered_word_frequencies(&word_filter) {
+                    *author_frequencies.entry(word).or_insert(0) += count;
+                }
+            }
+            for (word, count) in channel_data
+                .general_stats
+                .filtered_word_frequencies(&word_filter)
+            {
+                *baseline_frequencies.entry(word).or_insert(0) += count;
+            }
+        }
+        match found_user {
+            true => Some(signature_word_scores(
+                &author_frequencies,
+                &baseline_frequencies,
+            )),
+            false => None,
+        }
+    }
+
+    // Gathers everything we know about one word, on the whole server or just [channel_id], and
+    // for everyone or just [user_id]. None if there's nothing in that scope at all
+    pub fn look_up_word(
+        &self,
+        server_id: &GuildId,
+        channel_id: Option<ChannelId>,
+        user_id: Option<UserId>,
+        word: &str,
+    ) -> Option<WordLookup> {
+        let server_data = self.data.get(server_id)?;
+        let mut lookup = WordLookup::default();
+        // Everything in scope as one, so inflections are grouped across channels before ranking
+        let mut scope_stats = WordStats::default();
+        let mut scope_channels = vec![];
+        let mut by_author: HashMap<&User, usize> = HashMap::new();
+        for (this_channel_id, channel_data) in server_data.channels.iter() {
+            if channel_id.is_some() && channel_id != Some(*this_channel_id) {
+                continue;
+            }
+            let stats = match user_id {
+                Some(user_id) => match channel_data.get_user_by_id(&user_id) {
+                    Some(stats) => stats,
+                    None => continue,
+                },
+                None => &channel_data.general_stats,
+            };
+            scope_stats.merge(stats);
+            scope_channels.push(*this_channel_id);
+            lookup.count += stats.word_frequencies.get(word).copied().unwrap_or(0);
+            if let Some(word_use) = stats.word_use(word) {
+                let (first_id, first_time) = word_use.first;
+                let (last_id, last_time) = word_use.last;
+                let is_earlier = match lookup.first {
+                    Some((_, _, time)) => first_time < time,
+                    None => true,
+                };
+                if is_earlier {
+                    lookup.first = Some((*this_channel_id, first_id, first_time));
+                }
+                let is_later = match lookup.last {
+                    Some((_, _, time)) => last_time > time,
+                    None => true,
+                };
+                if is_later {
+                    lookup.last = Some((*this_channel_id, last_id, last_time));
+                }
+            }
+            if user_id.is_none() {
+                for (author, author_stats) in channel_data.author_stats.iter() {
+                    if let Some(count) = author_stats.word_frequencies.get(word) {
+                        *by_author.entry(author).or_insert(0) += count;
+                    }
+                }
+            }
+        }
+        if scope_channels.is_empty() {
+            return None;
+        }
+        let word_filter = self.word_filter_for_channels(server_id, &scope_channels);
+        lookup.stop_word = word_filter.is_stop_word(word);
+        let scope_frequencies = scope_stats.filtered_word_frequencies(&word_filter);
+        // With stemming the word is counted under its most common inflection, which needn't be
+        // [word] itself
+        let grouped_count = scope_frequencies
+            .iter()
+            .find(|(other_word, _)| word_filter.same_word(word, other_word))
+            .map(|(_, count)| count);
+        if let Some(count) = grouped_count {
+            let rank = 1 + scope_frequencies.values().filter(|c| *c > count).count();
+            lookup.rank = Some((rank, scope_frequencies.len()));
+        }
+        lookup.by_author = by_author
+            .into_iter()
+            .map(|(author, count)| (author.clone(), count))
+            .collect();
+        lookup
+            .by_author
+            .sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        Some(lookup)
+    }
+
     pub fn insert_channel_data_maybe_create_server_data(
         &mut self,
         (server_id, channel_id): &StoryKey,
         channel_data: ChannelData,
     ) {
-        let server_data = match self.data.get_mut(server_id) {
-            Some(server_data) => server_data,
-            None => {
-                let server_data = ServerData::new();
-                self.data.insert(*server_id, server_data);
-                self.data.get_mut(server_id).unwrap()
-            }
-        };
+        let server_data = self.get_server_data_mut_maybe_create(server_id);
         server_data.insert(channel_id, channel_data);
     }
+
+    // Server settings can be changed before any channels are initialised, so this creates the
+    // [ServerData] for them to live in if needed
+    pub fn get_server_data_mut_maybe_create(&mut self, server_id: &GuildId) -> &mut ServerData {
+        self.data.entry(*server_id).or_default()
+    }
 }
 
 pub type StoryKey = (GuildId, ChannelId);
 
+#[derive(Debug, Default)]
+pub struct WordLookup {
+    pub count: usize,
+    // Only filled in when not looking up a single user, most uses first
+    pub by_author: Vec<(User, usize)>,
+    pub first: Option<(ChannelId, MessageId, DateTime<Utc>)>,
+    pub last: Option<(ChannelId, MessageId, DateTime<Utc>)>,
+    // (rank, out of how many words), None for stop words and words never used. With stemming
+    // this is the rank of all the word's inflections together
+    pub rank: Option<(usize, usize)>,
+    pub stop_word: bool,
+}
+
+// e.g. "Dialogue: 40% (400 words in quotes, 600 outside)"
+fn describe_dialogue(stats: &WordStats) -> String {
+    format!(
+        "Dialogue: {:.0}% ({} words in quotes, {} outside)",
+        stats.dialogue_ratio().unwrap_or(0.0) * 100.0,
+        stats.dialogue_word_count(),
+        stats.narration_word_count()
+    )
+}
+
 // this could be a stable type since i intend to serialise this for disk storage.
 // this doesn't seem to be an obvious rust pattern but we could do ocaml/sexp style
 // and use an enum of v0,v1,...
@@ -176,70 +456,932 @@ pub type StoryKey = (GuildId, ChannelId);
 pub struct ChannelData {
     pub author_stats: HashMap<User, WordStats>,
     pub general_stats: WordStats,
+    // Words picked out as out-of-character by [ChannelSettings::ooc_rules]
+    #[serde(default)]
+    pub ooc_stats: WordStats,
+    #[serde(default)]
+    pub settings: ChannelSettings,
+    // The last message a turn nudge was sent for, so each turn is only nudged once
+    #[serde(default)]
+    nudged_for: Option<MessageId>,
+    // Stall reminders sent since the last message, see [ReminderSettings]
+    #[serde(default)]
+    reminder_state: ReminderState,
+    #[serde(default)]
+    pub chapters: Chapters,
+    // The story text, only kept once archiving is turned on for the channel
+    #[serde(default)]
+    archive: Option<Archive>,
+    // Like [archive], only kept while archiving is on
+    #[serde(default)]
+    pub search_index: SearchIndex,
+    // Only learned while imitation is on for the channel
+    #[serde(default)]
+    imitation_models: HashMap<UserId, MarkovModel>,
+    #[serde(default)]
+    pub reactions: ReactionCounts,
+    // For merging split posts, see [ServerSettings::post_window]
+    #[serde(default)]
+    posts: PostTimeline,
+    // Posts from proxy bots by the character they were posted as
+    #[serde(default)]
+    pub character_stats: HashMap<String, WordStats>,
+    // Lines picked out by [ChannelSettings::speaker_tags] by who said them. Kept apart from
+    // [character_stats] as nobody claims these, and a proxied post can have tagged lines too
+    #[serde(default)]
+    pub speaker_stats: HashMap<String, WordStats>,
+    // Proxied posts from characters nobody had claimed yet, by character. Claimed characters'
+    // posts count for whoever plays them, and these are moved over once someone does
+    #[serde(default)]
+    uncredited: HashMap<String, WordStats>,
+    // Capitalised words, see [Glossary::entries] for the proper nouns among them
+    #[serde(default)]
+    pub glossary: Glossary,
+}
+
+/// Per-channel options, these add to (rather than override) the [ServerSettings]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelSettings {
+    pub stop_words: HashSet<String>,
+    pub ooc_rules: OocRules,
+    pub turns: TurnSettings,
+    pub reminders: ReminderSettings,
+    pub imitation: bool,
+    pub speaker_tags: SpeakerTags,
 }
 
 impl ChannelData {
-    pub fn update(&mut self, message: &Message) {
-        self.general_stats.update(message);
-        if let Some(word_stats) = self.author_stats.get_mut(&message.author) {
-            debug!("Updating word stats for existing author");
-            word_stats.update(message);
-        } else {
-            debug!("Inserting new word stats for new author");
-            let word_stats = WordStats::new_from_message(&message);
-            self.author_stats.insert(message.author.clone(), word_stats);
+    /// [owners] say whose proxied posts are whose, see [CharacterOwners::credited_author]
+    pub fn update(&mut self, message: &Message, owners: &CharacterOwners) {
+        let (in_character, out_of_character) = self.settings.ooc_rules.split_message(message);
+        let author = owners.credited_author(message);
+        self.glossary.update(message, &in_character);
+        // General stats always see the message, even if it's all OOC, so the last message stays
+        // accurate for replaying what we missed
+        self.general_stats.update(message, &in_character);
+        // OOC chatter isn't part of a post, so it doesn't split one up either. Nor are the bot's
+        // own messages, like reminders, which would otherwise look like the story moving on
+        let from_bot = message.author.bot && message.webhook_id.is_none();
+        if !in_character.trim().is_empty() && !from_bot {
+            self.posts
+                .add(message, author.map_or(message.author.id, |author| author.id));
+        }
+        if !out_of_character.trim().is_empty() {
+            self.ooc_stats.update(message, &out_of_character);
+        }
+        self.chapters.update(message, author, &in_character);
+        if let Some(archive) = &mut self.archive {
+            archive.add(message, &in_character);
+            self.search_index.add(message, &in_character);
+        }
+        // Messages that are all OOC aren't story posts, so they don't count for anyone
+        if in_character.trim().is_empty() {
+            return;
+        }
+        // Proxied posts count for the character too, rather than the webhook they came through
+        let character = proxies::character_name(message);
+        if let Some(character) = character {
+            self.character_stats
+                .entry(String::from(character))
+                .or_default()
+                .update(message, &in_character);
+        }
+        match (author, character) {
+            (Some(author), _) => {
+                debug!("Updating word stats for author");
+                self.author_stats
+                    .entry(author.clone())
+                    .or_default()
+                    .update(message, &in_character);
+            }
+            (None, Some(character)) => {
+                self.uncredited
+                    .entry(String::from(character))
+                    .or_default()
+                    .update(message, &in_character);
+            }
+            (None, None) => {}
+        }
+        // Tagged lines still count for whoever wrote them, this is on top
+        for (character, said) in self.settings.speaker_tags.split_content(&in_character) {
+            if !said.trim().is_empty() {
+                self.speaker_stats
+                    .entry(character)
+                    .or_default()
+                    .update(message, &said);
+            }
         }
     }
 
+    // [title] is what the stats are for, e.g. a channel mention
     pub fn make_stats_string(
         &self,
-        text_channel: &GuildChannel,
+        title: &str,
         truncate_limit: Option<usize>,
+        word_filter: &WordFilter,
     ) -> String {
         let mut stats_iterator =
             sort_by_last_message_and_maybe_truncate(&self.author_stats, truncate_limit);
         let mut builder = MessageBuilder::new();
         let base_builder = builder
             .push("For ")
-            .channel(text_channel)
+            .push(title)
             .newline()
             .push_bold_line("General")
             .push_line_safe(format!(
                 "Word count: {}",
                 self.general_stats.word_count
             ))
+            .apply_if(self.ooc_stats.word_count > 0, |mb| {
+                mb.push_line_safe(format!(
+                    "Out of character word count: {}",
+                    self.ooc_stats.word_count
+                ))
+            })
+            .apply_if(self.general_stats.dialogue_ratio().is_some(), |mb| {
+                mb.push_line_safe(describe_dialogue(&self.general_stats))
+            })
             .apply_if(stats_iterator.is_truncated(), |mb|
                 mb.newline().push_line(
                     format!("Not all authors are displayed below, just the {} most recent ones. Add [-full] to see all of them",
                             stats_iterator.limit())
                 )
             );
+        let baseline_frequencies = self.general_stats.filtered_word_frequencies(word_filter);
         let final_builder = stats_iterator.fold(base_builder, |builder, (author, stats)| {
+            let signature_scores = signature_word_scores(
+                &stats.filtered_word_frequencies(word_filter),
+                &baseline_frequencies,
+            );
             builder
                 .newline()
                 .user(author)
                 .newline()
                 .push_line_safe(format!("Word count: {}", stats.word_count))
-                .push_line_safe(format!("Top words: {}", stats.top_words(10)))
+                .push_line_safe(format!("Top words: {}", stats.top_words(10, word_filter)))
+                .apply_if(stats.dialogue_ratio().is_some(), |mb| {
+                    mb.push_line_safe(describe_dialogue(stats))
+                })
+                .apply_if(stats.dialogue_word_count() > 0, |mb| {
+                    mb.push_line_safe(format!(
+                        "Top dialogue words: {}",
+                        stats.top_dialogue_words(10, word_filter)
+                    ))
+                })
+                .apply_if(!signature_scores.is_empty(), |mb| {
+                    mb.push_line_safe(format!(
+                        "Signature words: {}",
+                        top_scored_words(&signature_scores, 10)
+                    ))
+                })
         });
         final_builder.build()
     }
+    // When each author last posted
+    fn last_posts(&self) -> Vec<(UserId, DateTime<Utc>)> {
+        self.author_stats
+            .iter()
+            .filter_map(|(author, stats)| {
+                stats
+                    .last_message_time()
+                    .map(|time| (author.id, *time))
+            })
+            .collect()
+    }
+
+    pub fn turn(&self, now: DateTime<Utc>) -> Option<Turn> {
+        self.settings.turns.turn(&self.last_posts(), now)
+    }
+
+    // A nudge is due once the next writer has had [TurnSettings::nudge_after_hours] to post.
+    // Nudges are kept to one per post, however many messages it was split over, and wait until
+    // the post can't be carried on any more
+    fn take_due_nudge(&mut self, now: DateTime<Utc>, post_window: Duration) -> Option<Turn> {
+        let nudge_after_hours = self.settings.turns.nudge_after_hours?;
+        let last_post = self
+            .posts
+            .latest_post_start(post_window)
+            .or_else(|| self.general_stats.last_message())?;
+        if self.nudged_for == Some(last_post) {
+            return None;
+        }
+        let turn = self.turn(now)?;
+        turn.next?;
+        let wait = std::cmp::max(Duration::hours(nudge_after_hours as i64), post_window);
+        if now - turn.last_post_time < wait {
+            return None;
+        }
+        self.nudged_for = Some(last_post);
+        Some(turn)
+    }
+
+    // The latest story post, which is what a stalled story is stalled at. Falls back to the
+    // latest message for channels with no posts since the timeline was added
+    fn last_story_post(&self) -> Option<(MessageId, DateTime<Utc>)> {
+        self.posts.latest().or_else(|| {
+            let last_message = self.general_stats.last_message()?;
+            Some((last_message, *self.general_stats.last_message_time()?))
+        })
+    }
+
+    // When the next stall reminder is due, [None] if reminders are off or have all been sent
+    pub fn next_reminder_due(&self) -> Option<DateTime<Utc>> {
+        let (last_message, last_message_time) = self.last_story_post()?;
+        // Anything sent before the latest message was for an earlier stall
+        let state = match self.reminder_state.stalled_at == Some(last_message) {
+            true => self.reminder_state.clone(),
+            false => ReminderState::default(),
+        };
+        self.settings.reminders.next_due(&state, last_message_time)
+    }
+
+    // Takes a reminder if nobody has posted for long enough, recording it as sent
+    fn take_due_reminder(
+        &mut self,
+        (server_id, channel_id): StoryKey,
+        now: DateTime<Utc>,
+    ) -> Option<Reminder> {
+        if self.next_reminder_due()? > now {
+            return None;
+        }
+        let (last_message, last_message_time) = self.last_story_post()?;
+        let sent = match self.reminder_state.stalled_at == Some(last_message) {
+            true => self.reminder_state.sent + 1,
+            false => 1,
+        };
+        self.reminder_state = ReminderState {
+            stalled_at: Some(last_message),
+            sent,
+            last_sent: Some(now),
+        };
+        let participants = match self.settings.reminders.ping_participants {
+            true => reminders::participants(&self.last_posts(), last_message_time),
+            false => vec![],
+        };
+        Some(Reminder {
+            server_id,
+            channel_id,
+            idle_for: now - last_message_time,
+            participants,
+            is_last: sent == MAX_REMINDERS,
+        })
+    }
+
+    /// Feeds [message] to the chapters only, for filling them back in from history
+    pub fn update_chapters(&mut self, message: &Message, owners: &CharacterOwners) {
+        let (in_character, _) = self.settings.ooc_rules.split_message(message);
+        let author = owners.credited_author(message);
+        self.chapters.update(message, author, &in_character);
+    }
+
+    // Moves what [character] posted before anyone claimed them over to [owner]
+    fn credit(&mut self, character: &str, owner: &User) {
+        let names: Vec<String> = self
+            .uncredited
+            .keys()
+            .filter(|name| name.to_lowercase() == character.to_lowercase())
+            .cloned()
+            .collect();
+        for name in names {
+            if let Some(word_stats) = self.uncredited.remove(&name) {
+                self.author_stats
+                    .entry(owner.clone())
+                    .or_default()
+                    .merge(&word_stats);
+            }
+        }
+    }
+
+    /// Adds [message] to the archive and search index only, for filling them in from history
+    pub fn archive_message(&mut self, message: &Message) {
+        if let Some(archive) = &mut self.archive {
+            let (in_character, _) = self.settings.ooc_rules.split_message(message);
+            archive.add(message, &in_character);
+            self.search_index.add(message, &in_character);
+        }
+    }
+
+    pub fn archive(&self) -> Option<&Archive> {
+        self.archive.as_ref()
+    }
+
+    /// Starts keeping story text, returning whether it already was
+    pub fn start_archiving(&mut self) -> bool {
+        let already_archiving = self.archive.is_some();
+        self.archive.get_or_insert_with(Archive::default);
+        already_archiving
+    }
+
+    /// Deletes all the story text kept, the archive and the search index, returning how many
+    /// posts were archived. [None] if archiving was already off
+    pub fn stop_archiving(&mut self) -> Option<usize> {
+        self.search_index = SearchIndex::default();
+        self.archive.take().map(|archive| archive.len())
+    }
+
+    // Learns [author]'s style from [message], if imitation is on for the channel
+    fn learn_style(&mut self, message: &Message, author: UserId) {
+        if !self.settings.imitation {
+            return;
+        }
+        let (in_character, _) = self.settings.ooc_rules.split_message(message);
+        if in_character.trim().is_empty() {
+            return;
+        }
+        self.imitation_models
+            .entry(author)
+            .or_default()
+            .learn(message.id, &in_character);
+    }
+
+    /// Turns imitation on or off, turning it off forgets everything learned in the channel
+    pub fn set_imitation(&mut self, on: bool) {
+        self.settings.imitation = on;
+        if !on {
+            self.imitation_models.clear();
+        }
+    }
+
+    /// Adds [message] to the glossary only, for posts from before there was one
+    pub fn add_to_glossary(&mut self, message: &Message) {
+        let (in_character, _) = self.settings.ooc_rules.split_message(message);
+        self.glossary.update(message, &in_character);
+    }
+
+    /// See [Chapters::insert]
+    pub fn start_chapter(
+        &mut self,
+        title: &str,
+        start: MessageId,
+    ) -> Result<Option<ReplaySpan>, String> {
+        let seen_up_to = self.general_stats.last_message();
+        self.chapters.insert(title, start, seen_up_to)
+    }
+
+    /// The chapter's stats on their own, as if it were a channel with the same stop words
+    pub fn chapter_data(&self, title: &str) -> Option<ChannelData> {
+        let chapter = self.chapters.get(title)?;
+        let mut chapter_data = ChannelData::default();
+        chapter_data.general_stats.merge(&chapter.general_stats);
+        for (author, stats) in chapter.author_stats.iter() {
+            chapter_data
+                .author_stats
+                .entry(author.clone())
+                .or_default()
+                .merge(stats);
+        }
+        chapter_data.settings.stop_words = self.settings.stop_words.clone();
+        Some(chapter_data)
+    }
+
+    /// Every channel's stats added together, as if they were one channel. Only the stop words
+    /// are kept from the settings
+    pub fn merged<'a>(channels: impl Iterator<Item = &'a ChannelData>) -> ChannelData {
+        let mut merged = ChannelData::default();
+        for channel_data in channels {
+            merged.general_stats.merge(&channel_data.general_stats);
+            merged.ooc_stats.merge(&channel_data.ooc_stats);
+            for (author, stats) in channel_data.author_stats.iter() {
+                merged
+                    .author_stats
+                    .entry(author.clone())
+                    .or_default()
+                    .merge(stats);
+            }
+            for (character, stats) in channel_data.character_stats.iter() {
+                merged
+                    .character_stats
+                    .entry(character.clone())
+                    .or_default()
+                    .merge(stats);
+            }
+            for (character, stats) in channel_data.speaker_stats.iter() {
+                merged
+                    .speaker_stats
+                    .entry(character.clone())
+                    .or_default()
+                    .merge(stats);
+            }
+            merged.glossary.merge(&channel_data.glossary);
+            merged
+                .settings
+                .stop_words
+                .extend(channel_data.settings.stop_words.iter().cloned());
+        }
+        merged
+    }
+
     pub fn get_user(&self, user: &User) -> Option<&WordStats> {
         self.author_stats.get(user)
     }
+    pub fn get_user_by_id(&self, user_id: &UserId) -> Option<&WordStats> {
+        self.author_stats
+            .iter()
+            .find(|(author, _)| &author.id == user_id)
+            .map(|(_, stats)| stats)
+    }
+
+    // Scored against the channel as a whole, see [signature_word_scores]
+    pub fn signature_word_scores(
+        &self,
+        user_id: &UserId,
+        word_filter: &WordFilter,
+    ) -> Option<HashMap<String, f64>> {
+        self.get_user_by_id(user_id).map(|stats| {
+            signature_word_scores(
+                &stats.filtered_word_frequencies(word_filter),
+                &self.general_stats.filtered_word_frequencies(word_filter),
+            )
+        })
+    }
+}
+
+/// What [ServerData::leaderboard] ranks authors by
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter)]
+pub enum LeaderboardMetric {
+    Words,
+    Messages,
+    // Distinct days with at least one message, across all the channels counted
+    ActiveDays,
+}
+impl LeaderboardMetric {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Words => "words",
+            Self::Messages => "messages",
+            Self::ActiveDays => "days",
+        }
+    }
+}
+impl FromStr for LeaderboardMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::iter().find(|metric| metric.to_str() == s) {
+            Some(metric) => Ok(metric),
+            None => Err(format!("Invalid leaderboard metric {}", s)),
+        }
+    }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ServerData {
     channels: HashMap<ChannelId, ChannelData>,
+    // Defaulted so state files from before settings existed still load
+    #[serde(default)]
+    pub settings: ServerSettings,
+    #[serde(default)]
+    goals: Vec<Goal>,
+    // Kept so milestones are only announced once, even when messages are seen again
+    #[serde(default)]
+    announced_milestones: HashSet<Milestone>,
+    // When each milestone was reached, for the digest
+    #[serde(default)]
+    milestone_log: Vec<(NaiveDate, Milestone)>,
+    #[serde(default)]
+    stories: Vec<Story>,
+    // People who don't want to be imitated, nothing is learned from them
+    #[serde(default)]
+    imitation_opt_outs: HashSet<UserId>,
+    // Posts already reposted to the starboard, so they're only reposted once
+    #[serde(default)]
+    starred: HashSet<MessageId>,
+    #[serde(default)]
+    pub character_owners: CharacterOwners,
 }
 
-impl ServerData {
-    pub fn new() -> Self {
+/// Per-server options, changed through commands and persisted alongside the stats
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerSettings {
+    pub stemming: bool,
+    pub stemming_language: Language,
+    pub stop_word_languages: Vec<Language>,
+    pub stop_words: HashSet<String>,
+    pub milestones: MilestoneSettings,
+    pub digest: DigestSettings,
+    pub starboard: StarboardSettings,
+    // Consecutive messages from one author this close together count as one post, 0 to count
+    // every message on its own
+    pub post_window_minutes: u64,
+    pub glossary: GlossaryCuration,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
         Self {
-            channels: HashMap::new(),
+            stemming: false,
+            stemming_language: Language::English,
+            stop_word_languages: vec![Language::English],
+            stop_words: HashSet::new(),
+            milestones: MilestoneSettings::default(),
+            digest: DigestSettings::default(),
+            starboard: StarboardSettings::default(),
+            post_window_minutes: 5,
+            glossary: GlossaryCuration::default(),
+        }
+    }
+}
+
+impl ServerSettings {
+    /// See [crate::posts::continues_post]
+    pub fn post_window(&self) -> Duration {
+        Duration::minutes(self.post_window_minutes as i64)
+    }
+}
+
+impl ServerData {
+    pub fn get_channel_data(&self, channel_id: &ChannelId) -> Option<&ChannelData> {
+        self.channels.get(channel_id)
+    }
+
+    /// Updates the channel's stats with [message], returning the milestones it crossed that
+    /// haven't been announced before. [None] if the channel isn't initialised
+    pub fn update_channel(
+        &mut self,
+        channel_id: &ChannelId,
+        message: &Message,
+    ) -> Option<Vec<Milestone>> {
+        // Proxied posts nobody has claimed yet don't count for anyone, so only the channel's
+        // milestones can be crossed
+        let author = self
+            .character_owners
+            .credited_author(message)
+            .cloned();
+        let milestone_author = author.as_ref().unwrap_or(&message.author);
+        let before = self.milestone_counts(channel_id, milestone_author);
+        let channel_data = self.channels.get_mut(channel_id)?;
+        channel_data.update(message, &self.character_owners);
+        if let Some(author) = &author {
+            if !self.imitation_opt_outs.contains(&author.id) {
+                channel_data.learn_style(message, author.id);
+            }
+        }
+        // Crossed milestones are logged for the digest even when there's nowhere to announce them,
+        // [Store::update_channel_data] only announces them if there is
+        let after = self.milestone_counts(channel_id, milestone_author);
+        let crossed =
+            self.settings
+                .milestones
+                .crossed(*channel_id, milestone_author.id, before, after);
+        let announced_milestones = &mut self.announced_milestones;
+        let new_milestones: Vec<Milestone> = crossed
+            .into_iter()
+            .filter(|milestone| announced_milestones.insert(*milestone))
+            .collect();
+        let reached_on = message.timestamp.naive_utc().date();
+        self.milestone_log.extend(
+            new_milestones
+                .iter()
+                .map(|milestone| (reached_on, *milestone)),
+        );
+        Some(new_milestones)
+    }
+
+    /// Counts a reaction on a post in [channel_id]. Returns the post if it's just crossed the
+    /// starboard threshold, [None] if it hasn't, it's been starred before or the channel isn't
+    /// initialised
+    pub fn add_reaction(
+        &mut self,
+        channel_id: &ChannelId,
+        message_id: MessageId,
+        emoji: &str,
+    ) -> Option<Star> {
+        let channel_data = self.channels.get_mut(channel_id)?;
+        let reactions = channel_data.reactions.add(message_id, emoji);
+        let starboard = self.settings.starboard.channel?;
+        if reactions < self.settings.starboard.threshold
+            || *channel_id == starboard
+            || !self.starred.insert(message_id)
+        {
+            return None;
+        }
+        Some(Star {
+            starboard,
+            channel_id: *channel_id,
+            message_id,
+            reactions,
+        })
+    }
+
+    pub fn remove_reaction(&mut self, channel_id: &ChannelId, message_id: MessageId, emoji: &str) {
+        if let Some(channel_data) = self.channels.get_mut(channel_id) {
+            channel_data.reactions.remove(message_id, emoji);
+        }
+    }
+
+    pub fn clear_reactions(&mut self, channel_id: &ChannelId, message_id: MessageId) {
+        if let Some(channel_data) = self.channels.get_mut(channel_id) {
+            channel_data.reactions.clear(message_id);
+        }
+    }
+
+    /// Stops anything being learned from [user_id] and forgets what has been. False if they'd
+    /// already opted out
+    pub fn opt_out_of_imitation(&mut self, user_id: UserId) -> bool {
+        for channel_data in self.channels.values_mut() {
+            channel_data.imitation_models.remove(&user_id);
+        }
+        self.imitation_opt_outs.insert(user_id)
+    }
+
+    /// False if they hadn't opted out
+    pub fn opt_in_to_imitation(&mut self, user_id: UserId) -> bool {
+        self.imitation_opt_outs.remove(&user_id)
+    }
+
+    /// What's been learned of [user_id]'s style in [channel_id], or in every channel with
+    /// imitation on if there's no channel
+    pub fn imitation_model(
+        &self,
+        user_id: UserId,
+        channel_id: Option<ChannelId>,
+    ) -> Result<MarkovModel, String> {
+        if self.imitation_opt_outs.contains(&user_id) {
+            return Err(String::from("They've opted out of being imitated"));
+        }
+        if let Some(channel_id) = channel_id {
+            match self.channels.get(&channel_id) {
+                Some(channel_data) if !channel_data.settings.imitation => {
+                    return Err(String::from("Imitation isn't on for that channel"))
+                }
+                Some(_) => {}
+                None => {
+                    return Err(String::from(
+                        "Channel not initialised, use [init-channel] to add it",
+                    ))
+                }
+            }
+        }
+        let mut model = MarkovModel::default();
+        for (_, channel_data) in self
+            .channels
+            .iter()
+            .filter(|(id, _)| channel_id.is_none() || channel_id == Some(**id))
+        {
+            if let Some(channel_model) = channel_data.imitation_models.get(&user_id) {
+                model.merge(channel_model);
+            }
+        }
+        Ok(model)
+    }
+
+    /// The search index for [channel_id], or for every channel if there's no channel
+    pub fn search_indexes(
+        &self,
+        channel_id: Option<ChannelId>,
+    ) -> Vec<(ChannelId, &SearchIndex)> {
+        self.channels
+            .iter()
+            .filter(|(id, _)| channel_id.is_none() || channel_id == Some(**id))
+            .map(|(id, channel_data)| (*id, &channel_data.search_index))
+            .collect()
+    }
+
+    fn channels_matching(&self, channel_id: Option<ChannelId>) -> Vec<&ChannelData> {
+        match channel_id {
+            Some(channel_id) => self.channels.get(&channel_id).into_iter().collect(),
+            None => self.channels.values().collect(),
+        }
+    }
+
+    /// The stats for [user_id] in [channel_id], or for everyone if there's no user, across every
+    /// channel if there's no channel. Empty if nothing has been recorded for them
+    pub fn matching_stats(
+        &self,
+        channel_id: Option<ChannelId>,
+        user_id: Option<UserId>,
+    ) -> Vec<&WordStats> {
+        self.channels_matching(channel_id)
+            .into_iter()
+            .filter_map(|channel_data| match user_id {
+                Some(user_id) => channel_data.get_user_by_id(&user_id),
+                None => Some(&channel_data.general_stats),
+            })
+            .collect()
+    }
+
+    /// Each author's words per week (Monday to Sunday) for the [weeks] weeks up to and including
+    /// the one [today] is in, for the [max_authors] who wrote the most over them. Returns the
+    /// start of each week along with the counts
+    pub fn weekly_words_by_author(
+        &self,
+        channel_id: Option<ChannelId>,
+        weeks: usize,
+        max_authors: usize,
+        today: NaiveDate,
+    ) -> (Vec<NaiveDate>, Vec<(User, Vec<usize>)>) {
+        let this_week = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let week_starts: Vec<NaiveDate> = (0..weeks)
+            .rev()
+            .map(|weeks_ago| this_week - Duration::weeks(weeks_ago as i64))
+            .collect();
+        let mut by_author: HashMap<&User, Vec<usize>> = HashMap::new();
+        for channel_data in self.channels_matching(channel_id) {
+            for (author, stats) in channel_data.author_stats.iter() {
+                let counts = by_author.entry(author).or_insert_with(|| vec![0; weeks]);
+                for (count, week_start) in counts.iter_mut().zip(week_starts.iter()) {
+                    *count += stats.words_between(*week_start, *week_start + Duration::days(6));
+                }
+            }
+        }
+        let mut series: Vec<(User, Vec<usize>)> = by_author
+            .into_iter()
+            .map(|(author, counts)| (author.clone(), counts))
+            .filter(|(_, counts)| counts.iter().any(|count| *count > 0))
+            .collect();
+        series.sort_by_key(|(_, counts)| std::cmp::Reverse(counts.iter().sum::<usize>()));
+        series.truncate(max_authors);
+        (week_starts, series)
+    }
+
+    /// Activity between [start] and [end] inclusive
+    pub fn make_digest(&self, start: NaiveDate, end: NaiveDate) -> Digest {
+        let mut channel_words: Vec<(ChannelId, usize)> = self
+            .channels
+            .iter()
+            .map(|(channel_id, channel_data)| {
+                (*channel_id, channel_data.general_stats.words_between(start, end))
+            })
+            .filter(|(_, words)| *words > 0)
+            .collect();
+        channel_words.sort_by(|(_, a), (_, b)| b.cmp(a));
+        let mut top_authors =
+            self.leaderboard(LeaderboardMetric::Words, Some((start, end)), None);
+        top_authors.truncate(5);
+        let milestones = self
+            .milestone_log
+            .iter()
+            .filter(|(reached_on, _)| start <= *reached_on && *reached_on <= end)
+            .map(|(_, milestone)| *milestone)
+            .collect();
+        Digest {
+            start,
+            end,
+            channel_words,
+            top_authors,
+            new_authors: self.new_authors(start, end),
+            milestones,
         }
     }
+
+    // Authors whose first message on the server was between [start] and [end] inclusive
+    fn new_authors(&self, start: NaiveDate, end: NaiveDate) -> Vec<User> {
+        let mut first_active_days: HashMap<&User, Option<NaiveDate>> = HashMap::new();
+        for channel_data in self.channels.values() {
+            for (author, stats) in channel_data.author_stats.iter() {
+                let first_day = stats.first_active_day();
+                let earliest = first_active_days.entry(author).or_insert(first_day);
+                *earliest = match (*earliest, first_day) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    // Unknown anywhere means they could have written something before [start]
+                    _ => None,
+                };
+            }
+        }
+        let mut new_authors: Vec<User> = first_active_days
+            .into_iter()
+            .filter(|(_, first_day)| match first_day {
+                Some(first_day) => start <= *first_day && *first_day <= end,
+                None => false,
+            })
+            .map(|(author, _)| author.clone())
+            .collect();
+        new_authors.sort_by(|a, b| a.name.cmp(&b.name));
+        new_authors
+    }
+
+    fn milestone_counts(&self, channel_id: &ChannelId, author: &User) -> MilestoneCounts {
+        let channel_data = self.channels.get(channel_id);
+        MilestoneCounts {
+            channel: channel_data.map_or(0, |data| data.general_stats.word_count),
+            author_in_channel: channel_data
+                .and_then(|data| data.get_user(author))
+                .map_or(0, |stats| stats.word_count),
+            author_on_server: self
+                .channels
+                .values()
+                .filter_map(|data| data.get_user(author))
+                .map(|stats| stats.word_count)
+                .sum(),
+        }
+    }
+
+    pub fn get_goal(&self, owner: &GoalOwner) -> Option<&Goal> {
+        self.goals.iter().find(|goal| &goal.owner == owner)
+    }
+
+    // Each owner has at most one goal, so this replaces any existing one
+    pub fn set_goal(&mut self, goal: Goal) {
+        self.remove_goal(&goal.owner);
+        self.goals.push(goal);
+    }
+
+    pub fn remove_goal(&mut self, owner: &GoalOwner) -> bool {
+        let goals_before = self.goals.len();
+        self.goals.retain(|goal| &goal.owner != owner);
+        self.goals.len() != goals_before
+    }
+
+    pub fn goal_progress(&self, goal: &Goal) -> usize {
+        match goal.owner {
+            GoalOwner::User(user_id) => self
+                .channels
+                .values()
+                .filter_map(|channel_data| channel_data.get_user_by_id(&user_id))
+                .map(|stats| stats.words_between(goal.start, goal.deadline))
+                .sum(),
+            GoalOwner::Channel(channel_id) => match self.get_channel_data(&channel_id) {
+                Some(channel_data) => channel_data
+                    .general_stats
+                    .words_between(goal.start, goal.deadline),
+                None => 0,
+            },
+        }
+    }
+
+    pub fn stories(&self) -> &[Story] {
+        &self.stories
+    }
+    pub fn get_story(&self, name: &str) -> Option<&Story> {
+        self.stories.iter().find(|story| story.is_called(name))
+    }
+    pub fn get_story_mut(&mut self, name: &str) -> Option<&mut Story> {
+        self.stories.iter_mut().find(|story| story.is_called(name))
+    }
+    // Returns false if there's already a story with that name
+    pub fn add_story(&mut self, story: Story) -> bool {
+        if self.get_story(&story.name).is_some() {
+            return false;
+        }
+        self.stories.push(story);
+        true
+    }
+    pub fn remove_story(&mut self, name: &str) -> bool {
+        let before = self.stories.len();
+        self.stories.retain(|story| !story.is_called(name));
+        self.stories.len() < before
+    }
+
+    /// Whether proxy bots are used here, going by whether any character has posted through one
+    /// or been claimed. Messages are only held back for them on servers that do
+    pub fn uses_proxies(&self) -> bool {
+        !self.character_owners.is_empty()
+            || self
+                .channels
+                .values()
+                .any(|channel_data| !channel_data.character_stats.is_empty())
+    }
+
+    /// Makes [owner] the player of [character], counting what the character posted before
+    /// anyone claimed them as [owner]'s. Returns whoever played them before, if it was
+    /// someone else
+    pub fn claim_character(&mut self, character: &str, owner: &User) -> Option<UserId> {
+        for channel_data in self.channels.values_mut() {
+            channel_data.credit(character, owner);
+        }
+        self.character_owners.claim(character, owner)
+    }
+
+    /// Characters in [channel_ids] nobody has claimed, with the last message each of them sent
+    pub fn unclaimed_characters(&self, channel_ids: &[ChannelId]) -> Vec<(String, MessageId)> {
+        let mut unclaimed: HashMap<&String, (MessageId, DateTime<Utc>)> = HashMap::new();
+        let channels = channel_ids
+            .iter()
+            .filter_map(|channel_id| self.channels.get(channel_id));
+        for channel_data in channels {
+            for (character, stats) in channel_data.character_stats.iter() {
+                if self.character_owners.owner(character).is_some() {
+                    continue;
+                }
+                if let (Some(message_id), Some(time)) =
+                    (stats.last_message(), stats.last_message_time())
+                {
+                    let latest = unclaimed.entry(character).or_insert((message_id, *time));
+                    if *time > latest.1 {
+                        *latest = (message_id, *time);
+                    }
+                }
+            }
+        }
+        unclaimed
+            .into_iter()
+            .map(|(character, (message_id, _))| (character.clone(), message_id))
+            .collect()
+    }
+
+    /// The stats for every channel in the story added together, see [ChannelData::merged]
+    pub fn story_data(&self, name: &str) -> Option<ChannelData> {
+        let story = self.get_story(name)?;
+        Some(ChannelData::merged(
+            story
+                .channels
+                .iter()
+                .filter_map(|channel_id| self.channels.get(channel_id)),
+        ))
+    }
+
     pub fn get_all_channel_ids(&self) -> Vec<ChannelId> {
         self.channels.keys().map(|x| x.clone()).collect()
     }
@@ -260,6 +1402,72 @@ impl ServerData {
         self.channels.insert(*channel_id, channel_data);
     }
 
+    /// Every author with any activity, highest first. [window] is an inclusive date range, which
+    /// only covers messages processed since daily counts were added, [None] for all time.
+    /// [channel_ids] limits it to those channels, [None] for all of them. Messages are counted
+    /// as posts, so a post split over several messages only counts once
+    pub fn leaderboard(
+        &self,
+        metric: LeaderboardMetric,
+        window: Option<(NaiveDate, NaiveDate)>,
+        channel_ids: Option<&HashSet<ChannelId>>,
+    ) -> Vec<(User, usize)> {
+        let mut totals: HashMap<&User, usize> = HashMap::new();
+        let mut active_days: HashMap<&User, HashSet<NaiveDate>> = HashMap::new();
+        let channels = self
+            .channels
+            .iter()
+            .filter(|(channel_id, _)| match channel_ids {
+                Some(channel_ids) => channel_ids.contains(channel_id),
+                None => true,
+            });
+        let post_window = self.settings.post_window();
+        for (_, channel_data) in channels {
+            let continuation_counts = match metric {
+                LeaderboardMetric::Messages => {
+                    channel_data.posts.continuation_counts(window, post_window)
+                }
+                _ => HashMap::new(),
+            };
+            for (author, stats) in channel_data.author_stats.iter() {
+                let total = totals.entry(author).or_default();
+                let continuations = || continuation_counts.get(&author.id).copied().unwrap_or(0);
+                match (metric, window) {
+                    (LeaderboardMetric::Words, Some((start, end))) => {
+                        *total += stats.words_between(start, end)
+                    }
+                    (LeaderboardMetric::Words, None) => *total += stats.word_count,
+                    (LeaderboardMetric::Messages, Some((start, end))) => {
+                        *total += stats
+                            .messages_between(start, end)
+                            .saturating_sub(continuations())
+                    }
+                    (LeaderboardMetric::Messages, None) => {
+                        *total += stats.message_count().saturating_sub(continuations())
+                    }
+                    (LeaderboardMetric::ActiveDays, _) => active_days
+                        .entry(author)
+                        .or_default()
+                        .extend(stats.active_days(window)),
+                }
+            }
+        }
+        for (author, days) in active_days {
+            totals.insert(author, days.len());
+        }
+        let mut ranked: Vec<(User, usize)> = totals
+            .into_iter()
+            .filter(|(_, total)| *total > 0)
+            .map(|(author, total)| (author.clone(), total))
+            .collect();
+        ranked.sort_by(|(a_author, a_total), (b_author, b_total)| {
+            b_total
+                .cmp(a_total)
+                .then_with(|| a_author.name.cmp(&b_author.name))
+        });
+        ranked
+    }
+
     // Returns the sorted list of channel ids for a given user.
     pub fn channel_ids_by_wordcount_for_user(&self, user: &User) -> Vec<(ChannelId, usize)> {
         // Todo: Enable -recent- word count by supporting it in stats
@@ -313,3 +1521,238 @@ impl ServerData {
         }
     }
 }
+
+#[cfg(test)]
+mod testing {
+    use crate::ooc::OocRules;
+    use crate::proxies::CharacterOwners;
+    use crate::state::{ChannelData, LeaderboardMetric, ServerData, Store};
+    use crate::utils::test_messages::{message, proxied};
+    use chrono::{Duration, TimeZone, Utc};
+    use serenity::model::id::{ChannelId, GuildId, UserId};
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn the_bots_own_messages_dont_reset_reminders() {
+        let mut channel_data = ChannelData::default();
+        channel_data.settings.reminders.remind_after_days = Some(10);
+        let owners = CharacterOwners::default();
+        let start = Utc.ymd(2026, 10, 1).and_hms(12, 0, 0);
+        let post = message(1, (10, "ana"), start, "Ana rides off into the sunset");
+        channel_data.update(&post, &owners);
+        let key = (GuildId(1), ChannelId(1));
+        let first = start + Duration::days(10);
+        assert!(channel_data.take_due_reminder(key, first).is_some());
+        // The reminder itself, seen again when catching up after a restart
+        let mut reminder = message(2, (99, "scrivener"), first, "Nobody has posted in 10 days");
+        reminder.author.bot = true;
+        channel_data.update(&reminder, &owners);
+        assert_eq!(channel_data.reminder_state.sent, 1);
+        assert_eq!(
+            channel_data.next_reminder_due(),
+            Some(first + Duration::days(20))
+        );
+        assert!(channel_data
+            .take_due_reminder(key, first + Duration::days(19))
+            .is_none());
+        assert!(channel_data
+            .take_due_reminder(key, first + Duration::days(20))
+            .is_some());
+        assert_eq!(channel_data.reminder_state.sent, 2);
+        // Someone posting again starts over
+        let post = message(3, (10, "ana"), first + Duration::days(21), "She rides back");
+        channel_data.update(&post, &owners);
+        assert_eq!(
+            channel_data.next_reminder_due(),
+            Some(first + Duration::days(31))
+        );
+    }
+
+    #[test]
+    fn milestones_are_logged_without_an_announcement_channel() {
+        let (server_id, channel_id) = (GuildId(1), ChannelId(1));
+        let mut server_data = ServerData::default();
+        server_data.settings.milestones.channel_thresholds = vec![3];
+        server_data.insert(&channel_id, ChannelData::default());
+        let mut data = HashMap::new();
+        data.insert(server_id, server_data);
+        let mut store = Store::new(data);
+        let at = Utc.ymd(2026, 10, 1).and_hms(12, 0, 0);
+        let post = message(1, (10, "ana"), at, "one two three four");
+        // There's nowhere to announce it
+        assert!(store
+            .update_channel_data(&(server_id, channel_id), &post)
+            .is_empty());
+        let server_data = store.get_server_data(&server_id).unwrap();
+        let logged: Vec<_> = server_data
+            .milestone_log
+            .iter()
+            .map(|(day, milestone)| (*day, milestone.threshold))
+            .collect();
+        assert_eq!(logged, vec![(at.naive_utc().date(), 3)]);
+    }
+
+    #[test]
+    fn digests_stay_due_until_theyre_posted() {
+        let server_id = GuildId(1);
+        let mut server_data = ServerData::default();
+        server_data.settings.digest.channel = Some(ChannelId(2));
+        let mut data = HashMap::new();
+        data.insert(server_id, server_data);
+        let mut store = Store::new(data);
+        // A Monday, after the default 9:00 UTC
+        let now = Utc.ymd(2026, 11, 2).and_hms(10, 0, 0);
+        let digests = store.due_digests(now);
+        assert_eq!(digests.len(), 1);
+        // Say sending failed, it's tried again
+        assert_eq!(store.due_digests(now + Duration::minutes(1)).len(), 1);
+        store.mark_digest_posted(&digests[0]);
+        assert!(store.due_digests(now + Duration::minutes(2)).is_empty());
+    }
+
+    #[test]
+    fn leaderboards_rank_by_each_metric_over_a_window() {
+        let mut server_data = ServerData::default();
+        for channel_id in [ChannelId(1), ChannelId(2)].iter() {
+            let mut channel_data = ChannelData::default();
+            channel_data.settings.ooc_rules = OocRules::common();
+            server_data.insert(channel_id, channel_data);
+        }
+        let (ana, bo) = ((10, "ana"), (20, "bo"));
+        let at = |day, hour, minute| Utc.ymd(2026, 10, day).and_hms(hour, minute, 0);
+        let posts = [
+            (1, ana, at(1, 12, 0), "one two three"),
+            // Carries on the post before it, so it isn't another message
+            (1, ana, at(1, 12, 1), "four five"),
+            (1, bo, at(1, 12, 2), "six"),
+            // All OOC, so not a message or a day active
+            (1, ana, at(2, 9, 0), "(( sorry, busy today ))"),
+            (1, bo, at(3, 12, 0), "seven eight"),
+            (2, ana, at(3, 14, 0), "nine ten eleven twelve"),
+            (2, bo, at(3, 15, 0), "thirteen"),
+        ];
+        for (id, (channel, author, timestamp, content)) in posts.iter().enumerate() {
+            let post = message(id as u64, *author, *timestamp, content);
+            server_data.update_channel(&ChannelId(*channel), &post);
+        }
+        let day = |day| Utc.ymd(2026, 10, day).naive_utc();
+        let ranks = |metric, window, channel_ids: Option<&HashSet<ChannelId>>| {
+            server_data
+                .leaderboard(metric, window, channel_ids)
+                .into_iter()
+                .map(|(author, total)| (author.name, total))
+                .collect::<Vec<_>>()
+        };
+        let ranked = |pairs: &[(&str, usize)]| {
+            pairs
+                .iter()
+                .map(|(name, total)| (String::from(*name), *total))
+                .collect::<Vec<_>>()
+        };
+        let after_the_first_day = Some((day(2), day(3)));
+        assert_eq!(
+            ranks(LeaderboardMetric::Words, None, None),
+            ranked(&[("ana", 9), ("bo", 4)])
+        );
+        assert_eq!(
+            ranks(LeaderboardMetric::Words, after_the_first_day, None),
+            ranked(&[("ana", 4), ("bo", 3)])
+        );
+        assert_eq!(
+            ranks(LeaderboardMetric::Messages, None, None),
+            ranked(&[("bo", 3), ("ana", 2)])
+        );
+        assert_eq!(
+            ranks(LeaderboardMetric::Messages, Some((day(1), day(1))), None),
+            ranked(&[("ana", 1), ("bo", 1)])
+        );
+        assert_eq!(
+            ranks(LeaderboardMetric::ActiveDays, None, None),
+            ranked(&[("ana", 2), ("bo", 2)])
+        );
+        assert_eq!(
+            ranks(LeaderboardMetric::ActiveDays, after_the_first_day, None),
+            ranked(&[("ana", 1), ("bo", 1)])
+        );
+        // Nobody wrote anything before the first post
+        let september = Some((Utc.ymd(2026, 9, 1).naive_utc(), Utc.ymd(2026, 9, 30).naive_utc()));
+        assert!(ranks(LeaderboardMetric::Words, september, None).is_empty());
+        let first_channel: HashSet<ChannelId> = [ChannelId(1)].iter().cloned().collect();
+        assert_eq!(
+            ranks(LeaderboardMetric::Words, None, Some(&first_channel)),
+            ranked(&[("ana", 5), ("bo", 3)])
+        );
+    }
+
+    #[test]
+    fn looked_up_words_rank_with_their_inflections() {
+        let server_id = GuildId(1);
+        let mut server_data = ServerData::default();
+        for channel_id in [ChannelId(1), ChannelId(2)].iter() {
+            server_data.insert(channel_id, ChannelData::default());
+        }
+        let at = |minute| Utc.ymd(2026, 10, 1).and_hms(12, minute, 0);
+        let posts = [
+            (1, "The road narrowed"),
+            (1, "It narrowed and narrowed"),
+            (1, "The river narrows"),
+            (1, "A road, a road, a road, a road"),
+            // Most of the "narrows" are here, but they're all the same word
+            (2, "The river narrows and narrows"),
+        ];
+        for (i, (channel, post)) in posts.iter().enumerate() {
+            let post = message(i as u64, (10, "ana"), at(i as u32), post);
+            server_data.update_channel(&ChannelId(*channel), &post);
+        }
+        let mut data = HashMap::new();
+        data.insert(server_id, server_data);
+        let mut store = Store::new(data);
+        let look_up = |store: &Store, word| {
+            store
+                .look_up_word(&server_id, None, None, word)
+                .map(|lookup| (lookup.count, lookup.rank, lookup.stop_word))
+        };
+        assert_eq!(look_up(&store, "narrows"), Some((3, Some((2, 4)), false)));
+        assert_eq!(look_up(&store, "road"), Some((5, Some((1, 4)), false)));
+        assert_eq!(look_up(&store, "the"), Some((3, None, true)));
+        store
+            .get_server_data_mut_maybe_create(&server_id)
+            .settings
+            .stemming = true;
+        // "narrows" is ranked along with "narrowed" now, across both channels, though its own
+        // count doesn't change
+        assert_eq!(look_up(&store, "narrows"), Some((3, Some((1, 3)), false)));
+        assert_eq!(look_up(&store, "river"), Some((2, Some((3, 3)), false)));
+        assert_eq!(look_up(&store, "the"), Some((3, None, true)));
+    }
+
+    #[test]
+    fn proxied_posts_count_for_whoever_plays_the_character() {
+        let channel_id = ChannelId(1);
+        let mut server_data = ServerData::default();
+        server_data.insert(&channel_id, ChannelData::default());
+        let at = |minute| Utc.ymd(2026, 10, 1).and_hms(12, minute, 0);
+        let ana = message(1, (10, "ana"), at(0), "Ana writes some narration");
+        server_data.update_channel(&channel_id, &ana);
+        assert!(!server_data.uses_proxies());
+        // Posted before anyone claims Aria, so it's held for whoever does
+        server_data.update_channel(&channel_id, &proxied(2, 99, "Aria", at(1), "one two three"));
+        assert!(server_data.uses_proxies());
+        let ana_words = |server_data: &ServerData| {
+            server_data
+                .get_channel_data(&channel_id)
+                .and_then(|data| data.get_user_by_id(&UserId(10)))
+                .map(|stats| stats.word_count)
+        };
+        assert_eq!(ana_words(&server_data), Some(4));
+        server_data.claim_character("aria", &ana.author);
+        assert_eq!(ana_words(&server_data), Some(7));
+        server_data.update_channel(&channel_id, &proxied(3, 99, "Aria", at(2), "four five"));
+        assert_eq!(ana_words(&server_data), Some(9));
+        let channel_data = server_data.get_channel_data(&channel_id).unwrap();
+        assert_eq!(channel_data.character_stats["Aria"].word_count, 5);
+        assert_eq!(channel_data.general_stats.word_count, 9);
+        // The webhook isn't an author
+        assert!(channel_data.get_user_by_id(&UserId(99)).is_none());
+    }
+}
@@ -1,42 +1,175 @@
-use chrono::{DateTime, Utc};
+use crate::sentiment::{self, SentimentTotals};
+use crate::stop_words::Language;
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc};
 use log::{debug, info};
 use serde::{Deserialize, Serialize};
 use serenity::model::channel::Message;
 use serenity::model::id::MessageId;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::iter::FromIterator;
 
+/// Options applied when reading words back out of [WordStats]. The stored frequencies are always
+/// the raw lowercased words, so these can be changed at any time without reprocessing history
+#[derive(Debug, Clone)]
+pub struct WordFilter {
+    pub stemming: bool,
+    // Which language's rules decide what counts as an inflection of what
+    pub stemming_language: Language,
+    pub stop_word_languages: Vec<Language>,
+    // Server and channel specific stop words, on top of the bundled language lists
+    pub extra_stop_words: HashSet<String>,
+}
+
+impl Default for WordFilter {
+    fn default() -> Self {
+        Self {
+            stemming: false,
+            stemming_language: Language::English,
+            stop_word_languages: vec![Language::English],
+            extra_stop_words: HashSet::new(),
+        }
+    }
+}
+
+impl WordFilter {
+    pub fn is_stop_word(&self, word: &str) -> bool {
+        self.extra_stop_words.contains(word)
+            || self
+                .stop_word_languages
+                .iter()
+                .any(|language| language.stop_words().contains(&word))
+    }
+
+    /// Whether [word] and [other] are counted as one word, either the same or (when stemming)
+    /// inflections grouped together by [WordStats::filtered_word_frequencies]
+    pub fn same_word(&self, word: &str, other: &str) -> bool {
+        if self.stemming {
+            let stemmer = self.stemming_language.stemmer();
+            stemmer.stem(word) == stemmer.stem(other)
+        } else {
+            word == other
+        }
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct WordStats {
     pub word_count: usize,
     pub word_frequencies: HashMap<String, usize>,
     last_message: Option<(MessageId, DateTime<Utc>)>,
     included_messages: HashSet<MessageId>,
+    // Only filled in for messages processed since this was added, older words have no entry
+    #[serde(default)]
+    word_uses: HashMap<String, WordUse>,
+    // Words per (UTC) day, for anything that needs counts over a period of time. Like
+    // [word_uses] this only covers messages processed since it was added
+    #[serde(default)]
+    daily_word_counts: BTreeMap<NaiveDate, usize>,
+    #[serde(default)]
+    daily_message_counts: BTreeMap<NaiveDate, usize>,
+    // Words by (UTC) weekday from Monday, then hour
+    #[serde(default)]
+    weekday_hour_words: [[usize; 24]; 7],
+    // Sentiment per (UTC) day, only for messages processed since this was added that have
+    // some words that carry sentiment
+    #[serde(default)]
+    daily_sentiment: BTreeMap<NaiveDate, SentimentTotals>,
+    // Words in quotes and words outside them, only for messages processed since these were
+    // added, so the two don't have to add up to [word_count]
+    #[serde(default)]
+    dialogue_word_count: usize,
+    #[serde(default)]
+    narration_word_count: usize,
+    #[serde(default)]
+    dialogue_word_frequencies: HashMap<String, usize>,
+}
+
+/// The first and last message a word was seen in
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WordUse {
+    pub first: (MessageId, DateTime<Utc>),
+    pub last: (MessageId, DateTime<Utc>),
+}
+
+impl WordUse {
+    fn new(message_id: MessageId, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            first: (message_id, timestamp),
+            last: (message_id, timestamp),
+        }
+    }
+
+    // Messages arrive out of order when backfilling, so this can move either end
+    fn update(&mut self, message_id: MessageId, timestamp: DateTime<Utc>) {
+        if timestamp < self.first.1 {
+            self.first = (message_id, timestamp);
+        }
+        if timestamp > self.last.1 {
+            self.last = (message_id, timestamp);
+        }
+    }
 }
 
 impl WordStats {
-    pub fn new_from_message(message: &Message) -> Self {
+    pub fn new_from_message(message: &Message, content: &str) -> Self {
         let mut t = Self::default();
-        t.update(message);
+        t.update(message, content);
         t
     }
-    pub fn update(&mut self, message: &Message) {
+    // [content] is the part of the message to count, which can be less than [message.content]
+    // when some of it has been picked out as out-of-character
+    pub fn update(&mut self, message: &Message, content: &str) {
         if !self.included_messages.contains(&message.id) {
             debug!("Wordstats update. message: {:?}", message);
-            let words = crate::language_parsing::tokenise(&message.content);
+            let words = crate::language_parsing::tokenise_with_quotes(content);
             debug!("Parsed {} words from message {}", words.len(), message.id);
             //self.word_count += words.len();
-            for word_ in words {
+            let mut message_word_count = 0;
+            for (word_, in_quote) in words {
                 let word = word_.to_lowercase().to_string();
                 if has_at_least_one_letter(&word) {
+                    match self.word_uses.get_mut(&word) {
+                        Some(word_use) => word_use.update(message.id, message.timestamp),
+                        None => {
+                            let word_use = WordUse::new(message.id, message.timestamp);
+                            self.word_uses.insert(word.clone(), word_use);
+                        }
+                    }
+                    if in_quote {
+                        self.dialogue_word_count += 1;
+                        *self
+                            .dialogue_word_frequencies
+                            .entry(word.clone())
+                            .or_insert(0) += 1;
+                    } else {
+                        self.narration_word_count += 1;
+                    }
                     if let Some(existing_count) = self.word_frequencies.get_mut(&word) {
                         *existing_count += 1;
                     } else {
                         self.word_frequencies.insert(word, 1);
                     }
-                    self.word_count += 1;
+                    message_word_count += 1;
                 }
             }
+            self.word_count += message_word_count;
+            *self
+                .daily_word_counts
+                .entry(message.timestamp.naive_utc().date())
+                .or_insert(0) += message_word_count;
+            *self
+                .daily_message_counts
+                .entry(message.timestamp.naive_utc().date())
+                .or_insert(0) += 1;
+            let weekday = message.timestamp.weekday().num_days_from_monday() as usize;
+            self.weekday_hour_words[weekday][message.timestamp.hour() as usize] +=
+                message_word_count;
+            if let Some(score) = sentiment::score(content) {
+                self.daily_sentiment
+                    .entry(message.timestamp.naive_utc().date())
+                    .or_default()
+                    .add(score);
+            }
             self.included_messages.insert(message.id);
             let should_update_last_message = match self.last_message {
                 None => true,
@@ -53,41 +186,38 @@ impl WordStats {
         }
     }
 
-    pub fn top_words(&self, n: usize) -> String {
-        // This is a bit gross considering the possible size of [word_frequencies] but this is due
-        // a major overhaul and that HashMap will be replaced by some efficient Summary type soon
-        // and this whole function will need redoing then anyway
-        let mut word_vec = Vec::from_iter(self.word_frequencies.iter());
-        word_vec.sort_by_key(|(_, count)| *count);
-        let mut sorted_words: Vec<String> = word_vec
-            .iter()
-            .filter_map(|(word, _)| {
-                // That ref ref deref deref deref is ... ugly ...
-                if is_valid_word(*word) {
-                    Some((*word).clone())
-                } else {
-                    None
-                }
-            })
-            .collect();
-        sorted_words.reverse();
-        let result_len = if sorted_words.len() < n {
-            sorted_words.len()
-        } else {
-            n
-        };
-        let top_words = sorted_words.get(0..result_len).unwrap();
-        top_words.join(", ")
+    pub fn top_words(&self, n: usize, filter: &WordFilter) -> String {
+        top_n_words(self.filtered_word_frequencies(filter), n)
     }
 
-    pub fn filtered_word_frequencies(&self) -> HashMap<String, usize> {
-        let mut out = HashMap::new();
-        for (word, count) in self.word_frequencies.iter() {
-            if is_valid_word(word) {
-                out.insert(word.clone(), *count);
-            }
+    /// [top_words] counting only words in quotes
+    pub fn top_dialogue_words(&self, n: usize, filter: &WordFilter) -> String {
+        top_n_words(
+            filter_word_frequencies(&self.dialogue_word_frequencies, filter),
+            n,
+        )
+    }
+
+    /// The share of words in quotes, out of the words that have been sorted into dialogue or
+    /// narration. [None] if none have
+    pub fn dialogue_ratio(&self) -> Option<f64> {
+        let classified = self.dialogue_word_count + self.narration_word_count;
+        match classified {
+            0 => None,
+            _ => Some(self.dialogue_word_count as f64 / classified as f64),
         }
-        out
+    }
+
+    pub fn dialogue_word_count(&self) -> usize {
+        self.dialogue_word_count
+    }
+
+    pub fn narration_word_count(&self) -> usize {
+        self.narration_word_count
+    }
+
+    pub fn filtered_word_frequencies(&self, filter: &WordFilter) -> HashMap<String, usize> {
+        filter_word_frequencies(&self.word_frequencies, filter)
     }
 
     pub fn last_message(&self) -> Option<MessageId> {
@@ -99,210 +229,427 @@ impl WordStats {
             Some((_, date)) => Some(date),
         }
     }
+
+    pub fn word_use(&self, word: &str) -> Option<&WordUse> {
+        self.word_uses.get(word)
+    }
+
+    // Inclusive of both [start] and [end]
+    pub fn words_between(&self, start: NaiveDate, end: NaiveDate) -> usize {
+        if start > end {
+            return 0;
+        }
+        self.daily_word_counts
+            .range(start..=end)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    pub fn daily_word_counts(&self) -> &BTreeMap<NaiveDate, usize> {
+        &self.daily_word_counts
+    }
+
+    // Words from before daily counts were added, which can't be placed on any day
+    pub fn untracked_word_count(&self) -> usize {
+        let tracked_words: usize = self.daily_word_counts.values().sum();
+        self.word_count.saturating_sub(tracked_words)
+    }
+
+    pub fn daily_sentiment(&self) -> &BTreeMap<NaiveDate, SentimentTotals> {
+        &self.daily_sentiment
+    }
+
+    pub fn sentiment(&self) -> SentimentTotals {
+        let mut totals = SentimentTotals::default();
+        for day_totals in self.daily_sentiment.values() {
+            totals.merge(day_totals);
+        }
+        totals
+    }
+
+    pub fn weekday_hour_words(&self) -> &[[usize; 24]; 7] {
+        &self.weekday_hour_words
+    }
+
+    pub fn message_count(&self) -> usize {
+        self.included_messages.len()
+    }
+
+    // Inclusive of both [start] and [end]
+    pub fn messages_between(&self, start: NaiveDate, end: NaiveDate) -> usize {
+        if start > end {
+            return 0;
+        }
+        self.daily_message_counts
+            .range(start..=end)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    // [None] when some messages predate the daily counts, since then it can't be known
+    pub fn first_active_day(&self) -> Option<NaiveDate> {
+        let tracked_messages: usize = self.daily_message_counts.values().sum();
+        if tracked_messages < self.included_messages.len() {
+            return None;
+        }
+        self.daily_message_counts.keys().next().cloned()
+    }
+
+    // Days with at least one message, [None] for all of them
+    pub fn active_days(&self, window: Option<(NaiveDate, NaiveDate)>) -> Vec<NaiveDate> {
+        match window {
+            Some((start, end)) if start > end => vec![],
+            Some((start, end)) => self
+                .daily_message_counts
+                .range(start..=end)
+                .map(|(day, _)| *day)
+                .collect(),
+            None => self.daily_message_counts.keys().cloned().collect(),
+        }
+    }
+
+    /// Adds [other]'s counts into these, to see several channels as one
+    pub fn merge(&mut self, other: &WordStats) {
+        self.word_count += other.word_count;
+        for (word, count) in other.word_frequencies.iter() {
+            *self.word_frequencies.entry(word.clone()).or_default() += count;
+        }
+        self.last_message = match (self.last_message, other.last_message) {
+            (Some(ours), Some(theirs)) if theirs.1 > ours.1 => Some(theirs),
+            (None, theirs) => theirs,
+            (ours, _) => ours,
+        };
+        self.included_messages
+            .extend(other.included_messages.iter().cloned());
+        for (word, other_use) in other.word_uses.iter() {
+            match self.word_uses.get_mut(word) {
+                Some(word_use) => {
+                    word_use.update(other_use.first.0, other_use.first.1);
+                    word_use.update(other_use.last.0, other_use.last.1);
+                }
+                None => {
+                    self.word_uses.insert(word.clone(), *other_use);
+                }
+            }
+        }
+        for (day, count) in other.daily_word_counts.iter() {
+            *self.daily_word_counts.entry(*day).or_default() += count;
+        }
+        for (day, count) in other.daily_message_counts.iter() {
+            *self.daily_message_counts.entry(*day).or_default() += count;
+        }
+        self.dialogue_word_count += other.dialogue_word_count;
+        self.narration_word_count += other.narration_word_count;
+        for (word, count) in other.dialogue_word_frequencies.iter() {
+            *self.dialogue_word_frequencies.entry(word.clone()).or_default() += count;
+        }
+        for (day, totals) in other.daily_sentiment.iter() {
+            self.daily_sentiment.entry(*day).or_default().merge(totals);
+        }
+        for (day, hours) in other.weekday_hour_words.iter().enumerate() {
+            for (hour, words) in hours.iter().enumerate() {
+                self.weekday_hour_words[day][hour] += words;
+            }
+        }
+    }
+}
+
+/// Scores how distinctive each of an author's words are against a baseline (their channel or
+/// server, which includes the author themselves) with Dunning's log-likelihood. Only words the
+/// author uses proportionally more than everyone else are scored, so common words drop out
+pub fn signature_word_scores(
+    author_frequencies: &HashMap<String, usize>,
+    baseline_frequencies: &HashMap<String, usize>,
+) -> HashMap<String, f64> {
+    let author_total: usize = author_frequencies.values().sum();
+    let baseline_total: usize = baseline_frequencies.values().sum();
+    let rest_total = baseline_total.saturating_sub(author_total) as f64;
+    let author_total = author_total as f64;
+    let mut scores = HashMap::new();
+    if author_total == 0.0 || rest_total == 0.0 {
+        // Nobody else to compare against, so nothing is distinctive
+        return scores;
+    }
+    for (word, count) in author_frequencies.iter() {
+        let a = *count as f64;
+        let b = baseline_frequencies
+            .get(word)
+            .map_or(0, |c| c.saturating_sub(*count)) as f64;
+        if a / author_total <= b / rest_total {
+            continue;
+        }
+        let expected_a = author_total * (a + b) / (author_total + rest_total);
+        let expected_b = rest_total * (a + b) / (author_total + rest_total);
+        let mut log_likelihood = a * (a / expected_a).ln();
+        if b > 0.0 {
+            log_likelihood += b * (b / expected_b).ln();
+        }
+        scores.insert(word.clone(), 2.0 * log_likelihood);
+    }
+    scores
+}
+
+pub fn top_scored_words(scores: &HashMap<String, f64>, n: usize) -> String {
+    let mut word_vec = Vec::from_iter(scores.iter());
+    word_vec.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    word_vec
+        .iter()
+        .take(n)
+        .map(|(word, _)| word.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+// The most frequent [n] words, most frequent first
+fn top_n_words(word_frequencies: HashMap<String, usize>, n: usize) -> String {
+    // This is a bit gross considering the possible size of [word_frequencies] but this is due
+    // a major overhaul and that HashMap will be replaced by some efficient Summary type soon
+    // and this whole function will need redoing then anyway
+    let mut word_vec = Vec::from_iter(word_frequencies);
+    word_vec.sort_by_key(|(_, count)| *count);
+    let mut sorted_words: Vec<String> = word_vec.into_iter().map(|(word, _)| word).collect();
+    sorted_words.reverse();
+    let result_len = if sorted_words.len() < n {
+        sorted_words.len()
+    } else {
+        n
+    };
+    let top_words = sorted_words.get(0..result_len).unwrap();
+    top_words.join(", ")
+}
+
+fn filter_word_frequencies(
+    word_frequencies: &HashMap<String, usize>,
+    filter: &WordFilter,
+) -> HashMap<String, usize> {
+    let mut out = HashMap::new();
+    for (word, count) in word_frequencies.iter() {
+        if is_valid_word(word, filter) {
+            out.insert(word.clone(), *count);
+        }
+    }
+    if filter.stemming {
+        group_inflections(out, filter.stemming_language)
+    } else {
+        out
+    }
+}
+
+// Groups inflections sharing a stem in [language] ("narrow", "narrows", "narrowed") into one
+// entry, keyed by the most common surface form so what we display is still a real word
+fn group_inflections(
+    word_frequencies: HashMap<String, usize>,
+    language: Language,
+) -> HashMap<String, usize> {
+    let stemmer = language.stemmer();
+    // stem -> (total count, most common surface form, count of that surface form)
+    let mut groups: HashMap<String, (usize, String, usize)> = HashMap::new();
+    for (word, count) in word_frequencies {
+        let stem = stemmer.stem(&word).into_owned();
+        match groups.get_mut(&stem) {
+            Some((total, surface, surface_count)) => {
+                *total += count;
+                // Tie-break on the word itself so the chosen form doesn't depend on HashMap order
+                if count > *surface_count || (count == *surface_count && word < *surface) {
+                    *surface = word;
+                    *surface_count = count;
+                }
+            }
+            None => {
+                groups.insert(stem, (count, word, count));
+            }
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(_stem, (total, surface, _))| (surface, total))
+        .collect()
 }
 
-fn is_valid_word(word: &str) -> bool {
-    has_at_least_one_letter(word) && is_not_stop_word(word)
+fn is_valid_word(word: &str, filter: &WordFilter) -> bool {
+    has_at_least_one_letter(word) && !filter.is_stop_word(word)
 }
 fn has_at_least_one_letter(word: &str) -> bool {
     word.contains(|c| 'a' < c && c < 'z')
 }
-fn is_not_stop_word(word: &str) -> bool {
-    !STOP_WORDS.contains(&word)
-}
 
-// From: https://github.com/amueller/word_cloud/blob/master/wordcloud/stopwords
-const STOP_WORDS: [&str; 192] = [
-    "a",
-    "about",
-    "above",
-    "after",
-    "again",
-    "against",
-    "all",
-    "also",
-    "am",
-    "an",
-    "and",
-    "any",
-    "are",
-    "aren't",
-    "as",
-    "at",
-    "be",
-    "because",
-    "been",
-    "before",
-    "being",
-    "below",
-    "between",
-    "both",
-    "but",
-    "by",
-    "can",
-    "can't",
-    "cannot",
-    "com",
-    "could",
-    "couldn't",
-    "did",
-    "didn't",
-    "do",
-    "does",
-    "doesn't",
-    "doing",
-    "don't",
-    "down",
-    "during",
-    "each",
-    "else",
-    "ever",
-    "few",
-    "for",
-    "from",
-    "further",
-    "get",
-    "had",
-    "hadn't",
-    "has",
-    "hasn't",
-    "have",
-    "haven't",
-    "having",
-    "he",
-    "he'd",
-    "he'll",
-    "he's",
-    "hence",
-    "her",
-    "here",
-    "here's",
-    "hers",
-    "herself",
-    "him",
-    "himself",
-    "his",
-    "how",
-    "how's",
-    "however",
-    "http",
-    "i",
-    "i'd",
-    "i'll",
-    "i'm",
-    "i've",
-    "if",
-    "in",
-    "into",
-    "is",
-    "isn't",
-    "it",
-    "it's",
-    "its",
-    "itself",
-    "just",
-    "k",
-    "let's",
-    "like",
-    "me",
-    "more",
-    "most",
-    "mustn't",
-    "my",
-    "myself",
-    "no",
-    "nor",
-    "not",
-    "of",
-    "off",
-    "on",
-    "once",
-    "only",
-    "or",
-    "other",
-    "otherwise",
-    "ought",
-    "our",
-    "ours",
-    "ourselves",
-    "out",
-    "over",
-    "own",
-    "r",
-    "same",
-    "shall",
-    "shan't",
-    "she",
-    "she'd",
-    "she'll",
-    "she's",
-    "should",
-    "shouldn't",
-    "since",
-    "so",
-    "some",
-    "such",
-    "than",
-    "that",
-    "that's",
-    "the",
-    "their",
-    "theirs",
-    "them",
-    "themselves",
-    "then",
-    "there",
-    "there's",
-    "therefore",
-    "these",
-    "they",
-    "they'd",
-    "they'll",
-    "they're",
-    "they've",
-    "this",
-    "those",
-    "through",
-    "to",
-    "too",
-    "under",
-    "until",
-    "up",
-    "very",
-    "was",
-    "wasn't",
-    "we",
-    "we'd",
-    "we'll",
-    "we're",
-    "we've",
-    "were",
-    "weren't",
-    "what",
-    "what's",
-    "when",
-    "when's",
-    "where",
-    "where's",
-    "which",
-    "while",
-    "who",
-    "who's",
-    "whom",
-    "why",
-    "why's",
-    "with",
-    "won't",
-    "would",
-    "wouldn't",
-    "www",
-    "you",
-    "you'd",
-    "you'll",
-    "you're",
-    "you've",
-    "your",
-    "yours",
-    "yourself",
-    "yourselves",
-];
+#[cfg(test)]
+mod testing {
+    use crate::stats::{signature_word_scores, top_scored_words, WordFilter, WordStats};
+    use crate::stop_words::Language;
+    use crate::utils::test_messages::message;
+    use chrono::{TimeZone, Utc};
+    use serenity::model::id::MessageId;
+    use std::collections::HashMap;
+
+    fn make_stats() -> WordStats {
+        let mut stats = WordStats::default();
+        for (word, count) in [
+            ("eye", 3),
+            ("eyes", 5),
+            ("narrow", 1),
+            ("narrows", 2),
+            ("narrowed", 4),
+            ("the", 10),
+        ]
+        .iter()
+        {
+            stats.word_frequencies.insert(String::from(*word), *count);
+        }
+        stats
+    }
+
+    #[test]
+    fn no_stemming_keeps_inflections_apart() {
+        let stats = make_stats();
+        let freqs = stats.filtered_word_frequencies(&WordFilter::default());
+        assert_eq!(freqs.len(), 5);
+        assert_eq!(freqs.get("eye"), Some(&3));
+        assert_eq!(freqs.get("the"), None);
+    }
+
+    #[test]
+    fn stemming_groups_inflections_under_most_common_form() {
+        let stats = make_stats();
+        let filter = WordFilter {
+            stemming: true,
+            ..WordFilter::default()
+        };
+        let freqs = stats.filtered_word_frequencies(&filter);
+        let mut expected = HashMap::new();
+        expected.insert(String::from("eyes"), 8);
+        expected.insert(String::from("narrowed"), 7);
+        assert_eq!(freqs, expected);
+        assert_eq!(stats.top_words(10, &filter), "eyes, narrowed");
+    }
+
+    #[test]
+    fn stemming_uses_the_servers_language() {
+        let mut stats = WordStats::default();
+        for (word, count) in [("chante", 3), ("chantait", 2), ("chanter", 1)].iter() {
+            stats.word_frequencies.insert(String::from(*word), *count);
+        }
+        let mut filter = WordFilter {
+            stemming: true,
+            ..WordFilter::default()
+        };
+        assert_eq!(stats.filtered_word_frequencies(&filter).len(), 3);
+        assert!(!filter.same_word("chante", "chantait"));
+        filter.stemming_language = Language::French;
+        let mut expected = HashMap::new();
+        expected.insert(String::from("chante"), 6);
+        assert_eq!(stats.filtered_word_frequencies(&filter), expected);
+        assert!(filter.same_word("chante", "chantait"));
+    }
+
+    #[test]
+    fn extra_stop_words_and_languages() {
+        let mut stats = make_stats();
+        stats.word_frequencies.insert(String::from("lol"), 4);
+        stats.word_frequencies.insert(String::from("und"), 2);
+        let mut filter = WordFilter::default();
+        assert_eq!(
+            stats.filtered_word_frequencies(&filter).get("lol"),
+            Some(&4)
+        );
+        assert_eq!(
+            stats.filtered_word_frequencies(&filter).get("und"),
+            Some(&2)
+        );
+        filter.extra_stop_words.insert(String::from("lol"));
+        filter.stop_word_languages.push(Language::German);
+        let freqs = stats.filtered_word_frequencies(&filter);
+        assert_eq!(freqs.get("lol"), None);
+        assert_eq!(freqs.get("und"), None);
+        assert_eq!(freqs.get("eyes"), Some(&5));
+    }
+
+    #[test]
+    fn signature_words_prefer_distinctive_words() {
+        let mut author = HashMap::new();
+        author.insert(String::from("drone"), 5);
+        author.insert(String::from("walked"), 5);
+        let mut baseline = author.clone();
+        *baseline.get_mut("walked").unwrap() += 50;
+        baseline.insert(String::from("sword"), 40);
+        let scores = signature_word_scores(&author, &baseline);
+        assert!(scores.contains_key("drone"));
+        assert!(!scores.contains_key("walked"));
+        assert_eq!(top_scored_words(&scores, 10), "drone");
+    }
+
+    #[test]
+    fn signature_words_need_someone_to_compare_against() {
+        let mut author = HashMap::new();
+        author.insert(String::from("drone"), 5);
+        let scores = signature_word_scores(&author, &author.clone());
+        assert!(scores.is_empty());
+    }
+
+    #[test]
+    fn merging_adds_counts_together() {
+        let mut merged = WordStats {
+            word_count: 25,
+            ..make_stats()
+        };
+        let mut other = WordStats {
+            word_count: 3,
+            ..WordStats::default()
+        };
+        other.word_frequencies.insert(String::from("eye"), 2);
+        other.word_frequencies.insert(String::from("sword"), 1);
+        merged.merge(&other);
+        assert_eq!(merged.word_count, 28);
+        assert_eq!(merged.word_frequencies.get("eye"), Some(&5));
+        assert_eq!(merged.word_frequencies.get("sword"), Some(&1));
+        assert_eq!(merged.word_frequencies.get("the"), Some(&10));
+    }
+
+    #[test]
+    fn dialogue_is_counted_apart_from_narration() {
+        let mut stats = WordStats::default();
+        assert_eq!(stats.dialogue_ratio(), None);
+        let mut other = WordStats {
+            dialogue_word_count: 3,
+            narration_word_count: 9,
+            ..WordStats::default()
+        };
+        for (word, count) in [("the", 1), ("run", 2)].iter() {
+            other
+                .dialogue_word_frequencies
+                .insert(String::from(*word), *count);
+        }
+        stats.merge(&other);
+        stats.merge(&other);
+        assert_eq!(stats.dialogue_ratio(), Some(0.25));
+        assert_eq!(stats.top_dialogue_words(10, &WordFilter::default()), "run");
+    }
+
+    #[test]
+    fn words_in_quotes_are_dialogue() {
+        let at = Utc.ymd(2026, 10, 1).and_hms(12, 0, 0);
+        let content = "\"Run,\" she said. “Now, before it wakes.” They ran\n\"Unclosed quotes end with the line\nSo this is narration";
+        let mut stats = WordStats::default();
+        stats.update(&message(1, (10, "ana"), at, content), content);
+        // Run, Now before it wakes, Unclosed quotes end with the line
+        assert_eq!(stats.dialogue_word_count(), 11);
+        // she said, They ran, So this is narration
+        assert_eq!(stats.narration_word_count(), 8);
+        assert_eq!(stats.word_count, 19);
+    }
+
+    #[test]
+    fn word_uses_cover_the_first_and_last_message() {
+        let at = |minute| Utc.ymd(2026, 10, 1).and_hms(12, minute, 0);
+        let mut stats = WordStats::default();
+        // Backfilled newest first, then a live message
+        stats.update(&message(2, (10, "ana"), at(2), "The drone hums"), "The drone hums");
+        stats.update(&message(1, (10, "ana"), at(1), "A drone"), "A drone");
+        stats.update(&message(3, (10, "ana"), at(3), "Drones"), "Drones");
+        let drone = stats.word_use("drone").unwrap();
+        assert_eq!(drone.first, (MessageId(1), at(1)));
+        assert_eq!(drone.last, (MessageId(2), at(2)));
+        assert_eq!(stats.word_use("hums").unwrap().first.0, MessageId(2));
+        assert!(stats.word_use("sword").is_none());
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use serenity::framework::standard::Args;
+use serenity::model::id::ChannelId;
+
+/// A named group of channels that make up one story, e.g. one per arc plus an OOC channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Story {
+    pub name: String,
+    pub channels: Vec<ChannelId>,
+}
+
+impl Story {
+    // Names are matched ignoring case, so "fall of rome" finds "Fall of Rome"
+    pub fn is_called(&self, name: &str) -> bool {
+        self.name.to_lowercase() == name.to_lowercase()
+    }
+}
+
+/// What a stats command is about, one channel, every channel in a story, or one chapter of a
+/// channel
+#[derive(Debug, Clone)]
+pub enum StatsTarget {
+    Channel(ChannelId),
+    Story(String),
+    Chapter(ChannelId, String),
+}
+
+impl StatsTarget {
+    /// Takes a #channel mention from [args], or failing that a story name (in quotes if it has
+    /// spaces)
+    pub fn single(args: &mut Args) -> Result<Self, String> {
+        if let Ok(channel_id) = args.single::<ChannelId>() {
+            return Ok(Self::Channel(channel_id));
+        }
+        args.single_quoted::<String>()
+            .map(Self::Story)
+            .map_err(|_| String::from("Expected a channel or a story name"))
+    }
+}
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, GuildId, UserId};
+
+// Without a set rotation, it's everyone who has posted this recently
+const LEARNED_ROTATION_DAYS: i64 = 30;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurnSettings {
+    // Empty to learn the rotation from who's been posting
+    pub rotation: Vec<UserId>,
+    // DM the next writer once it's been their turn this long, [None] to never nudge
+    pub nudge_after_hours: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Turn {
+    pub last_poster: UserId,
+    pub last_post_time: DateTime<Utc>,
+    // [None] when there's nobody else in the rotation
+    pub next: Option<UserId>,
+}
+
+/// A DM to send to [Turn::next], reminding them it's their turn in the channel
+#[derive(Debug)]
+pub struct Nudge {
+    pub server_id: GuildId,
+    pub channel_id: ChannelId,
+    pub turn: Turn,
+}
+
+impl TurnSettings {
+    /// Works out whose turn it is from when each author last posted. With a set rotation it's
+    /// whoever comes after the last poster, otherwise it's whoever has waited the longest
+    pub fn turn(&self, last_posts: &[(UserId, DateTime<Utc>)], now: DateTime<Utc>) -> Option<Turn> {
+        let (last_poster, last_post_time) = *last_posts.iter().max_by_key(|(_, time)| *time)?;
+        let rotation_index = self
+            .rotation
+            .iter()
+            .position(|user_id| *user_id == last_poster);
+        let next = match rotation_index {
+            Some(index) if self.rotation.len() > 1 => {
+                Some(self.rotation[(index + 1) % self.rotation.len()])
+            }
+            Some(_) => None,
+            None => {
+                let last_post_of = |user_id: &UserId| {
+                    last_posts
+                        .iter()
+                        .find(|(author, _)| author == user_id)
+                        .map(|(_, time)| *time)
+                };
+                let waiting: Vec<(UserId, Option<DateTime<Utc>>)> = match self.rotation.is_empty() {
+                    true => last_posts
+                        .iter()
+                        .filter(|(_, time)| now - *time <= Duration::days(LEARNED_ROTATION_DAYS))
+                        .map(|(user_id, time)| (*user_id, Some(*time)))
+                        .collect(),
+                    // The last poster isn't in the rotation, so anyone in it could be up
+                    false => self
+                        .rotation
+                        .iter()
+                        .map(|user_id| (*user_id, last_post_of(user_id)))
+                        .collect(),
+                };
+                // Never having posted counts as waiting the longest
+                waiting
+                    .into_iter()
+                    .filter(|(user_id, _)| *user_id != last_poster)
+                    .min_by_key(|(_, time)| *time)
+                    .map(|(user_id, _)| user_id)
+            }
+        };
+        Some(Turn {
+            last_poster,
+            last_post_time,
+            next,
+        })
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::turns::{Turn, TurnSettings};
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use serenity::model::id::UserId;
+
+    fn now() -> DateTime<Utc> {
+        Utc.ymd(2026, 11, 1).and_hms(12, 0, 0)
+    }
+
+    fn last_posts() -> Vec<(UserId, DateTime<Utc>)> {
+        vec![
+            (UserId(1), now() - Duration::hours(30)),
+            (UserId(2), now() - Duration::hours(20)),
+            (UserId(3), now() - Duration::hours(10)),
+            // Hasn't posted in long enough to have dropped out
+            (UserId(4), now() - Duration::days(60)),
+        ]
+    }
+
+    #[test]
+    fn learned_rotation_picks_who_waited_longest() {
+        let turn = TurnSettings::default().turn(&last_posts(), now());
+        assert_eq!(
+            turn,
+            Some(Turn {
+                last_poster: UserId(3),
+                last_post_time: now() - Duration::hours(10),
+                next: Some(UserId(1)),
+            })
+        );
+        assert_eq!(TurnSettings::default().turn(&[], now()), None);
+    }
+
+    #[test]
+    fn set_rotation_follows_its_order() {
+        let mut settings = TurnSettings {
+            rotation: vec![UserId(3), UserId(2), UserId(1)],
+            nudge_after_hours: None,
+        };
+        let turn = settings.turn(&last_posts(), now()).unwrap();
+        assert_eq!(turn.next, Some(UserId(2)));
+        // Someone outside the rotation posted last, so it's whoever in it waited longest
+        settings.rotation = vec![UserId(5), UserId(1)];
+        let turn = settings.turn(&last_posts(), now()).unwrap();
+        assert_eq!(turn.next, Some(UserId(5)));
+    }
+}
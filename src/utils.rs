@@ -1,6 +1,60 @@
 use serenity::utils::MessageBuilder;
 
 pub mod helpers {
+    use serenity::model::id::{ChannelId, GuildId, MessageId};
+
+    pub fn message_link(
+        server_id: GuildId,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> String {
+        format!(
+            "https://discord.com/channels/{}/{}/{}",
+            server_id, channel_id, message_id
+        )
+    }
+
+    // Roughly how long [duration] is, in its biggest whole unit, e.g. "3 days"
+    pub fn describe_duration(duration: chrono::Duration) -> String {
+        let (amount, unit) = if duration.num_days() > 0 {
+            (duration.num_days(), "day")
+        } else if duration.num_hours() > 0 {
+            (duration.num_hours(), "hour")
+        } else {
+            (duration.num_minutes().max(0), "minute")
+        };
+        match amount {
+            1 => format!("1 {}", unit),
+            _ => format!("{} {}s", amount, unit),
+        }
+    }
+
+    pub fn parse_on_off(s: &str) -> Result<bool, String> {
+        match s {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            _ => Err(format!("Expected on or off, got {}", s)),
+        }
+    }
+
+    // Splits [text] into messages of at most [limit] characters, between lines where it can.
+    // Lines that are too long on their own are cut short
+    pub fn split_into_messages(text: &str, limit: usize) -> Vec<String> {
+        let mut messages = vec![];
+        let mut message = String::new();
+        for line in text.split_inclusive('\n') {
+            let line: String = line.chars().take(limit).collect();
+            if message.chars().count() + line.chars().count() > limit {
+                messages.push(std::mem::take(&mut message));
+            }
+            message.push_str(&line);
+        }
+        if !message.is_empty() {
+            messages.push(message);
+        }
+        messages
+    }
+
     pub fn strip_leading_trailing(s: &str, c: char) -> &str {
         let prefix_stripped: &str = match s.strip_prefix(c) {
             Some(stripped) => stripped,
@@ -174,6 +228,61 @@ pub mod trait_extensions {
     }
 }
 
+/// Messages built the way Discord sends them, for feeding through stats in tests
+#[cfg(test)]
+pub mod test_messages {
+    use chrono::{DateTime, Utc};
+    use serde_json::json;
+    use serenity::model::channel::Message;
+
+    pub fn message(
+        id: u64,
+        author: (u64, &str),
+        timestamp: DateTime<Utc>,
+        content: &str,
+    ) -> Message {
+        let (author_id, author_name) = author;
+        serde_json::from_value(json!({
+            "id": id.to_string(),
+            "channel_id": "1",
+            "guild_id": "1",
+            "author": {
+                "id": author_id.to_string(),
+                "username": author_name,
+                "discriminator": "0001",
+                "avatar": null,
+                "bot": false,
+            },
+            "content": content,
+            "timestamp": timestamp.to_rfc3339(),
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "attachments": [],
+            "embeds": [],
+            "pinned": false,
+            "type": 0,
+        }))
+        .unwrap()
+    }
+
+    /// A message from a proxy bot, posted through webhook [webhook_id] as [character]
+    pub fn proxied(
+        id: u64,
+        webhook_id: u64,
+        character: &str,
+        timestamp: DateTime<Utc>,
+        content: &str,
+    ) -> Message {
+        let mut message = message(id, (webhook_id, character), timestamp, content);
+        message.author.bot = true;
+        message.webhook_id = Some(webhook_id.into());
+        message
+    }
+}
+
 #[cfg(test)]
 mod test_iter {
     use std::collections::HashMap;